@@ -0,0 +1,426 @@
+//! A backend chosen at runtime, for tools that accept both HDF5 files and Zarr
+//! stores.
+//!
+//! ```no_run
+//! use anndata::{AnnData, AnnDataOp, Backend};
+//! use anndata_any::{AnyBackend, DynAnnData};
+//!
+//! let adata: DynAnnData = AnnData::open(AnyBackend::open("data.h5ad")?)?;
+//! println!("{} x {}", adata.n_obs(), adata.n_vars());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anndata::{
+    backend::*,
+    data::{DynArray, SelectInfoElem, Shape},
+    AnnData,
+};
+use anndata_hdf5::H5;
+use anndata_zarr::Zarr;
+
+use anyhow::{bail, Result};
+use ndarray::{Array, CowArray, Dimension};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// Backend dispatching to [`H5`] or [`Zarr`], depending on the file.
+pub struct AnyBackend;
+
+/// AnnData backed by either an HDF5 file or a Zarr store.
+pub type DynAnnData = AnnData<AnyBackend>;
+
+pub enum AnyStore {
+    H5(<H5 as Backend>::Store),
+    Zarr(<Zarr as Backend>::Store),
+}
+
+pub enum AnyGroup {
+    H5(<H5 as Backend>::Group),
+    Zarr(<Zarr as Backend>::Group),
+}
+
+pub enum AnyDataset {
+    H5(<H5 as Backend>::Dataset),
+    Zarr(<Zarr as Backend>::Dataset),
+}
+
+/// Apply the same expression to either variant, optionally wrapping the
+/// result in the corresponding variant of another enum.
+macro_rules! dispatch {
+    ($value:expr, $x:ident => $body:expr) => {
+        match $value {
+            Self::H5($x) => $body,
+            Self::Zarr($x) => $body,
+        }
+    };
+    ($value:expr, $x:ident => $body:expr, $wrap:ident) => {
+        match $value {
+            Self::H5($x) => $body.map($wrap::H5),
+            Self::Zarr($x) => $body.map($wrap::Zarr),
+        }
+    };
+}
+
+/// Returns the name of the backend able to read the file: [`Zarr::NAME`] for
+/// directories and zip archives, and [`H5::NAME`] for HDF5 files.
+pub fn detect_backend<P: AsRef<Path>>(path: P) -> Result<&'static str> {
+    let path = path.as_ref();
+    if path.is_dir() {
+        return Ok(Zarr::NAME);
+    }
+    let mut file = File::open(path)?;
+    let mut signature = [0u8; 8];
+    if file.read_exact(&mut signature[..4]).is_ok() && is_zip_signature(&signature[..4]) {
+        return Ok(Zarr::NAME);
+    }
+    // The HDF5 superblock is at offset 0, 512, 1024, 2048, etc., depending
+    // on the size of the user block.
+    let len = file.metadata()?.len();
+    let mut offset = 0;
+    while offset + 8 <= len {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut signature)?;
+        if signature == HDF5_SIGNATURE {
+            return Ok(H5::NAME);
+        }
+        offset = if offset == 0 { 512 } else { offset * 2 };
+    }
+    bail!(
+        "'{}' is neither an HDF5 file nor a Zarr store",
+        path.display()
+    )
+}
+
+const HDF5_SIGNATURE: [u8; 8] = [0x89, b'H', b'D', b'F', b'\r', b'\n', 0x1a, b'\n'];
+
+fn is_zip_signature(bytes: &[u8]) -> bool {
+    bytes == b"PK\x03\x04" || bytes == b"PK\x05\x06"
+}
+
+impl Backend for AnyBackend {
+    const NAME: &'static str = "any";
+
+    type Store = AnyStore;
+
+    type Group = AnyGroup;
+
+    type Dataset = AnyDataset;
+
+    /// Create a Zarr store if the path ends with `.zarr`, `.zarrs` or `.zip`,
+    /// and an HDF5 file otherwise.
+    fn new<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        let is_zarr = path
+            .as_ref()
+            .extension()
+            .is_some_and(|ext| ext == "zarr" || ext == "zarrs" || ext == "zip");
+        if is_zarr {
+            Zarr::new(path).map(AnyStore::Zarr)
+        } else {
+            H5::new(path).map(AnyStore::H5)
+        }
+    }
+
+    /// Opens a file as read-only, file must exist.
+    fn open<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        if detect_backend(&path)? == Zarr::NAME {
+            Zarr::open(path).map(AnyStore::Zarr)
+        } else {
+            H5::open(path).map(AnyStore::H5)
+        }
+    }
+
+    /// Opens a file as read/write, file must exist.
+    fn open_rw<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        if detect_backend(&path)? == Zarr::NAME {
+            Zarr::open_rw(path).map(AnyStore::Zarr)
+        } else {
+            H5::open_rw(path).map(AnyStore::H5)
+        }
+    }
+}
+
+impl AnyStore {
+    /// Returns the name of the underlying backend.
+    pub fn backend(&self) -> &'static str {
+        match self {
+            AnyStore::H5(_) => H5::NAME,
+            AnyStore::Zarr(_) => Zarr::NAME,
+        }
+    }
+}
+
+impl StoreOp<AnyBackend> for AnyStore {
+    fn filename(&self) -> PathBuf {
+        dispatch!(self, x => x.filename())
+    }
+
+    fn close(self) -> Result<()> {
+        dispatch!(self, x => x.close())
+    }
+
+    fn flush(&self) -> Result<()> {
+        dispatch!(self, x => x.flush())
+    }
+
+    fn is_swmr_write(&self) -> Result<bool> {
+        dispatch!(self, x => x.is_swmr_write())
+    }
+
+    fn start_swmr_write(&self) -> Result<()> {
+        dispatch!(self, x => x.start_swmr_write())
+    }
+}
+
+impl GroupOp<AnyBackend> for AnyStore {
+    fn list(&self) -> Result<Vec<String>> {
+        dispatch!(self, x => x.list())
+    }
+
+    fn new_group(&self, name: &str) -> Result<AnyGroup> {
+        dispatch!(self, x => x.new_group(name), AnyGroup)
+    }
+
+    fn open_group(&self, name: &str) -> Result<AnyGroup> {
+        dispatch!(self, x => x.open_group(name), AnyGroup)
+    }
+
+    fn new_empty_dataset<T: BackendData>(
+        &self,
+        name: &str,
+        shape: &Shape,
+        config: WriteConfig,
+    ) -> Result<AnyDataset> {
+        dispatch!(self, x => x.new_empty_dataset::<T>(name, shape, config), AnyDataset)
+    }
+
+    fn open_dataset(&self, name: &str) -> Result<AnyDataset> {
+        dispatch!(self, x => x.open_dataset(name), AnyDataset)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        dispatch!(self, x => x.delete(name))
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        dispatch!(self, x => x.rename(from, to))
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        dispatch!(self, x => x.exists(name))
+    }
+
+    fn new_array_dataset<'a, D, Dim>(
+        &self,
+        name: &str,
+        arr: CowArray<'a, D, Dim>,
+        config: WriteConfig,
+    ) -> Result<AnyDataset>
+    where
+        D: BackendData,
+        Dim: Dimension,
+    {
+        dispatch!(self, x => x.new_array_dataset(name, arr, config), AnyDataset)
+    }
+
+    fn new_scalar_dataset<D: BackendData>(&self, name: &str, data: &D) -> Result<AnyDataset> {
+        dispatch!(self, x => x.new_scalar_dataset(name, data), AnyDataset)
+    }
+}
+
+impl GroupOp<AnyBackend> for AnyGroup {
+    fn list(&self) -> Result<Vec<String>> {
+        dispatch!(self, x => x.list())
+    }
+
+    fn new_group(&self, name: &str) -> Result<AnyGroup> {
+        dispatch!(self, x => x.new_group(name), AnyGroup)
+    }
+
+    fn open_group(&self, name: &str) -> Result<AnyGroup> {
+        dispatch!(self, x => x.open_group(name), AnyGroup)
+    }
+
+    fn new_empty_dataset<T: BackendData>(
+        &self,
+        name: &str,
+        shape: &Shape,
+        config: WriteConfig,
+    ) -> Result<AnyDataset> {
+        dispatch!(self, x => x.new_empty_dataset::<T>(name, shape, config), AnyDataset)
+    }
+
+    fn open_dataset(&self, name: &str) -> Result<AnyDataset> {
+        dispatch!(self, x => x.open_dataset(name), AnyDataset)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        dispatch!(self, x => x.delete(name))
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        dispatch!(self, x => x.rename(from, to))
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        dispatch!(self, x => x.exists(name))
+    }
+
+    fn new_array_dataset<'a, D, Dim>(
+        &self,
+        name: &str,
+        arr: CowArray<'a, D, Dim>,
+        config: WriteConfig,
+    ) -> Result<AnyDataset>
+    where
+        D: BackendData,
+        Dim: Dimension,
+    {
+        dispatch!(self, x => x.new_array_dataset(name, arr, config), AnyDataset)
+    }
+
+    fn new_scalar_dataset<D: BackendData>(&self, name: &str, data: &D) -> Result<AnyDataset> {
+        dispatch!(self, x => x.new_scalar_dataset(name, data), AnyDataset)
+    }
+}
+
+impl AttributeOp<AnyBackend> for AnyGroup {
+    fn store(&self) -> Result<AnyStore> {
+        dispatch!(self, x => x.store(), AnyStore)
+    }
+
+    fn path(&self) -> PathBuf {
+        dispatch!(self, x => x.path())
+    }
+
+    fn new_json_attr(&mut self, name: &str, value: &Value) -> Result<()> {
+        dispatch!(self, x => x.new_json_attr(name, value))
+    }
+
+    fn get_json_attr(&self, name: &str) -> Result<Value> {
+        dispatch!(self, x => x.get_json_attr(name))
+    }
+
+    fn has_attr(&self, name: &str) -> Result<bool> {
+        dispatch!(self, x => x.has_attr(name))
+    }
+
+    fn delete_attr(&mut self, name: &str) -> Result<()> {
+        dispatch!(self, x => x.delete_attr(name))
+    }
+}
+
+impl AttributeOp<AnyBackend> for AnyDataset {
+    fn store(&self) -> Result<AnyStore> {
+        dispatch!(self, x => x.store(), AnyStore)
+    }
+
+    fn path(&self) -> PathBuf {
+        dispatch!(self, x => x.path())
+    }
+
+    fn new_json_attr(&mut self, name: &str, value: &Value) -> Result<()> {
+        dispatch!(self, x => x.new_json_attr(name, value))
+    }
+
+    fn get_json_attr(&self, name: &str) -> Result<Value> {
+        dispatch!(self, x => x.get_json_attr(name))
+    }
+
+    fn has_attr(&self, name: &str) -> Result<bool> {
+        dispatch!(self, x => x.has_attr(name))
+    }
+
+    fn delete_attr(&mut self, name: &str) -> Result<()> {
+        dispatch!(self, x => x.delete_attr(name))
+    }
+}
+
+impl DatasetOp<AnyBackend> for AnyDataset {
+    fn dtype(&self) -> Result<ScalarType> {
+        dispatch!(self, x => x.dtype())
+    }
+
+    fn shape(&self) -> Shape {
+        dispatch!(self, x => x.shape())
+    }
+
+    fn reshape(&mut self, shape: &Shape) -> Result<()> {
+        dispatch!(self, x => x.reshape(shape))
+    }
+
+    fn write_array_slice<S, T, D>(&self, arr: CowArray<'_, T, D>, selection: &[S]) -> Result<()>
+    where
+        T: BackendData,
+        S: AsRef<SelectInfoElem>,
+        D: Dimension,
+    {
+        dispatch!(self, x => x.write_array_slice(arr, selection))
+    }
+
+    fn read_array_slice<T: BackendData, S, D>(&self, selection: &[S]) -> Result<Array<T, D>>
+    where
+        S: AsRef<SelectInfoElem>,
+        D: Dimension,
+    {
+        dispatch!(self, x => x.read_array_slice(selection))
+    }
+
+    fn is_resizable(&self) -> Result<bool> {
+        dispatch!(self, x => x.is_resizable())
+    }
+
+    fn refresh(&self) -> Result<()> {
+        dispatch!(self, x => x.refresh())
+    }
+
+    fn record_fields(&self) -> Result<Option<Vec<String>>> {
+        dispatch!(self, x => x.record_fields())
+    }
+
+    fn read_record_field(&self, name: &str) -> Result<DynArray> {
+        dispatch!(self, x => x.read_record_field(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anndata::{AnnDataOp, ArrayElemOp};
+    use ndarray::{array, Array2};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_detect_backend() -> Result<()> {
+        let dir = tempdir()?;
+        let x = array![[1, 2, 3], [4, 5, 6]];
+        for (name, backend) in [
+            ("data.h5ad", H5::NAME),
+            ("data.zarr", Zarr::NAME),
+            ("data.zarr.zip", Zarr::NAME),
+        ] {
+            let path = dir.path().join(name);
+            let adata = DynAnnData::new(&path)?;
+            adata.set_x(x.clone())?;
+            adata.close()?;
+
+            // The content decides, not the extension.
+            let renamed = dir.path().join(format!("renamed_{}", name.replace('.', "_")));
+            std::fs::rename(&path, &renamed)?;
+            assert_eq!(detect_backend(&renamed)?, backend);
+            let store = AnyBackend::open(&renamed)?;
+            assert_eq!(store.backend(), backend);
+            let adata = DynAnnData::open(store)?;
+            let data: Array2<i32> = adata.x().get()?.unwrap();
+            assert_eq!(data, x);
+            adata.close()?;
+        }
+
+        let path = dir.path().join("data.txt");
+        std::fs::write(&path, "not anndata")?;
+        assert!(detect_backend(&path).is_err());
+        Ok(())
+    }
+}
@@ -3,13 +3,14 @@ use anndata::{
     data::{DynArray, DynCowArray, DynScalar, SelectInfoBounds, SelectInfoElem, SelectInfoElemBounds, Shape},
 };
 
-use anyhow::{bail, Ok, Result};
+use anyhow::{bail, ensure, Context, Ok, Result};
 use hdf5::{
     dataset::Dataset,
     types::IntSize::*,
-    types::{FloatSize, TypeDescriptor, VarLenUnicode},
+    types::{CompoundField, CompoundType, FloatSize, TypeDescriptor, VarLenAscii, VarLenUnicode},
     File, Group, H5Type, Location, Selection,
 };
+use hdf5::plist::file_create::FileSpaceStrategy;
 use ndarray::{Array, ArrayD, ArrayView, CowArray, Dimension, IxDyn, SliceInfo, SliceInfoElem};
 use std::ops::Deref;
 use std::ops::Index;
@@ -77,6 +78,166 @@ impl Backend for H5 {
     }
 }
 
+/// Options for creating and opening HDF5 files.
+#[derive(Debug, Clone, Default)]
+pub struct H5Options {
+    /// Raw data chunk cache used by every dataset of the file. HDF5 uses
+    /// [`ChunkCache::default`] if not set.
+    pub chunk_cache: Option<ChunkCache>,
+    /// Size in bytes of the page buffer, which caches whole file-space pages of
+    /// metadata and raw data. Page buffering is only available for files created
+    /// with `file_space_page_size`.
+    pub page_buffer_size: Option<usize>,
+    /// Use paged aggregation of the file space with pages of the given size in
+    /// bytes. Only used when creating a file.
+    pub file_space_page_size: Option<u64>,
+}
+
+/// Parameters of the HDF5 raw data chunk cache.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkCache {
+    /// Number of chunk slots in the hash table. Should be a prime number about
+    /// 100 times the number of chunks that fit in the cache.
+    pub nslots: usize,
+    /// Total size of the cache in bytes.
+    pub nbytes: usize,
+    /// Preemption policy between 0 and 1. Fully read or written chunks are
+    /// evicted first when set to 1.
+    pub w0: f64,
+}
+
+impl Default for ChunkCache {
+    fn default() -> Self {
+        Self {
+            nslots: 521,
+            nbytes: 1024 * 1024,
+            w0: 0.75,
+        }
+    }
+}
+
+impl H5Options {
+    fn builder(&self) -> hdf5::FileBuilder {
+        let mut builder = File::with_options();
+        builder.with_fapl(|p| {
+            if let Some(cache) = self.chunk_cache {
+                p.chunk_cache(cache.nslots, cache.nbytes, cache.w0);
+            }
+            if let Some(size) = self.page_buffer_size {
+                p.page_buffer_size(size, 0, 0);
+            }
+            p
+        });
+        if let Some(size) = self.file_space_page_size {
+            builder.with_fcpl(|p| {
+                p.file_space_strategy(FileSpaceStrategy::FreeSpaceManager {
+                    paged: true,
+                    persist: false,
+                    threshold: 1,
+                })
+                .file_space_page_size(size)
+            });
+        }
+        builder
+    }
+}
+
+impl H5 {
+    /// Create a new file with the given options.
+    pub fn new_with<P: AsRef<Path>>(path: P, options: &H5Options) -> Result<H5File> {
+        Ok(H5File(options.builder().create(path)?))
+    }
+
+    /// Open a file as read-only with the given options, file must exist.
+    pub fn open_with<P: AsRef<Path>>(path: P, options: &H5Options) -> Result<H5File> {
+        Ok(H5File(options.builder().open(path)?))
+    }
+
+    /// Open a file as read/write with the given options, file must exist.
+    pub fn open_rw_with<P: AsRef<Path>>(path: P, options: &H5Options) -> Result<H5File> {
+        Ok(H5File(options.builder().open_rw(path)?))
+    }
+}
+
+/// Single-writer/multiple-reader (SWMR) access.
+///
+/// A file written in SWMR mode can be read by other processes while it is
+/// being modified. The writer creates the file with [`H5::new_swmr`], builds
+/// the layout of groups, datasets and attributes, and then calls
+/// [`StoreOp::start_swmr_write`]. From that point on, HDF5 only allows writing
+/// to and resizing existing datasets; creating or deleting objects and
+/// attributes fails. Readers open the file with [`H5::open_swmr`] and call
+/// [`DatasetOp::refresh`] to observe the latest shape and content of a
+/// growing dataset.
+///
+/// At the AnnData level, the writer creates an empty `X` with
+/// `AnnData::new_empty_x`, starts SWMR writing with `AnnData::start_swmr_write`
+/// and grows `X` with `AnnData::append_x_rows`. Readers follow the new rows
+/// with `AnnData::refresh`.
+impl H5 {
+    /// Create a new file using the latest file format, which is required by
+    /// SWMR writing.
+    pub fn new_swmr<P: AsRef<Path>>(path: P) -> Result<H5File> {
+        Self::new_swmr_with(path, &H5Options::default())
+    }
+
+    /// Create a new file for SWMR writing with the given options.
+    pub fn new_swmr_with<P: AsRef<Path>>(path: P, options: &H5Options) -> Result<H5File> {
+        let file = options
+            .builder()
+            .with_fapl(|p| p.libver_latest())
+            .create(path)?;
+        Ok(H5File(file))
+    }
+
+    /// Open an existing file as read/write using the latest file format, so
+    /// that SWMR writing can be started with [`StoreOp::start_swmr_write`].
+    pub fn open_rw_swmr<P: AsRef<Path>>(path: P) -> Result<H5File> {
+        Self::open_rw_swmr_with(path, &H5Options::default())
+    }
+
+    /// Open an existing file for SWMR writing with the given options.
+    pub fn open_rw_swmr_with<P: AsRef<Path>>(path: P, options: &H5Options) -> Result<H5File> {
+        let file = options
+            .builder()
+            .with_fapl(|p| p.libver_latest())
+            .open_rw(path)?;
+        Ok(H5File(file))
+    }
+
+    /// Open a file as a SWMR reader. The file can be written by another
+    /// process at the same time.
+    pub fn open_swmr<P: AsRef<Path>>(path: P) -> Result<H5File> {
+        Self::open_swmr_with(path, &H5Options::default())
+    }
+
+    /// Open a file as a SWMR reader with the given options.
+    pub fn open_swmr_with<P: AsRef<Path>>(path: P, options: &H5Options) -> Result<H5File> {
+        use hdf5_sys::h5f;
+
+        let path = path.as_ref();
+        let filename = std::ffi::CString::new(path.to_string_lossy().as_bytes())?;
+        // The builder cannot pass the SWMR flag, so only its file access
+        // properties are used.
+        let mut builder = options.builder();
+        let fapl = builder.fapl().libver_latest().finish()?;
+        let id = {
+            let _lock = hdf5_sys::LOCK.lock();
+            unsafe {
+                h5f::H5Fopen(
+                    filename.as_ptr(),
+                    h5f::H5F_ACC_RDONLY | h5f::H5F_ACC_SWMR_READ,
+                    fapl.id(),
+                )
+            }
+        };
+        if id < 0 {
+            bail!("Cannot open {} as a SWMR reader", path.display());
+        }
+        Ok(H5File(hdf5::from_id(id)?))
+    }
+}
+
 impl StoreOp<H5> for H5File {
     fn filename(&self) -> PathBuf {
         hdf5::Location::filename(&self).into()
@@ -85,6 +246,35 @@ impl StoreOp<H5> for H5File {
     fn close(self) -> Result<()> {
         Ok(self.0.close()?)
     }
+
+    fn flush(&self) -> Result<()> {
+        Ok(self.0.flush()?)
+    }
+
+    fn is_swmr_write(&self) -> Result<bool> {
+        use hdf5_sys::h5f;
+
+        let mut intent = 0;
+        let status = {
+            let _lock = hdf5_sys::LOCK.lock();
+            unsafe { h5f::H5Fget_intent(self.id(), &mut intent) }
+        };
+        ensure!(status >= 0, "cannot get the intent of {}", self.filename().display());
+        Ok(intent & h5f::H5F_ACC_SWMR_WRITE != 0)
+    }
+
+    /// The file must have been created or opened with [`H5::new_swmr`] or
+    /// [`H5::open_rw_swmr`].
+    fn start_swmr_write(&self) -> Result<()> {
+        let status = {
+            let _lock = hdf5_sys::LOCK.lock();
+            unsafe { hdf5_sys::h5f::H5Fstart_swmr_write(self.id()) }
+        };
+        if status < 0 {
+            bail!("Cannot start SWMR writing for {}", self.filename().display());
+        }
+        Ok(())
+    }
 }
 
 // Generic GroupOp implementations
@@ -117,19 +307,40 @@ fn new_dataset<T: BackendData>(
         ScalarType::I16 => group.new_dataset::<i16>(),
         ScalarType::I32 => group.new_dataset::<i32>(),
         ScalarType::I64 => group.new_dataset::<i64>(),
+        ScalarType::F16 => group.new_dataset::<f16>(),
         ScalarType::F32 => group.new_dataset::<f32>(),
         ScalarType::F64 => group.new_dataset::<f64>(),
+        ScalarType::Complex32 => group.new_dataset::<Complex32>(),
+        ScalarType::Complex64 => group.new_dataset::<Complex64>(),
         ScalarType::Bool => group.new_dataset::<bool>(),
         ScalarType::String => group.new_dataset::<VarLenUnicode>(),
     };
 
+    if config.layout == Layout::Contiguous {
+        let s: hdf5::Extents = hdf5::SimpleExtents::fixed(shape.as_ref()).into();
+        return Ok(H5Dataset(builder.shape(s).create(name)?));
+    }
+
     builder = if let Some(compression) = config.compression {
         match compression {
             Compression::Gzip(lvl) => builder.deflate(lvl),
             Compression::Zst(lvl) => match dtype {
                 ScalarType::String => builder.deflate(3),
-                _ => builder.blosc_zstd(lvl, hdf5::filters::BloscShuffle::Byte),
-            }
+                _ => builder.blosc_zstd(lvl, blosc_shuffle(config.shuffle)),
+            },
+            Compression::Blosc(compressor, lvl) => match dtype {
+                ScalarType::String => builder.deflate(3),
+                _ => {
+                    let complib = match compressor {
+                        BloscCompressor::BloscLz => hdf5::filters::Blosc::BloscLZ,
+                        BloscCompressor::Lz4 => hdf5::filters::Blosc::LZ4,
+                        BloscCompressor::Lz4hc => hdf5::filters::Blosc::LZ4HC,
+                        BloscCompressor::Zlib => hdf5::filters::Blosc::ZLib,
+                        BloscCompressor::Zstd => hdf5::filters::Blosc::ZStd,
+                    };
+                    builder.blosc(complib, lvl, blosc_shuffle(config.shuffle))
+                }
+            },
         }
     } else {
         builder
@@ -150,6 +361,14 @@ fn new_dataset<T: BackendData>(
     Ok(H5Dataset(dataset))
 }
 
+fn blosc_shuffle(shuffle: Shuffle) -> hdf5::filters::BloscShuffle {
+    match shuffle {
+        Shuffle::None => hdf5::filters::BloscShuffle::None,
+        Shuffle::Byte => hdf5::filters::BloscShuffle::Byte,
+        Shuffle::Bit => hdf5::filters::BloscShuffle::Bit,
+    }
+}
+
 fn open_dataset(group: &Group, name: &str) -> Result<H5Dataset> {
     Ok(H5Dataset(group.dataset(name)?))
 }
@@ -158,6 +377,10 @@ fn delete(group: &Group, name: &str) -> Result<()> {
     Ok(group.unlink(name)?)
 }
 
+fn rename(group: &Group, from: &str, to: &str) -> Result<()> {
+    Ok(group.relink(from, to)?)
+}
+
 fn exists(group: &Group, name: &str) -> Result<bool> {
     Ok(group.link_exists(name))
 }
@@ -214,6 +437,21 @@ fn create_scalar_data<D: BackendData>(group: &Group, name: &str, data: &D) -> Re
             dataset.write_scalar(&x)?;
             Ok(dataset)
         }
+        DynScalar::F16(x) => {
+            let dataset = group.new_dataset::<f16>().create(name)?;
+            dataset.write_scalar(&x)?;
+            Ok(dataset)
+        }
+        DynScalar::Complex32(x) => {
+            let dataset = group.new_dataset::<Complex32>().create(name)?;
+            dataset.write_scalar(&x)?;
+            Ok(dataset)
+        }
+        DynScalar::Complex64(x) => {
+            let dataset = group.new_dataset::<Complex64>().create(name)?;
+            dataset.write_scalar(&x)?;
+            Ok(dataset)
+        }
         DynScalar::Bool(x) => {
             let dataset = group.new_dataset::<bool>().create(name)?;
             dataset.write_scalar(&x)?;
@@ -239,8 +477,11 @@ impl DatasetOp<H5> for H5Dataset {
             TypeDescriptor::Integer(U2) => ScalarType::I16,
             TypeDescriptor::Integer(U4) => ScalarType::I32,
             TypeDescriptor::Integer(U8) => ScalarType::I64,
+            TypeDescriptor::Float(FloatSize::U2) => ScalarType::F16,
             TypeDescriptor::Float(FloatSize::U4) => ScalarType::F32,
             TypeDescriptor::Float(FloatSize::U8) => ScalarType::F64,
+            TypeDescriptor::Compound(ty) if is_complex(&ty, FloatSize::U4) => ScalarType::Complex32,
+            TypeDescriptor::Compound(ty) if is_complex(&ty, FloatSize::U8) => ScalarType::Complex64,
             TypeDescriptor::Boolean => ScalarType::Bool,
             TypeDescriptor::VarLenAscii => ScalarType::String,
             TypeDescriptor::VarLenUnicode => ScalarType::String,
@@ -257,6 +498,99 @@ impl DatasetOp<H5> for H5Dataset {
         Ok(Dataset::resize(self, shape.as_ref())?)
     }
 
+    fn is_resizable(&self) -> Result<bool> {
+        Ok(self.space()?.is_resizable())
+    }
+
+    fn refresh(&self) -> Result<()> {
+        let status = {
+            let _lock = hdf5_sys::LOCK.lock();
+            unsafe { hdf5_sys::h5d::H5Drefresh(self.id()) }
+        };
+        if status < 0 {
+            bail!("Cannot refresh dataset {}", self.name());
+        }
+        Ok(())
+    }
+
+    fn record_fields(&self) -> Result<Option<Vec<String>>> {
+        match hdf5::Container::dtype(self)?.to_descriptor()? {
+            TypeDescriptor::Compound(ty)
+                if !is_complex(&ty, FloatSize::U4) && !is_complex(&ty, FloatSize::U8) =>
+            {
+                Ok(Some(ty.fields.into_iter().map(|x| x.name).collect()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn read_record_field(&self, name: &str) -> Result<DynArray> {
+        let field = match hdf5::Container::dtype(self)?.to_descriptor()? {
+            TypeDescriptor::Compound(ty) => ty
+                .fields
+                .into_iter()
+                .find(|x| x.name == name)
+                .with_context(|| format!("record array has no field named '{}'", name))?,
+            _ => bail!("cannot read field '{}': the dataset is not a record array", name),
+        };
+        let (ty, width) = match field.ty {
+            TypeDescriptor::FixedArray(ty, n) => (*ty, Some(n)),
+            ty => (ty, None),
+        };
+        let len = self.deref().size();
+        let shape = match width {
+            Some(n) => vec![len, n],
+            None => vec![len],
+        };
+
+        macro_rules! read_field {
+            ($ty:ty) => {{
+                let member = match width {
+                    Some(n) => TypeDescriptor::FixedArray(Box::new(<$ty>::type_descriptor()), n),
+                    None => <$ty>::type_descriptor(),
+                };
+                let values = read_compound_field::<$ty>(self, name, member)?;
+                ArrayD::from_shape_vec(shape, values)?
+            }};
+        }
+
+        let array: DynArray = match ty {
+            TypeDescriptor::Integer(_) => read_field!(i64).into(),
+            TypeDescriptor::Unsigned(_) => read_field!(u64).into(),
+            TypeDescriptor::Float(FloatSize::U4) => read_field!(f32).into(),
+            TypeDescriptor::Float(_) => read_field!(f64).into(),
+            TypeDescriptor::Boolean => read_field!(bool).into(),
+            TypeDescriptor::VarLenUnicode | TypeDescriptor::VarLenAscii if width.is_some() => {
+                bail!("field '{}' holds arrays of strings, which are not supported", name)
+            }
+            TypeDescriptor::VarLenUnicode => {
+                ArrayD::from_shape_vec(shape, read_compound_string_field::<VarLenUnicode>(self, name)?)?
+                    .into()
+            }
+            TypeDescriptor::VarLenAscii => {
+                ArrayD::from_shape_vec(shape, read_compound_string_field::<VarLenAscii>(self, name)?)?
+                    .into()
+            }
+            TypeDescriptor::FixedAscii(n) | TypeDescriptor::FixedUnicode(n) => {
+                if width.is_some() {
+                    bail!("field '{}' holds arrays of strings, which are not supported", name);
+                }
+                let bytes = read_compound_field::<u8>(self, name, ty)?;
+                bytes
+                    .chunks(n)
+                    .map(|x| {
+                        let end = x.iter().position(|c| *c == 0).unwrap_or(x.len());
+                        String::from_utf8_lossy(&x[..end]).into_owned()
+                    })
+                    .collect::<ndarray::Array1<_>>()
+                    .into_dyn()
+                    .into()
+            }
+            ty => bail!("field '{}' has unsupported type: {}", name, ty),
+        };
+        Ok(array)
+    }
+
     fn read_scalar<T: BackendData>(&self) -> Result<T> {
         let val = match T::DTYPE {
             ScalarType::Bool => self.deref().read_scalar::<bool>()?.into_dyn(),
@@ -269,7 +603,10 @@ impl DatasetOp<H5> for H5Dataset {
             ScalarType::I32 => self.deref().read_scalar::<i32>()?.into_dyn(),
             ScalarType::I64 => self.deref().read_scalar::<i64>()?.into_dyn(),
             ScalarType::F32 => self.deref().read_scalar::<f32>()?.into_dyn(),
+            ScalarType::F16 => self.deref().read_scalar::<f16>()?.into_dyn(),
             ScalarType::F64 => self.deref().read_scalar::<f64>()?.into_dyn(),
+            ScalarType::Complex32 => self.deref().read_scalar::<Complex32>()?.into_dyn(),
+            ScalarType::Complex64 => self.deref().read_scalar::<Complex64>()?.into_dyn(),
             ScalarType::String => {
                 let s = self.deref().read_scalar::<VarLenUnicode>()?;
                 s.to_string().into_dyn()
@@ -355,7 +692,10 @@ impl DatasetOp<H5> for H5Dataset {
             ScalarType::U32 => read_arr::<u32, _, D>(self, selection)?.into(),
             ScalarType::U64 => read_arr::<u64, _, D>(self, selection)?.into(),
             ScalarType::F32 => read_arr::<f32, _, D>(self, selection)?.into(),
+            ScalarType::F16 => read_arr::<f16, _, D>(self, selection)?.into(),
             ScalarType::F64 => read_arr::<f64, _, D>(self, selection)?.into(),
+            ScalarType::Complex32 => read_arr::<Complex32, _, D>(self, selection)?.into(),
+            ScalarType::Complex64 => read_arr::<Complex64, _, D>(self, selection)?.into(),
             ScalarType::Bool => read_arr::<bool, _, D>(self, selection)?.into(),
             ScalarType::String => {
                 if selection.as_ref().iter().any(|x| x.as_ref().is_index()) {
@@ -419,7 +759,10 @@ impl DatasetOp<H5> for H5Dataset {
             DynCowArray::I32(x) => write_array_impl(self, x, selection),
             DynCowArray::I64(x) => write_array_impl(self, x, selection),
             DynCowArray::F32(x) => write_array_impl(self, x, selection),
+            DynCowArray::F16(x) => write_array_impl(self, x, selection),
             DynCowArray::F64(x) => write_array_impl(self, x, selection),
+            DynCowArray::Complex32(x) => write_array_impl(self, x, selection),
+            DynCowArray::Complex64(x) => write_array_impl(self, x, selection),
             DynCowArray::Bool(x) => write_array_impl(self, x, selection),
             DynCowArray::String(x) => {
                 let data: Array<VarLenUnicode, _> = x.map(|x| x.parse().unwrap());
@@ -473,6 +816,10 @@ impl GroupOp<H5> for H5File {
         delete(self, name)
     }
 
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        rename(self, from, to)
+    }
+
     fn exists(&self, name: &str) -> Result<bool> {
         exists(self, name)
     }
@@ -516,6 +863,10 @@ impl GroupOp<H5> for H5Group {
         delete(self, name)
     }
 
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        rename(self, from, to)
+    }
+
     fn exists(&self, name: &str) -> Result<bool> {
         exists(self, name)
     }
@@ -562,6 +913,14 @@ impl AttributeOp<H5> for H5Group {
             read_array_attr(self, name)
         }
     }
+
+    fn has_attr(&self, name: &str) -> Result<bool> {
+        Ok(self.attr_names()?.iter().any(|x| x == name))
+    }
+
+    fn delete_attr(&mut self, name: &str) -> Result<()> {
+        delete_attr(self, name)
+    }
 }
 
 impl AttributeOp<H5> for H5Dataset {
@@ -597,11 +956,121 @@ impl AttributeOp<H5> for H5Dataset {
             read_array_attr(self, name)
         }
     }
+
+    fn has_attr(&self, name: &str) -> Result<bool> {
+        Ok(self.attr_names()?.iter().any(|x| x == name))
+    }
+
+    fn delete_attr(&mut self, name: &str) -> Result<()> {
+        delete_attr(self, name)
+    }
 }
 
 ///////////////////////////////////////////////////////////////////////////////
 /// Auxiliary functions
 ///////////////////////////////////////////////////////////////////////////////
+
+/// Read a single field of a compound dataset. `member` is the in-memory type
+/// of the field, whose elements are converted to `T` by HDF5.
+fn read_compound_field<T: H5Type>(
+    dataset: &H5Dataset,
+    name: &str,
+    member: TypeDescriptor,
+) -> Result<Vec<T>> {
+    let (mem_type, len) = compound_field_type::<T>(dataset, name, member)?;
+    read_compound_buffer(dataset, name, &mem_type, len)
+}
+
+/// Read a variable-length string field of a compound dataset. The strings
+/// allocated by HDF5 are reclaimed once they have been copied.
+fn read_compound_string_field<T: H5Type + std::fmt::Display>(
+    dataset: &H5Dataset,
+    name: &str,
+) -> Result<Vec<String>> {
+    let (mem_type, len) = compound_field_type::<T>(dataset, name, T::type_descriptor())?;
+    let mut buf = read_compound_buffer::<T>(dataset, name, &mem_type, len)?;
+    let strings = buf.iter().map(|x| x.to_string()).collect();
+    let space = dataset.deref().space()?;
+    let status = {
+        let _lock = hdf5_sys::LOCK.lock();
+        unsafe {
+            hdf5_sys::h5t::H5Treclaim(
+                mem_type.id(),
+                space.id(),
+                hdf5_sys::h5p::H5P_DEFAULT,
+                buf.as_mut_ptr().cast(),
+            )
+        }
+    };
+    // The elements have been freed by HDF5 and must not be dropped again.
+    unsafe { buf.set_len(0) };
+    if status < 0 {
+        bail!("failed to reclaim the strings of field '{}' of the record array", name);
+    }
+    Ok(strings)
+}
+
+/// Returns the in-memory compound type holding only the field `name`, and
+/// the number of elements of `T` needed to read the whole dataset.
+fn compound_field_type<T>(
+    dataset: &H5Dataset,
+    name: &str,
+    member: TypeDescriptor,
+) -> Result<(hdf5::Datatype, usize)> {
+    let size = member.size();
+    let elem_size = std::mem::size_of::<T>();
+    ensure!(
+        size % elem_size == 0,
+        "field '{}' of {} bytes cannot be read as elements of {} bytes",
+        name,
+        size,
+        elem_size,
+    );
+    let len = dataset.deref().size() * (size / elem_size);
+    let mem_type = hdf5::Datatype::from_descriptor(&TypeDescriptor::Compound(CompoundType {
+        fields: vec![CompoundField::new(name, member, 0, 0)],
+        size,
+    }))?;
+    Ok((mem_type, len))
+}
+
+fn read_compound_buffer<T>(
+    dataset: &H5Dataset,
+    name: &str,
+    mem_type: &hdf5::Datatype,
+    len: usize,
+) -> Result<Vec<T>> {
+    let mut buf: Vec<T> = Vec::with_capacity(len);
+    let status = {
+        let _lock = hdf5_sys::LOCK.lock();
+        unsafe {
+            hdf5_sys::h5d::H5Dread(
+                dataset.id(),
+                mem_type.id(),
+                hdf5_sys::h5s::H5S_ALL,
+                hdf5_sys::h5s::H5S_ALL,
+                hdf5_sys::h5p::H5P_DEFAULT,
+                buf.as_mut_ptr().cast(),
+            )
+        }
+    };
+    if status < 0 {
+        bail!("failed to read field '{}' of the record array", name);
+    }
+    unsafe { buf.set_len(len) };
+    Ok(buf)
+}
+
+/// Complex numbers are stored as compound types with two float fields, "r"
+/// and "i", following the convention of h5py.
+fn is_complex(ty: &CompoundType, size: FloatSize) -> bool {
+    ty.fields.len() == 2
+        && ty.fields[0].name == "r"
+        && ty.fields[1].name == "i"
+        && ty.fields
+            .iter()
+            .all(|x| x.ty == TypeDescriptor::Float(size))
+}
 fn read_scalar_attr(loc: &Location, name: &str) -> Result<Value> {
     let attr = loc.attr(name)?;
     let result = match attr.dtype()?.to_descriptor()? {
@@ -651,7 +1120,10 @@ where
         DynCowArray::I32(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
         DynCowArray::I64(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
         DynCowArray::F32(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
+        DynCowArray::F16(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
         DynCowArray::F64(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
+        DynCowArray::Complex32(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
+        DynCowArray::Complex64(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
         DynCowArray::Bool(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
         DynCowArray::String(x) => {
             let data: Array<VarLenUnicode, Dim> = x.map(|x| x.parse().unwrap()).into_dimensionality()?;
@@ -673,7 +1145,10 @@ fn write_scalar_attr<D: BackendData>(loc: &Location, name: &str, value: D) -> Re
         DynScalar::I32(x) => loc.new_attr::<i32>().create(name)?.write_scalar(&x)?,
         DynScalar::I64(x) => loc.new_attr::<i64>().create(name)?.write_scalar(&x)?,
         DynScalar::F32(x) => loc.new_attr::<f32>().create(name)?.write_scalar(&x)?,
+        DynScalar::F16(x) => loc.new_attr::<f16>().create(name)?.write_scalar(&x)?,
         DynScalar::F64(x) => loc.new_attr::<f64>().create(name)?.write_scalar(&x)?,
+        DynScalar::Complex32(x) => loc.new_attr::<Complex32>().create(name)?.write_scalar(&x)?,
+        DynScalar::Complex64(x) => loc.new_attr::<Complex64>().create(name)?.write_scalar(&x)?,
         DynScalar::Bool(x) => loc.new_attr::<bool>().create(name)?.write_scalar(&x)?,
         DynScalar::String(x) => {
             let value_: VarLenUnicode = x.parse().unwrap();
@@ -713,6 +1188,23 @@ fn del_attr(loc: &Location, name: &str) {
     }
 }
 
+/// Delete an attribute if it exists. Unlike `del_attr`, errors are reported.
+fn delete_attr(loc: &Location, name: &str) -> Result<()> {
+    let c_name = std::ffi::CString::new(name)?;
+    let status = {
+        let _lock = hdf5_sys::LOCK.lock();
+        unsafe {
+            match hdf5_sys::h5a::H5Aexists(loc.id(), c_name.as_ptr()) {
+                0 => 0,
+                n if n > 0 => hdf5_sys::h5a::H5Adelete(loc.id(), c_name.as_ptr()),
+                n => n,
+            }
+        }
+    };
+    ensure!(status >= 0, "cannot delete attribute '{}'", name);
+    Ok(())
+}
+
 fn json_to_ndarray<F, T>(json: &Value, f: F) -> Result<Option<ArrayD<T>>>
 where
     F: Fn(&Value) -> Option<T>,
@@ -780,8 +1272,8 @@ fn ndarray_to_json<T: Into<Value> + Clone>(array: &ArrayD<T>) -> Value {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use anndata::s;
-    use ndarray::{concatenate, Array1, Axis, Ix1};
+    use anndata::{s, AnnData, AnnDataOp, ArrayElemOp, ElemCollectionOp, ObsData};
+    use ndarray::{concatenate, Array1, Array2, Axis, Ix1, Ix2};
     use ndarray_rand::rand_distr::Uniform;
     use ndarray_rand::RandomExt;
     use std::path::PathBuf;
@@ -825,6 +1317,224 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_blosc() -> Result<()> {
+        with_tmp_path(|path| {
+            let file = H5::new(&path)?;
+            let arr = Array::random((40, 30), Uniform::new(0, 100));
+            let compressors = [
+                BloscCompressor::BloscLz,
+                BloscCompressor::Lz4,
+                BloscCompressor::Lz4hc,
+                BloscCompressor::Zlib,
+                BloscCompressor::Zstd,
+            ];
+            for (i, compressor) in compressors.into_iter().enumerate() {
+                for shuffle in [Shuffle::None, Shuffle::Byte, Shuffle::Bit] {
+                    let config = WriteConfig {
+                        compression: Some(Compression::Blosc(compressor, 5)),
+                        shuffle,
+                        ..Default::default()
+                    };
+                    let name = format!("{}_{:?}", i, shuffle);
+                    let dataset = file.new_array_dataset(&name, arr.view().into(), config)?;
+                    assert_eq!(dataset.read_array::<i32, Ix2>()?, arr);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_open_options() -> Result<()> {
+        with_tmp_path(|path| {
+            let options = H5Options {
+                chunk_cache: Some(ChunkCache {
+                    nslots: 10007,
+                    nbytes: 64 * 1024 * 1024,
+                    w0: 1.0,
+                }),
+                page_buffer_size: Some(1024 * 1024),
+                file_space_page_size: Some(4096),
+            };
+            let arr = Array::random((100, 50), Uniform::new(0, 100));
+            let config = WriteConfig {
+                compression: Some(Compression::Zst(3)),
+                block_size: Some(vec![10, 50].into()),
+                ..Default::default()
+            };
+            let file = H5::new_with(&path, &options)?;
+            file.new_array_dataset("data", arr.view().into(), config)?;
+            file.close()?;
+
+            let file = H5::open_with(&path, &options)?;
+            let dataset = file.open_dataset("data")?;
+            let rows = dataset.read_array_slice::<i32, _, Ix2>(s![vec![3usize, 42, 97], ..].as_ref())?;
+            assert_eq!(rows, arr.select(Axis(0), &[3, 42, 97]));
+            drop(dataset);
+            file.close()?;
+
+            let file = H5::open_rw_with(&path, &H5Options::default())?;
+            assert_eq!(file.open_dataset("data")?.read_array::<i32, Ix2>()?, arr);
+            Ok(())
+        })
+    }
+
+    /// Run by `test_swmr` in a separate process, as HDF5 shares files opened
+    /// twice in the same process.
+    #[test]
+    #[ignore]
+    fn swmr_reader() -> Result<()> {
+        let Some(path) = std::env::var_os("ANNDATA_SWMR_FILE") else {
+            return Ok(());
+        };
+        let expected: usize = std::env::var("ANNDATA_SWMR_ROWS")?.parse()?;
+        let file = H5::open_swmr(path)?;
+        let dataset = file.open_dataset("data")?;
+        dataset.refresh()?;
+        assert_eq!(dataset.shape().as_ref(), &[expected, 10]);
+        let arr = dataset.read_array::<i32, Ix2>()?;
+        assert_eq!(arr, Array::from_shape_fn((expected, 10), |(i, j)| (i * 10 + j) as i32));
+        Ok(())
+    }
+
+    #[test]
+    fn test_swmr() -> Result<()> {
+        with_tmp_path(|path| {
+            let file = H5::new_swmr(&path)?;
+            let mut dataset = file.new_empty_dataset::<i32>(
+                "data",
+                &vec![0, 10].into(),
+                WriteConfig {
+                    block_size: Some(vec![5, 10].into()),
+                    ..Default::default()
+                },
+            )?;
+            file.start_swmr_write()?;
+
+            let read = |rows: usize| -> Result<()> {
+                let status = std::process::Command::new(std::env::current_exe()?)
+                    .args(["tests::swmr_reader", "--exact", "--ignored", "--quiet"])
+                    .env("ANNDATA_SWMR_FILE", &path)
+                    .env("ANNDATA_SWMR_ROWS", rows.to_string())
+                    .status()?;
+                assert!(status.success());
+                Ok(())
+            };
+            for rows in [5, 10] {
+                dataset.reshape(&vec![rows, 10].into())?;
+                let arr = Array::from_shape_fn((5, 10), |(i, j)| ((rows - 5 + i) * 10 + j) as i32);
+                dataset.write_array_slice(arr.view().into(), s![rows - 5..rows, ..].as_ref())?;
+                file.flush()?;
+                read(rows)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Run by `test_swmr_anndata` in a separate process. For every number of
+    /// rows read from stdin, the AnnData object is refreshed and checked.
+    #[test]
+    #[ignore]
+    fn swmr_anndata_reader() -> Result<()> {
+        use std::io::BufRead;
+
+        let Some(path) = std::env::var_os("ANNDATA_SWMR_FILE") else {
+            return Ok(());
+        };
+        let adata = AnnData::<H5>::open(H5::open_swmr(path)?)?;
+        for line in std::io::stdin().lock().lines() {
+            let rows: usize = line?.parse()?;
+            adata.refresh()?;
+            assert_eq!(adata.n_obs(), rows);
+            assert_eq!(adata.n_vars(), 10);
+            let x = adata.x().get::<Array2<i32>>()?.unwrap();
+            assert_eq!(x, Array::from_shape_fn((rows, 10), |(i, j)| (i * 10 + j) as i32));
+            println!("checked {}", rows);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_swmr_anndata() -> Result<()> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::process::{Command, Stdio};
+
+        with_tmp_path(|path| {
+            let adata = AnnData::<H5>::open(H5::new_swmr(&path)?)?;
+            adata.new_empty_x::<i32>(10, WriteConfig::default())?;
+            adata.start_swmr_write()?;
+
+            let mut reader = Command::new(std::env::current_exe()?)
+                .args(["tests::swmr_anndata_reader", "--exact", "--ignored", "--nocapture"])
+                .env("ANNDATA_SWMR_FILE", &path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()?;
+            let mut stdin = reader.stdin.take().unwrap();
+            let mut stdout = BufReader::new(reader.stdout.take().unwrap()).lines();
+            for rows in [5, 10, 15] {
+                let arr = Array::from_shape_fn((5, 10), |(i, j)| ((rows - 5 + i) * 10 + j) as i32);
+                adata.append_x_rows(arr)?;
+                adata.flush()?;
+                assert_eq!(adata.n_obs(), rows);
+
+                // Wait until the reader has seen the new rows.
+                writeln!(stdin, "{}", rows)?;
+                let expected = format!("checked {}", rows);
+                loop {
+                    let line = stdout.next().context("SWMR reader exited")??;
+                    if line == expected {
+                        break;
+                    }
+                }
+            }
+            drop(stdin);
+            assert!(reader.wait()?.success());
+
+            // Objects and attributes cannot be created in SWMR mode.
+            assert!(adata.uns().add("key", 1i32).is_err());
+            adata.close()?;
+
+            let adata = AnnData::<H5>::open(H5::open(&path)?)?;
+            assert_eq!(adata.n_obs(), 15);
+            assert_eq!(
+                adata.x().get::<Array2<i32>>()?.unwrap(),
+                Array::from_shape_fn((15, 10), |(i, j)| (i * 10 + j) as i32),
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_append_contiguous() -> Result<()> {
+        with_tmp_path(|path| {
+            let adata = AnnData::<H5>::new(&path)?;
+            let x = Array::from_shape_fn((4, 3), |(i, j)| (i * 3 + j) as i32);
+            let config = WriteConfig {
+                layout: Layout::Contiguous,
+                ..Default::default()
+            };
+            adata.set_x_with_config(x.clone(), &config)?;
+            adata.set_obs_names((0..4).map(|i| format!("cell{}", i)).collect())?;
+
+            // Contiguous datasets cannot grow, which is detected before anything
+            // is written.
+            let obs_names = (4..6).map(|i| format!("cell{}", i)).collect();
+            let data = ObsData::new(obs_names, Array::from_shape_fn((2, 3), |(i, j)| (i + j) as i32));
+            let err = adata.append_obs(data).unwrap_err();
+            assert!(err.to_string().contains("contiguous"), "{}", err);
+            assert_eq!(adata.interrupted_operation()?, None);
+            assert_eq!(adata.n_obs(), 4);
+            assert_eq!(adata.x().get::<Array2<i32>>()?.unwrap(), x);
+
+            // A contiguous X cannot be used for streaming either.
+            let adata = AnnData::<H5>::new(path.with_file_name("stream.h5"))?;
+            assert!(adata.new_empty_x::<i32>(3, config).is_err());
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_write_slice() -> Result<()> {
         with_tmp_path(|path| -> Result<()> {
@@ -861,4 +1571,125 @@ mod tests {
             Ok(())
         })
     }
+
+    #[derive(hdf5::H5Type, Clone)]
+    #[repr(C)]
+    struct LegacyObs {
+        index: VarLenUnicode,
+        louvain: i8,
+        n_genes: i64,
+    }
+
+    #[derive(hdf5::H5Type, Clone)]
+    #[repr(C)]
+    struct LegacyObsm {
+        #[hdf5(rename = "X_pca")]
+        x_pca: [f32; 2],
+    }
+
+    #[derive(hdf5::H5Type, Clone)]
+    #[repr(C)]
+    struct RankGenes {
+        #[hdf5(rename = "0")]
+        group0: hdf5::types::FixedAscii<8>,
+        #[hdf5(rename = "1")]
+        group1: hdf5::types::FixedAscii<8>,
+    }
+
+    #[test]
+    fn test_read_legacy() -> Result<()> {
+        use anndata::data::{CategoricalArray, Mapping};
+        use anndata::legacy::{read_legacy, Conversion};
+        use anndata::{AnnData, AnnDataOp, ArrayData, AxisArraysOp, Data, ElemCollectionOp};
+        use std::collections::HashMap;
+
+        with_tmp_dir(|dir| -> Result<()> {
+            let path = dir.join("legacy.h5ad");
+            {
+                let file = File::create(&path)?;
+                let x = ndarray::array![[1.0f32, 0.0], [0.0, 2.0], [3.0, 0.0]];
+                file.new_dataset_builder().with_data(&x).create("X")?;
+                let obs: Vec<_> = [("c1", 0, 10), ("c2", 1, 20), ("c3", -1, 30)]
+                    .into_iter()
+                    .map(|(index, louvain, n_genes)| LegacyObs {
+                        index: index.parse().unwrap(),
+                        louvain,
+                        n_genes,
+                    })
+                    .collect();
+                file.new_dataset_builder().with_data(&obs).create("obs")?;
+                let obsm: Vec<_> = (0..3)
+                    .map(|i| LegacyObsm { x_pca: [i as f32, -(i as f32)] })
+                    .collect();
+                file.new_dataset_builder().with_data(&obsm).create("obsm")?;
+
+                let uns = file.create_group("uns")?;
+                let categories: Vec<VarLenUnicode> =
+                    vec!["a".parse().unwrap(), "b".parse().unwrap()];
+                uns.new_dataset_builder()
+                    .with_data(&categories)
+                    .create("louvain_categories")?;
+                let names = vec![
+                    RankGenes {
+                        group0: hdf5::types::FixedAscii::from_ascii("CD3E")?,
+                        group1: hdf5::types::FixedAscii::from_ascii("MS4A1")?,
+                    },
+                    RankGenes {
+                        group0: hdf5::types::FixedAscii::from_ascii("CD3D")?,
+                        group1: hdf5::types::FixedAscii::from_ascii("CD79A")?,
+                    },
+                ];
+                uns.create_group("rank_genes_groups")?
+                    .new_dataset_builder()
+                    .with_data(&names)
+                    .create("names")?;
+            }
+
+            let input = H5::open(&path)?;
+            let output = AnnData::<H5>::new(dir.join("output.h5ad"))?;
+            let report = read_legacy::<H5, _>(&input, &output)?;
+            assert_eq!(
+                report.conversions,
+                vec![
+                    Conversion::RecordArray { path: "uns/rank_genes_groups/names".to_string() },
+                    Conversion::RecordArray { path: "obs".to_string() },
+                    Conversion::UnsCategories {
+                        column: "obs/louvain".to_string(),
+                        categories: "uns/louvain_categories".to_string(),
+                    },
+                    Conversion::RecordArray { path: "obsm".to_string() },
+                ]
+            );
+
+            assert_eq!(output.obs_names().into_vec(), vec!["c1", "c2", "c3"]);
+            let obs = output.read_obs()?;
+            let louvain = CategoricalArray::try_from(obs.column("louvain")?.as_materialized_series())?;
+            assert_eq!(louvain.categories.to_vec(), vec!["a", "b"]);
+            assert_eq!(
+                louvain.codes.iter().copied().collect::<Vec<_>>(),
+                vec![Some(0), Some(1), None]
+            );
+            assert_eq!(
+                obs.column("n_genes")?.i64()?.into_no_null_iter().collect::<Vec<_>>(),
+                vec![10, 20, 30]
+            );
+
+            let x_pca: ndarray::Array2<f32> = output.obsm().get_item("X_pca")?.unwrap();
+            assert_eq!(x_pca, ndarray::array![[0.0, -0.0], [1.0, -1.0], [2.0, -2.0]]);
+            assert!(output.uns().get_item::<Data>("louvain_categories")?.is_none());
+            let rank_genes: HashMap<String, Data> = output
+                .uns()
+                .get_item::<Mapping>("rank_genes_groups")?
+                .unwrap()
+                .into();
+            let Data::ArrayData(ArrayData::DataFrame(names)) = &rank_genes["names"] else {
+                panic!("rank_genes_groups/names is not a dataframe");
+            };
+            assert_eq!(
+                names.column("1")?.str()?.into_no_null_iter().collect::<Vec<_>>(),
+                vec!["MS4A1", "CD79A"]
+            );
+            Ok(())
+        })
+    }
 }
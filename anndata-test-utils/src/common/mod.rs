@@ -317,10 +317,7 @@ pub fn select_strat(n: usize) -> BoxedStrategy<SelectInfoElem> {
 /// AnnData operations
 ////////////////////////////////////////////////////////////////////////////////
 
-pub fn anndata_eq<B1: Backend, B2: Backend>(
-    adata1: &AnnData<B1>,
-    adata2: &AnnData<B2>,
-) -> Result<bool> {
+pub fn anndata_eq<A1: AnnDataOp, A2: AnnDataOp>(adata1: &A1, adata2: &A2) -> Result<bool> {
     let is_equal = adata1.n_obs() == adata2.n_obs()
         && adata1.n_vars() == adata2.n_vars()
         && adata1.obs_names() == adata2.obs_names()
@@ -353,10 +350,24 @@ pub fn anndata_eq<B1: Backend, B2: Backend>(
         })
         && adata1.layers().keys().iter().all(|k| {
             adata1.layers().get_item::<ArrayData>(k).unwrap() == adata2.layers().get_item(k).unwrap()
-        });
+        })
+        && raw_eq(adata1.get_raw()?, adata2.get_raw()?);
     Ok(is_equal)
 }
 
+pub fn raw_eq(raw1: Option<RawData>, raw2: Option<RawData>) -> bool {
+    match (raw1, raw2) {
+        (None, None) => true,
+        (Some(a), Some(b)) => {
+            a.x == b.x
+                && a.var_names == b.var_names
+                && a.varm == b.varm
+                && (a.var == b.var || (a.var.is_empty() && b.var.is_empty()))
+        }
+        _ => false,
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 /// Array operations
 ////////////////////////////////////////////////////////////////////////////////
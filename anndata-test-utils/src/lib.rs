@@ -1,11 +1,23 @@
 mod common;
 pub use common::*;
 
+use anndata::backend::{
+    f16, AttributeOp, BloscCompressor, Complex32, Complex64, Compression, DatasetOp, GroupOp,
+    IndexWidth, Layout, Mem, ScalarType, Shuffle, SparseEncoding, StoreOp, WriteConfig,
+    WritePolicy,
+};
 use anndata::concat::{concat, JoinType};
-use anndata::{data::CsrNonCanonical, *};
+use anndata::{
+    data::{
+        AwkwardArray, CategoricalArray, CsrNonCanonical, DataFrameIndex, DynArray, SelectInfoElem,
+        SelectInfoElemBounds, Stackable,
+    },
+    *,
+};
 use data::ArrayConvert;
-use nalgebra_sparse::{CooMatrix, CsrMatrix};
-use ndarray::Array2;
+use nalgebra_sparse::{CooMatrix, CscMatrix, CsrMatrix};
+use ndarray::{array, Array2, ArrayD, Axis};
+use polars::prelude::{df, DataFrame, DataType, NamedFrom, Series, TimeUnit, TimeZone};
 use proptest::prelude::*;
 
 pub fn test_basic<B: Backend>() {
@@ -55,6 +67,652 @@ pub fn test_save<B: Backend>() {
     });
 }
 
+pub fn test_raw<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let input = dir.join("input");
+        let output = dir.join("output");
+        let anndatas = ((1 as usize..50), (0 as usize..50), (1 as usize..50)).prop_flat_map(
+            |(n_obs, n_vars, n_raw_vars)| {
+                (
+                    anndata_strat::<B, _>(&input, n_obs, n_vars),
+                    array_strat(&vec![n_obs, n_raw_vars]),
+                    array_strat(&vec![n_raw_vars, 5]),
+                    select_strat(n_obs),
+                    select_strat(n_vars),
+                )
+            },
+        );
+        proptest!(ProptestConfig::with_cases(50), |((adata, x, varm, slice_obs, slice_var) in anndatas)| {
+            let mut raw = RawData::new(x.clone());
+            raw.varm.insert("varm".to_string(), varm);
+            adata.set_raw(raw.clone()).unwrap();
+            prop_assert!(raw_eq(adata.get_raw().unwrap(), Some(raw.clone())));
+
+            adata.write::<B, _>(&output).unwrap();
+            let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+            prop_assert!(anndata_eq(&adata, &adata_in).unwrap());
+            adata_in.close().unwrap();
+
+            let select = [slice_obs.clone(), slice_var];
+            adata.write_select::<B, _, _>(&select, &output).unwrap();
+            adata.subset(&select).unwrap();
+            let raw_x = adata.get_raw().unwrap().unwrap().x;
+            prop_assert_eq!(raw_x, array_select(&x, &[slice_obs, SelectInfoElem::full()]));
+            let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+            prop_assert!(anndata_eq(&adata, &adata_in).unwrap());
+            adata_in.close().unwrap();
+
+            adata.del_raw().unwrap();
+            prop_assert!(adata.get_raw().unwrap().is_none());
+        });
+    });
+}
+
+/// A failed attempt to replace the raw slot must leave the existing one intact.
+pub fn test_replace_raw<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let file = dir.join("test");
+        let adata = AnnData::<B>::new(&file).unwrap();
+        let x1 = Array2::<i32>::from_shape_fn((4, 3), |(i, j)| (i * 3 + j) as i32);
+        let x2 = Array2::<i32>::from_shape_fn((4, 2), |(i, j)| (i * 2 + j) as i32 + 100);
+        adata.set_raw(RawData::new(x1.clone())).unwrap();
+
+        // The number of rows does not match the number of observations.
+        assert!(adata.set_raw(RawData::new(Array2::<i32>::zeros((5, 3)))).is_err());
+        assert_eq!(adata.read_raw_x().unwrap().unwrap(), ArrayData::from(x1.clone().into_dyn()));
+
+        // The number of variables does not match the data matrix, which is
+        // only detected after the matrix has been written.
+        let var_names: DataFrameIndex = ["a", "b", "c"].into_iter().map(String::from).collect();
+        assert!(adata
+            .set_raw_from_iter([x2.clone()].into_iter(), var_names, DataFrame::empty())
+            .is_err());
+        assert_eq!(adata.read_raw_x().unwrap().unwrap(), ArrayData::from(x1.clone().into_dyn()));
+
+        adata.set_raw(RawData::new(x2.clone())).unwrap();
+        assert_eq!(adata.read_raw_x().unwrap().unwrap(), ArrayData::from(x2.clone().into_dyn()));
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open(B::open(&file).unwrap()).unwrap();
+        assert_eq!(adata.read_raw_x().unwrap().unwrap(), ArrayData::from(x2.into_dyn()));
+        assert_eq!(adata.get_raw_var().unwrap().unwrap().0.len(), 2);
+    })
+}
+
+/// Round-trip dataframes with various column types through `obs`.
+pub fn test_dataframe<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let obs = df!(
+            "str" => ["a", "b", "c", "d"],
+            "nullable_str" => [Some("a"), None, Some("c"), None],
+            "nullable_int" => [Some(1i32), None, Some(3), Some(4)],
+            "nullable_bool" => [Some(true), None, Some(false), None],
+            "nullable_float" => [Some(1.5f64), None, Some(f64::NAN), Some(-2.0)],
+            "nullable_f32" => [None, Some(0.5f32), Some(f32::NAN), None],
+        )
+        .unwrap();
+        // Categoricals with unused categories, ordered and unordered
+        let categories = array!["G1".to_string(), "S".to_string(), "G2M".to_string()];
+        let phase = CategoricalArray {
+            codes: array![Some(2), None, Some(0), Some(2)].into_dyn(),
+            categories: categories.clone(),
+            ordered: true,
+        };
+        let batch = CategoricalArray {
+            codes: array![Some(1), Some(1), None, Some(2)].into_dyn(),
+            categories,
+            ordered: false,
+        };
+        let mut obs = obs;
+        obs.with_column(Into::<Series>::into(phase.clone()).with_name("phase".into()))
+            .unwrap();
+        obs.with_column(Into::<Series>::into(batch.clone()).with_name("batch".into()))
+            .unwrap();
+        // Temporal columns with nulls
+        let days = Series::new("date".into(), [Some(0i32), None, Some(19000), Some(-1)]);
+        obs.with_column(days.cast(&DataType::Date).unwrap()).unwrap();
+        let time = Series::new("time".into(), [Some(1i64), Some(-5), None, Some(1_700_000_000_000)]);
+        obs.with_column(
+            time.cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+                .unwrap(),
+        )
+        .unwrap();
+        let time = time.with_name("time_tz".into()).cast(&DataType::Datetime(
+            TimeUnit::Microseconds,
+            TimeZone::opt_try_new(Some("Europe/Amsterdam")).unwrap(),
+        ));
+        obs.with_column(time.unwrap()).unwrap();
+        let elapsed = Series::new("elapsed".into(), [None, Some(3i64), Some(0), Some(-7)]);
+        obs.with_column(
+            elapsed
+                .cast(&DataType::Duration(TimeUnit::Nanoseconds))
+                .unwrap(),
+        )
+        .unwrap();
+
+        adata.set_obs(obs.clone()).unwrap();
+        let obs_in = adata.read_obs().unwrap();
+        assert_eq!(obs_in, obs);
+        // Missing values stay distinct from NaN
+        let col = obs_in.column("nullable_float").unwrap().f64().unwrap();
+        assert_eq!(col.null_count(), 1);
+        assert!(col.get(1).is_none());
+        assert!(col.get(2).unwrap().is_nan());
+        for (name, cat) in [("phase", phase), ("batch", batch)] {
+            let series = obs_in.column(name).unwrap().as_materialized_series();
+            assert_eq!(CategoricalArray::try_from(series).unwrap(), cat);
+        }
+
+        let output = dir.join("output");
+        adata.write::<B, _>(&output).unwrap();
+        let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+        assert_eq!(adata_in.read_obs().unwrap(), obs);
+        adata_in.close().unwrap();
+
+        let select = [SelectInfoElem::from(vec![3, 1]), SelectInfoElem::full()];
+        let expected: DataFrame = Selectable::select(&obs, &select);
+        adata.write_select::<B, _, _>(&select, &output).unwrap();
+        let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+        assert_eq!(adata_in.read_obs().unwrap(), expected);
+        adata_in.close().unwrap();
+
+        // Partial reads of dataframes stored as arrays
+        adata.obsm().add("df", obs).unwrap();
+        let df: DataFrame = adata.obsm().get_item_slice("df", &select).unwrap().unwrap();
+        assert_eq!(df, expected);
+    })
+}
+
+/// Round-trip ragged arrays stored with the awkward-array encoding through `obsm`.
+pub fn test_awkward<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let peaks: AwkwardArray = vec![vec![1.5f64, 2.0], vec![], vec![3.0], vec![4.0, 5.0, 6.0]]
+            .into_iter()
+            .collect();
+        let chains: AwkwardArray = vec![
+            vec!["TRA".to_string()],
+            vec!["TRA".to_string(), "TRB".to_string()],
+            vec![],
+            vec!["TRB".to_string()],
+        ]
+        .into_iter()
+        .collect();
+        adata.obsm().add("peaks", peaks.clone()).unwrap();
+        adata.obsm().add("chains", chains.clone()).unwrap();
+        assert_eq!(adata.n_obs(), 4);
+        assert_eq!(adata.obsm().get_item::<AwkwardArray>("peaks").unwrap().unwrap(), peaks);
+        assert_eq!(adata.obsm().get_item::<AwkwardArray>("chains").unwrap().unwrap(), chains);
+
+        let select = [SelectInfoElem::from(vec![3, 1, 0]), SelectInfoElem::full()];
+        let expected: AwkwardArray = vec![vec![4.0f64, 5.0, 6.0], vec![], vec![1.5, 2.0]]
+            .into_iter()
+            .collect();
+        assert_eq!(peaks.select(&select), expected);
+        let peaks_in: AwkwardArray = adata.obsm().get_item_slice("peaks", &select).unwrap().unwrap();
+        assert_eq!(peaks_in, expected);
+        let chains_in: AwkwardArray = adata.obsm().get_item_slice("chains", &select).unwrap().unwrap();
+        assert_eq!(chains_in, chains.select(&select));
+        // Awkward arrays have no second axis to select along.
+        let bad_select = [SelectInfoElem::full(), SelectInfoElem::from(vec![0])];
+        assert!(adata.obsm().get_item_slice::<AwkwardArray, _>("peaks", &bad_select).is_err());
+        let store = B::new(dir.join("store")).unwrap();
+        let container = peaks.write::<B, _>(&store, "peaks").unwrap();
+        assert!(AwkwardArray::read_select(&container, &bad_select).is_err());
+
+        let output = dir.join("output");
+        adata.write_select::<B, _, _>(&select, &output).unwrap();
+        let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+        assert_eq!(adata_in.obsm().get_item::<AwkwardArray>("peaks").unwrap().unwrap(), expected);
+        adata_in.close().unwrap();
+
+        adata.subset(&select).unwrap();
+        assert_eq!(adata.obsm().get_item::<AwkwardArray>("peaks").unwrap().unwrap(), expected);
+    })
+}
+
+pub fn test_scalar_types<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let half = array![[1.5, -2.0], [0.25, 65504.0], [f32::INFINITY, 0.0]].mapv(f16::from_f32);
+        let c64 = Array2::from_shape_fn((3, 2), |(_, j)| {
+            [Complex32::new(1.0, -1.0), Complex32::new(0.5, 2.0)][j]
+        });
+        let c128 = array![Complex64::new(1e-300, 3.0), Complex64::new(-2.5, 0.0), Complex64::new(0.0, 0.0)];
+        adata.set_x(half.clone()).unwrap();
+        adata.obsm().add("c64", c64.clone()).unwrap();
+        adata.uns().add("c128", c128.clone().into_dyn()).unwrap();
+        let csr = CsrMatrix::try_from_csr_data(
+            3, 2, vec![0, 1, 1, 2], vec![1, 0], vec![Complex64::new(1.0, 2.0), Complex64::new(-3.0, 0.5)],
+        ).unwrap();
+        adata.obsm().add("csr", csr.clone()).unwrap();
+
+        let output = dir.join("output");
+        adata.write::<B, _>(&output).unwrap();
+        let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+        for a in [&adata, &adata_in] {
+            assert_eq!(a.x().get::<Array2<f16>>().unwrap().unwrap(), half);
+            assert_eq!(a.obsm().get_item::<Array2<Complex32>>("c64").unwrap().unwrap(), c64);
+            assert_eq!(a.uns().get_item::<ArrayD<Complex64>>("c128").unwrap().unwrap(), c128.clone().into_dyn());
+            assert_eq!(a.obsm().get_item::<CsrMatrix<Complex64>>("csr").unwrap().unwrap(), csr);
+            let slice: Array2<f16> = a.x().slice_axis(0, SelectInfoElem::from(1..3)).unwrap().unwrap();
+            assert_eq!(slice, half.slice(ndarray::s![1.., ..]).to_owned());
+        }
+        let x: Array2<f32> = adata_in.x().get::<ArrayData>().unwrap().unwrap().try_convert().unwrap();
+        assert_eq!(x, half.mapv(f16::to_f32));
+        // Complex arrays have no dataframe column type.
+        assert!(Series::try_from(DynArray::from(c128.into_dyn())).is_err());
+        adata_in.close().unwrap();
+    })
+}
+
+pub fn test_sparse_encoding<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let store = B::new(dir.join("test")).unwrap();
+        let csr = CsrMatrix::try_from_csr_data(
+            3, 4, vec![0, 2, 2, 3], vec![0, 3, 1], vec![1.0f64, 2.0, 3.0],
+        ).unwrap();
+        let csc = CscMatrix::from(&csr);
+
+        let container = csr.write::<B, _>(&store, "default").unwrap();
+        assert_eq!(container.get_attr::<String>("encoding-type").unwrap(), "csr_matrix");
+        let group = container.as_group().unwrap();
+        assert_eq!(group.open_dataset("indptr").unwrap().dtype().unwrap(), ScalarType::I32);
+        assert_eq!(group.open_dataset("indices").unwrap().dtype().unwrap(), ScalarType::I32);
+
+        let config = WriteConfig {
+            index_width: IndexWidth::I64,
+            sparse_encoding: SparseEncoding::Array,
+            ..Default::default()
+        };
+        let container = csr.write_with_config::<B, _>(&store, "csr", &config).unwrap();
+        assert_eq!(container.get_attr::<String>("encoding-type").unwrap(), "csr_array");
+        assert_eq!(
+            container.encoding_type().unwrap(),
+            anndata::backend::DataType::CsrMatrix(ScalarType::F64)
+        );
+        let group = container.as_group().unwrap();
+        assert_eq!(group.open_dataset("indptr").unwrap().dtype().unwrap(), ScalarType::I64);
+        assert_eq!(group.open_dataset("indices").unwrap().dtype().unwrap(), ScalarType::I64);
+        assert_eq!(CsrMatrix::<f64>::read(&container).unwrap(), csr);
+        assert_eq!(ArrayData::read(&container).unwrap(), csr.clone().into());
+
+        let container = csc.write_with_config::<B, _>(&store, "csc", &config).unwrap();
+        assert_eq!(container.get_attr::<String>("encoding-type").unwrap(), "csc_array");
+        assert_eq!(CscMatrix::<f64>::read(&container).unwrap(), csc);
+
+        // Too many columns for i32 indices.
+        let wide = CsrMatrix::<f64>::try_from_csr_data(1, 1 << 32, vec![0, 0], vec![], vec![]).unwrap();
+        let config = WriteConfig {
+            index_width: IndexWidth::I32,
+            ..Default::default()
+        };
+        assert!(wide.write_with_config::<B, _>(&store, "wide_i32", &config).is_err());
+        let container = wide.write::<B, _>(&store, "wide").unwrap();
+        let group = container.as_group().unwrap();
+        assert_eq!(group.open_dataset("indptr").unwrap().dtype().unwrap(), ScalarType::I64);
+        assert_eq!(CsrMatrix::<f64>::read(&container).unwrap(), wide);
+    })
+}
+
+/// Build objects with the in-memory backend and persist them with `B`.
+pub fn test_in_memory<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let output = dir.join("output");
+        let anndatas = ((0 as usize..50), (0 as usize..50))
+            .prop_flat_map(|(n_obs, n_vars)| anndata_strat::<Mem, _>("memory", n_obs, n_vars));
+        proptest!(ProptestConfig::with_cases(50), |(adata in anndatas)| {
+            adata.write::<B, _>(&output).unwrap();
+            let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+            prop_assert!(anndata_eq(&adata, &adata_in).unwrap());
+            adata_in.close().unwrap();
+        });
+    });
+}
+
+/// Write objects with a different configuration for each element.
+pub fn test_write_policy<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let input = dir.join("input");
+        let output = dir.join("output");
+        let policy = WritePolicy::default()
+            .with("X", WriteConfig {
+                compression: Some(Compression::Zst(9)),
+                block_size: Some(vec![7, 3].into()),
+                ..Default::default()
+            })
+            .with("obs", WriteConfig {
+                compression: Some(Compression::Blosc(BloscCompressor::Lz4hc, 9)),
+                ..Default::default()
+            })
+            .with("obsm", WriteConfig {
+                layout: Layout::Contiguous,
+                ..Default::default()
+            })
+            .with("layers", WriteConfig {
+                compression: Some(Compression::Blosc(BloscCompressor::Lz4, 5)),
+                shuffle: Shuffle::Bit,
+                ..Default::default()
+            });
+        let anndatas = ((0 as usize..50), (0 as usize..50))
+            .prop_flat_map(|(n_obs, n_vars)| anndata_strat::<B, _>(&input, n_obs, n_vars));
+        proptest!(ProptestConfig::with_cases(20), |(adata in anndatas)| {
+            adata.write_with_policy::<B, _>(&output, &policy).unwrap();
+            let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+            prop_assert!(anndata_eq(&adata, &adata_in).unwrap());
+            adata_in.close().unwrap();
+        });
+
+        let adata = AnnData::<B>::new(dir.join("x")).unwrap();
+        let x = array![[1u16, 2, 3], [4, 5, 6]];
+        let config = WriteConfig {
+            layout: Layout::Contiguous,
+            ..Default::default()
+        };
+        adata.set_x_with_config(x.clone(), &config).unwrap();
+        assert_eq!(adata.x().get::<Array2<u16>>().unwrap().unwrap(), x);
+        let config = WriteConfig {
+            compression: Some(Compression::Blosc(BloscCompressor::Lz4, 5)),
+            ..Default::default()
+        };
+        adata.set_x_with_config(x.clone(), &config).unwrap();
+        assert_eq!(adata.x().get::<Array2<u16>>().unwrap().unwrap(), x);
+    })
+}
+
+/// Views, and views of views, should match the subset objects.
+pub fn test_view<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let input = dir.join("input");
+        let output = dir.join("output");
+        let anndatas = ((0 as usize..50), (0 as usize..50))
+            .prop_flat_map(|(n_obs, n_vars)| {
+                (Just(n_obs), Just(n_vars), select_strat(n_obs), select_strat(n_vars))
+            })
+            .prop_flat_map(|(n_obs, n_vars, slice_obs, slice_var)| {
+                let n_obs_view = SelectInfoElemBounds::new(&slice_obs, n_obs).len();
+                let n_vars_view = SelectInfoElemBounds::new(&slice_var, n_vars).len();
+                (
+                    anndata_strat::<B, _>(&input, n_obs, n_vars),
+                    Just([slice_obs, slice_var]),
+                    select_strat(n_obs_view),
+                    select_strat(n_vars_view),
+                )
+            });
+        proptest!(ProptestConfig::with_cases(50), |((adata, select, slice_obs, slice_var) in anndatas)| {
+            let view = adata.view(&select).unwrap();
+            adata.write_select::<B, _, _>(&select, &output).unwrap();
+            let adata_in = AnnData::<B>::open(B::open_rw(&output).unwrap()).unwrap();
+            prop_assert!(anndata_eq(&view, &adata_in).unwrap());
+            prop_assert!(view.del_x().is_err());
+
+            let select = [slice_obs, slice_var];
+            let view = view.view(&select).unwrap();
+            adata_in.subset(&select).unwrap();
+            prop_assert!(anndata_eq(&view, &adata_in).unwrap());
+            for chunk in view.x().iter::<ArrayData>(7) {
+                let (chunk, i, j) = chunk.unwrap();
+                let expected = adata_in.x().slice_axis::<ArrayData, _>(0, SelectInfoElem::from(i..j));
+                prop_assert_eq!(Some(chunk), expected.unwrap());
+            }
+            adata_in.close().unwrap();
+        });
+
+        // Chunks that cannot be read as the requested type are errors.
+        let adata = AnnData::<B>::new(dir.join("iter")).unwrap();
+        adata.set_x(array![[1, 2], [3, 4], [5, 6]]).unwrap();
+        let view = adata.view(&[SelectInfoElem::from(vec![2, 0]), SelectInfoElem::full()]).unwrap();
+        let chunks = view.x().iter::<CsrMatrix<i32>>(1);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.map(|x| x.is_err()).all(|x| x));
+        let chunks: Vec<_> = view.x().iter::<Array2<i32>>(1).map(|x| x.unwrap().0).collect();
+        assert_eq!(chunks, vec![array![[5, 6]], array![[1, 2]]]);
+        adata.close().unwrap();
+    })
+}
+
+/// Appended observations should match the rows stacked in memory.
+pub fn test_append_obs<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let file = dir.join("test");
+        let adata = AnnData::<B>::new(&file).unwrap();
+        let x1 = rand_csr::<i32>(10, 5, 20, 1, 100);
+        let x2 = rand_csr::<i32>(4, 5, 8, 1, 100);
+        let pca1 = Array2::<f64>::from_shape_fn((10, 3), |(i, j)| (i * 3 + j) as f64);
+        let pca2 = Array2::<f64>::from_shape_fn((4, 3), |(i, j)| (i * 7 + j) as f64);
+        let counts1 = Array2::<u16>::from_shape_fn((10, 5), |(i, j)| (i + j) as u16);
+        let counts2 = Array2::<u16>::from_shape_fn((4, 5), |(i, j)| (i * j) as u16);
+        let group: Vec<i32> = (0..14).map(|i| i % 3).collect();
+        let raw1 = Array2::<f64>::from_shape_fn((10, 7), |(i, j)| (i * 7 + j) as f64);
+        let raw2 = Array2::<f64>::from_shape_fn((4, 7), |(i, j)| (i * 2 + j) as f64);
+
+        adata.set_x(&x1).unwrap();
+        adata.set_raw(RawData::new(raw1.clone())).unwrap();
+        adata.set_obs(df!("group" => &group[..10]).unwrap()).unwrap();
+        adata.set_obs_names((0..10).map(|i| format!("cell{}", i)).collect()).unwrap();
+        adata.set_var_names((0..5).map(|i| format!("gene{}", i)).collect()).unwrap();
+        adata.obsm().add("pca", &pca1).unwrap();
+        adata.layers().add("counts", &counts1).unwrap();
+
+        // The variables of the new observations are given in reverse order.
+        let rev: SelectInfoElem = (0..5).rev().collect::<Vec<_>>().into();
+        let obs_names: DataFrameIndex = (10..14).map(|i| format!("cell{}", i)).collect();
+        let mut data = ObsData::new(obs_names, x2.select_axis(1, &rev));
+        data.var_names = Some((0..5).rev().map(|i| format!("gene{}", i)).collect());
+        data.obs = df!("group" => &group[10..]).unwrap();
+        data.obsm.insert("pca".to_string(), pca2.clone().into());
+        data.layers.insert("counts".to_string(), counts2.select_axis(1, &rev).into());
+        data.raw_x = Some(raw2.clone().into());
+
+        // raw/X must receive the new rows.
+        let mut invalid = data.clone();
+        invalid.raw_x = None;
+        assert!(adata.append_obs(invalid).is_err());
+        let mut invalid = data.clone();
+        invalid.raw_x = Some(raw2.slice(ndarray::s![.., ..6]).to_owned().into());
+        assert!(adata.append_obs(invalid).is_err());
+        // Keys of obsm must match.
+        let mut invalid = data.clone();
+        invalid.obsm.clear();
+        assert!(adata.append_obs(invalid).is_err());
+        // Variables must match.
+        let mut invalid = data.clone();
+        invalid.var_names = Some((1..6).map(|i| format!("gene{}", i)).collect());
+        assert!(adata.append_obs(invalid).is_err());
+        // Names must be unique.
+        let mut invalid = data.clone();
+        invalid.obs_names = ["cell10", "cell11", "cell10", "cell13"]
+            .map(String::from)
+            .into_iter()
+            .collect();
+        assert!(adata.append_obs(invalid).is_err());
+        assert_eq!(adata.n_obs(), 10);
+
+        adata.append_obs(data).unwrap();
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open(B::open_rw(&file).unwrap()).unwrap();
+        assert_eq!(adata.n_obs(), 14);
+        // The stored columns and names are checked without reading obs.
+        let mut invalid = ObsData::new(
+            std::iter::once("cell14".to_string()).collect(),
+            rand_csr::<i32>(1, 5, 2, 1, 100),
+        );
+        invalid.obs = df!("group" => [0.5f64]).unwrap();
+        invalid.obsm.insert("pca".to_string(), Array2::<f64>::zeros((1, 3)).into());
+        invalid.layers.insert("counts".to_string(), Array2::<u16>::zeros((1, 5)).into());
+        invalid.raw_x = Some(Array2::<f64>::zeros((1, 7)).into());
+        assert!(adata.append_obs(invalid.clone()).is_err());
+        invalid.obs = df!("group" => [0i32]).unwrap();
+        invalid.obs_names = std::iter::once("cell3".to_string()).collect();
+        assert!(adata.append_obs(invalid).is_err());
+        assert_eq!(adata.n_obs(), 14);
+        assert_eq!(adata.n_vars(), 5);
+        assert_eq!(
+            adata.obs_names().into_vec(),
+            (0..14).map(|i| format!("cell{}", i)).collect::<Vec<_>>(),
+        );
+        assert_eq!(adata.read_obs().unwrap(), df!("group" => &group).unwrap());
+        assert_eq!(
+            adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap(),
+            CsrMatrix::vstack([x1, x2].into_iter()).unwrap(),
+        );
+        assert_eq!(
+            adata.obsm().get_item::<Array2<f64>>("pca").unwrap().unwrap(),
+            ndarray::concatenate(Axis(0), &[pca1.view(), pca2.view()]).unwrap(),
+        );
+        assert_eq!(
+            adata.layers().get_item::<Array2<u16>>("counts").unwrap().unwrap(),
+            ndarray::concatenate(Axis(0), &[counts1.view(), counts2.view()]).unwrap(),
+        );
+        let raw = ndarray::concatenate(Axis(0), &[raw1.view(), raw2.view()]).unwrap();
+        assert_eq!(adata.read_raw_x().unwrap().unwrap(), ArrayData::from(raw.into_dyn()));
+    })
+}
+
+pub fn test_append_vars<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let file = dir.join("test");
+        let adata = AnnData::<B>::new(&file).unwrap();
+        let x1 = rand_csr::<i32>(10, 5, 20, 1, 100);
+        let x2 = rand_csr::<i32>(10, 3, 8, 1, 100);
+        let pcs1 = Array2::<f64>::from_shape_fn((5, 2), |(i, j)| (i * 2 + j) as f64);
+        let pcs2 = Array2::<f64>::from_shape_fn((3, 2), |(i, j)| (i * 5 + j) as f64);
+        let counts1 = Array2::<u16>::from_shape_fn((10, 5), |(i, j)| (i + j) as u16);
+        let counts2 = Array2::<u16>::from_shape_fn((10, 3), |(i, j)| (i * j) as u16);
+        let highly_variable: Vec<bool> = (0..8).map(|i| i % 2 == 0).collect();
+
+        adata.set_x(&x1).unwrap();
+        adata.set_var(df!("highly_variable" => &highly_variable[..5]).unwrap()).unwrap();
+        adata.set_obs_names((0..10).map(|i| format!("cell{}", i)).collect()).unwrap();
+        adata.set_var_names((0..5).map(|i| format!("gene{}", i)).collect()).unwrap();
+        adata.varm().add("pcs", &pcs1).unwrap();
+        adata.layers().add("counts", &counts1).unwrap();
+
+        // The observations of the new variables are given in reverse order.
+        let rev: SelectInfoElem = (0..10).rev().collect::<Vec<_>>().into();
+        let var_names: DataFrameIndex = (5..8).map(|i| format!("gene{}", i)).collect();
+        let mut data = VarData::new(var_names, x2.select_axis(0, &rev));
+        data.obs_names = Some((0..10).rev().map(|i| format!("cell{}", i)).collect());
+        data.var = df!("highly_variable" => &highly_variable[5..]).unwrap();
+        data.varm.insert("pcs".to_string(), pcs2.clone().into());
+        data.layers.insert("counts".to_string(), counts2.select_axis(0, &rev).into());
+
+        // Keys of varm must match.
+        let mut invalid = data.clone();
+        invalid.varm.clear();
+        assert!(adata.append_vars(invalid).is_err());
+        // Observations must match.
+        let mut invalid = data.clone();
+        invalid.obs_names = Some((1..11).map(|i| format!("cell{}", i)).collect());
+        assert!(adata.append_vars(invalid).is_err());
+        // Columns of var must match.
+        let mut invalid = data.clone();
+        invalid.var = df!("dispersion" => [1.0, 2.0, 3.0]).unwrap();
+        assert!(adata.append_vars(invalid).is_err());
+        assert_eq!(adata.n_vars(), 5);
+
+        adata.append_vars(data).unwrap();
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open(B::open(&file).unwrap()).unwrap();
+        assert_eq!(adata.n_obs(), 10);
+        assert_eq!(adata.n_vars(), 8);
+        assert_eq!(
+            adata.var_names().into_vec(),
+            (0..8).map(|i| format!("gene{}", i)).collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            adata.read_var().unwrap(),
+            df!("highly_variable" => &highly_variable).unwrap()
+        );
+        assert_eq!(
+            adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap(),
+            CsrMatrix::vstack([x1.transpose(), x2.transpose()].into_iter())
+                .unwrap()
+                .transpose(),
+        );
+        assert_eq!(
+            adata.varm().get_item::<Array2<f64>>("pcs").unwrap().unwrap(),
+            ndarray::concatenate(Axis(0), &[pcs1.view(), pcs2.view()]).unwrap(),
+        );
+        assert_eq!(
+            adata.layers().get_item::<Array2<u16>>("counts").unwrap().unwrap(),
+            ndarray::concatenate(Axis(1), &[counts1.view(), counts2.view()]).unwrap(),
+        );
+    })
+}
+
+pub fn test_atomic_write<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let input = dir.join("input");
+        let output = dir.join("output");
+        let x = rand_csr::<i32>(10, 5, 20, 1, 100);
+        let adata = AnnData::<B>::new(&input).unwrap();
+        adata
+            .set_x_from_iter(x.row_iter().map(|row| {
+                let (idx, val) = (row.col_indices().to_vec(), row.values().to_vec());
+                CsrMatrix::try_from_csr_data(1, 5, vec![0, idx.len()], idx, val).unwrap()
+            }))
+            .unwrap();
+        assert_eq!(adata.interrupted_operation().unwrap(), None);
+
+        // An existing file is replaced.
+        let select = [SelectInfoElem::from(0..2), SelectInfoElem::full()];
+        adata.write_select::<B, _, _>(&select, &output).unwrap();
+        adata.write_atomic::<B, _>(&output).unwrap();
+        // A failed write leaves the existing file untouched.
+        let select = [SelectInfoElem::from(0..20), SelectInfoElem::full()];
+        assert!(adata.write_select_atomic::<B, _, _>(&select, &output).is_err());
+        // No temporary files are left behind.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+        assert_eq!(adata_in.x().get::<CsrMatrix<i32>>().unwrap().unwrap(), x);
+        assert_eq!(adata_in.interrupted_operation().unwrap(), None);
+        adata_in.close().unwrap();
+        adata.close().unwrap();
+
+        // Simulate an in-place operation that did not finish.
+        let store = B::open_rw(&input).unwrap();
+        store.open_group("/").unwrap().new_attr("dirty", "append_obs").unwrap();
+        store.close().unwrap();
+        let adata = AnnData::<B>::open(B::open_rw(&input).unwrap()).unwrap();
+        assert_eq!(adata.interrupted_operation().unwrap().as_deref(), Some("append_obs"));
+
+        let obs_names: DataFrameIndex = (0..10).map(|i| format!("cell{}", i)).collect();
+        adata.set_obs_names(obs_names).unwrap();
+        let new_names: DataFrameIndex = (10..12).map(|i| format!("cell{}", i)).collect();
+        let x_new = rand_csr::<i32>(2, 5, 4, 1, 100);
+        adata.append_obs(ObsData::new(new_names, x_new)).unwrap();
+        assert_eq!(adata.interrupted_operation().unwrap(), None);
+        adata.close().unwrap();
+        // The marker is removed rather than cleared.
+        let store = B::open(&input).unwrap();
+        assert!(!store.open_group("/").unwrap().has_attr("dirty").unwrap());
+        store.close().unwrap();
+
+        // A subset that fails after X has been subset leaves the marker.
+        let file = dir.join("subset");
+        let adata = AnnData::<B>::new(&file).unwrap();
+        adata.set_x(&x).unwrap();
+        let peaks: AwkwardArray = (0..10).map(|i| vec![i as f64; i % 3]).collect();
+        adata.obsm().add("peaks", peaks).unwrap();
+        assert_eq!(adata.interrupted_operation().unwrap(), None);
+        adata.close().unwrap();
+        let store = B::open_rw(&file).unwrap();
+        let obsm = store.open_group("obsm").unwrap();
+        obsm.open_group("peaks").unwrap().delete("node0-offsets").unwrap();
+        drop(obsm);
+        store.close().unwrap();
+        let adata = AnnData::<B>::open(B::open_rw(&file).unwrap()).unwrap();
+        assert!(adata.subset([SelectInfoElem::from(0..5), SelectInfoElem::full()]).is_err());
+        assert_eq!(adata.interrupted_operation().unwrap().as_deref(), Some("subset"));
+        adata.close().unwrap();
+    })
+}
+
 pub fn test_speacial_cases<F, T>(adata_gen: F)
 where
     F: Fn() -> T,
@@ -150,7 +808,7 @@ where
             adata.obsm().add_iter("test", array_chunks(&x, 7)).unwrap();
             prop_assert_eq!(adata.obsm().get_item::<ArrayData>("test").unwrap().unwrap(), x.clone());
 
-            adata.obsm().add_iter("test2", adata.obsm().get_item_iter::<ArrayData>("test", 7).unwrap().map(|x| x.0)).unwrap();
+            adata.obsm().add_iter("test2", adata.obsm().get_item_iter::<ArrayData>("test", 7).unwrap().map(|x| x.unwrap().0)).unwrap();
             prop_assert_eq!(adata.obsm().get_item::<ArrayData>("test2").unwrap().unwrap(), x);
         }
     });
@@ -184,4 +842,41 @@ pub fn test_concat<B: Backend>() {
             concat::<_, _, String>(&adatas, JoinType::Outer, None, None, &out).unwrap();
         })
     });
-}
\ No newline at end of file
+}
+
+pub fn test_concat_raw<B: Backend>() {
+    with_tmp_dir(|dir| {
+        // The first object spans several chunks.
+        let x1 = Array2::<i32>::from_shape_fn((2500, 3), |(i, j)| (i * 3 + j) as i32);
+        let x2 = Array2::<i32>::from_shape_fn((2, 3), |(i, j)| (i * 3 + j) as i32 + 100);
+
+        let adata1 = AnnData::<B>::new(dir.join("input1")).unwrap();
+        adata1.set_x(&x1).unwrap();
+        adata1.set_obs_names((0..2500).map(|i| format!("cell{}", i)).collect()).unwrap();
+        adata1.set_var_names(["x", "y", "z"].into_iter().map(String::from).collect()).unwrap();
+        let mut raw = RawData::new(x1.clone());
+        raw.var_names = ["a", "b", "c"].into_iter().map(String::from).collect();
+        adata1.set_raw(raw).unwrap();
+
+        // The raw variables of the second object are in a different order.
+        let adata2 = AnnData::<B>::new(dir.join("input2")).unwrap();
+        adata2.set_x(&x2).unwrap();
+        adata2.set_obs_names((2500..2502).map(|i| format!("cell{}", i)).collect()).unwrap();
+        adata2.set_var_names(["x", "y", "z"].into_iter().map(String::from).collect()).unwrap();
+        let mut raw = RawData::new(x2.clone());
+        raw.var_names = ["c", "a", "b"].into_iter().map(String::from).collect();
+        adata2.set_raw(raw).unwrap();
+
+        let out = AnnData::<B>::new(dir.join("output")).unwrap();
+        concat::<_, _, String>(&[adata1, adata2], JoinType::Inner, None, None, &out).unwrap();
+        let (var_names, _) = out.get_raw_var().unwrap().unwrap();
+        assert_eq!(var_names.into_vec(), vec!["a", "b", "c"]);
+        let expected =
+            ndarray::concatenate(Axis(0), &[x1.view(), x2.select(Axis(1), &[1, 2, 0]).view()])
+                .unwrap();
+        assert_eq!(
+            out.read_raw_x().unwrap().unwrap(),
+            ArrayData::from(expected.into_dyn()),
+        );
+    })
+}
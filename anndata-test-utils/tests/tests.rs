@@ -1,13 +1,15 @@
 use anndata_test_utils as utils;
 use anndata_test_utils::with_tmp_dir;
+use anndata_any::AnyBackend;
 use anndata_hdf5::H5;
 use anndata_zarr::Zarr;
-use anndata::{AnnData, Backend};
+use anndata::{backend::Mem, AnnData, Backend};
 
 #[test]
 fn test_basic() {
     utils::test_basic::<H5>();
     utils::test_basic::<Zarr>();
+    utils::test_basic::<AnyBackend>();
 }
 
 #[test]
@@ -23,10 +25,89 @@ fn test_complex_dataframe() {
     })
 }
 
+#[test]
+fn test_dataframe() {
+    utils::test_dataframe::<H5>();
+    utils::test_dataframe::<Zarr>();
+}
+
+#[test]
+fn test_awkward() {
+    utils::test_awkward::<H5>();
+    utils::test_awkward::<Zarr>();
+}
+
+#[test]
+fn test_scalar_types() {
+    utils::test_scalar_types::<H5>();
+    utils::test_scalar_types::<Zarr>();
+}
+
+#[test]
+fn test_sparse_encoding() {
+    utils::test_sparse_encoding::<H5>();
+    utils::test_sparse_encoding::<Zarr>();
+}
+
+#[test]
+fn test_in_memory() {
+    utils::test_in_memory::<H5>();
+    utils::test_in_memory::<Zarr>();
+}
+
 #[test]
 fn test_save() {
     utils::test_save::<H5>();
     utils::test_save::<Zarr>();
+    utils::test_save::<AnyBackend>();
+}
+
+#[test]
+fn test_write_policy() {
+    utils::test_write_policy::<H5>();
+    utils::test_write_policy::<Zarr>();
+}
+
+#[test]
+fn test_view() {
+    utils::test_view::<H5>();
+    utils::test_view::<Zarr>();
+}
+
+#[test]
+fn test_append_obs() {
+    utils::test_append_obs::<H5>();
+    utils::test_append_obs::<Zarr>();
+}
+
+#[test]
+fn test_append_vars() {
+    utils::test_append_vars::<H5>();
+    utils::test_append_vars::<Zarr>();
+}
+
+#[test]
+fn test_atomic_write() {
+    utils::test_atomic_write::<H5>();
+    utils::test_atomic_write::<Zarr>();
+}
+
+#[test]
+fn test_raw() {
+    utils::test_raw::<H5>();
+    utils::test_raw::<Zarr>();
+}
+
+#[test]
+fn test_replace_raw() {
+    utils::test_replace_raw::<H5>();
+    utils::test_replace_raw::<Zarr>();
+}
+
+#[test]
+fn test_concat_raw() {
+    utils::test_concat_raw::<H5>();
+    utils::test_concat_raw::<Zarr>();
 }
 
 #[test]
@@ -39,6 +120,9 @@ fn test_speacial_cases() {
         let file = dir.join("test.zarr");
         let adata_gen = || AnnData::<Zarr>::new(&file).unwrap();
         utils::test_speacial_cases(|| adata_gen());
+
+        let adata_gen = || AnnData::<Mem>::new("memory").unwrap();
+        utils::test_speacial_cases(|| adata_gen());
     })
 }
 
@@ -52,6 +136,9 @@ fn test_noncanonical() {
         let file = dir.join("test.zarr");
         let adata_gen = || AnnData::<Zarr>::new(&file).unwrap();
         utils::test_noncanonical(|| adata_gen());
+
+        let adata_gen = || AnnData::<Mem>::new("memory").unwrap();
+        utils::test_noncanonical(|| adata_gen());
     })
 }
 
@@ -65,6 +152,9 @@ fn test_io() {
         let file = dir.join("test.zarr");
         let adata_gen = || AnnData::<Zarr>::new(&file).unwrap();
         utils::test_io(|| adata_gen());
+
+        let adata_gen = || AnnData::<Mem>::new("memory").unwrap();
+        utils::test_io(|| adata_gen());
     })
 }
 
@@ -78,6 +168,9 @@ fn test_index_h5() {
         let file = dir.join("test.zarr");
         let adata_gen = || AnnData::<Zarr>::new(&file).unwrap();
         utils::test_index(|| adata_gen());
+
+        let adata_gen = || AnnData::<Mem>::new("memory").unwrap();
+        utils::test_index(|| adata_gen());
     })
 }
 
@@ -91,6 +184,9 @@ fn test_iterator() {
         let file = dir.join("test.zarr");
         let adata_gen = || AnnData::<Zarr>::new(&file).unwrap();
         utils::test_iterator(|| adata_gen());
+
+        let adata_gen = || AnnData::<Mem>::new("memory").unwrap();
+        utils::test_iterator(|| adata_gen());
     })
 }
 
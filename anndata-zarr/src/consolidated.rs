@@ -0,0 +1,266 @@
+use crate::ZarrVersion;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, RwLock},
+};
+use zarrs::storage::{
+    byte_range::ByteRange, Bytes, ListableStorageTraits, MaybeBytes, ReadableStorageTraits,
+    ReadableWritableListableStorageTraits, StorageError, StoreKey, StoreKeyOffsetValue, StoreKeys,
+    StoreKeysPrefixes, StorePrefix, WritableStorageTraits,
+};
+
+/// Metadata documents of groups and arrays.
+const METADATA_FILES: [&str; 4] = ["zarr.json", ".zgroup", ".zarray", ".zattrs"];
+
+/// Storage adapter that serves group and array metadata from the consolidated
+/// metadata of the store.
+///
+/// Consolidated metadata is stored inline in the root `zarr.json` for v3 stores,
+/// and in `.zmetadata` for v2 stores, as done by zarr-python. It is used only
+/// while it is up to date: the first change to any metadata document removes it,
+/// and [`ConsolidatedStore::consolidate`] writes it again.
+pub(crate) struct ConsolidatedStore {
+    inner: Arc<dyn ReadableWritableListableStorageTraits>,
+    version: ZarrVersion,
+    writable: bool,
+    /// Metadata documents keyed by their store keys. `None` if the store has no
+    /// consolidated metadata or it is out of date.
+    metadata: RwLock<Option<BTreeMap<String, Value>>>,
+}
+
+impl ConsolidatedStore {
+    pub fn new(inner: Arc<dyn ReadableWritableListableStorageTraits>, version: ZarrVersion, writable: bool) -> Self {
+        Self {
+            inner,
+            version,
+            writable,
+            metadata: RwLock::new(None),
+        }
+    }
+
+    /// Load the consolidated metadata of an existing store, if any.
+    pub fn open(inner: Arc<dyn ReadableWritableListableStorageTraits>, version: ZarrVersion, writable: bool) -> Result<Self> {
+        let metadata = match version {
+            ZarrVersion::V3 => read_json(&*inner, "zarr.json")?.and_then(|mut root| {
+                let nodes = root.as_object_mut()?.remove("consolidated_metadata")?;
+                let mut metadata: BTreeMap<String, Value> = nodes
+                    .get("metadata")?
+                    .as_object()?
+                    .iter()
+                    .map(|(path, value)| (format!("{}/zarr.json", path), value.clone()))
+                    .collect();
+                metadata.insert("zarr.json".to_string(), root);
+                Some(metadata)
+            }),
+            ZarrVersion::V2 => read_json(&*inner, ".zmetadata")?.and_then(|consolidated| {
+                let metadata = consolidated.get("metadata")?.as_object()?;
+                Some(metadata.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            }),
+        };
+        Ok(Self {
+            inner,
+            version,
+            writable,
+            metadata: RwLock::new(metadata),
+        })
+    }
+
+    /// Write the consolidated metadata, unless it is up to date or the store is
+    /// read-only.
+    pub fn consolidate(&self) -> Result<()> {
+        let mut metadata = self.metadata.write().unwrap();
+        if !self.writable || metadata.is_some() {
+            return Ok(());
+        }
+
+        let mut nodes = BTreeMap::new();
+        self.collect("", &mut nodes)?;
+        match self.version {
+            ZarrVersion::V3 => {
+                let mut root = nodes.get("zarr.json").cloned().unwrap_or_else(|| json!({}));
+                let children: serde_json::Map<String, Value> = nodes
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let path = key.strip_suffix("/zarr.json")?;
+                        Some((path.to_string(), value.clone()))
+                    })
+                    .collect();
+                root["consolidated_metadata"] = json!({
+                    "kind": "inline",
+                    "must_understand": false,
+                    "metadata": children,
+                });
+                self.inner.set(&StoreKey::new("zarr.json")?, serde_json::to_vec(&root)?.into())?;
+            }
+            ZarrVersion::V2 => {
+                let consolidated = json!({
+                    "metadata": nodes,
+                    "zarr_consolidated_format": 1,
+                });
+                self.inner.set(&StoreKey::new(".zmetadata")?, serde_json::to_vec(&consolidated)?.into())?;
+            }
+        }
+        *metadata = Some(nodes);
+        Ok(())
+    }
+
+    /// Read the metadata documents of the node at `prefix` and its descendants.
+    fn collect(&self, prefix: &str, nodes: &mut BTreeMap<String, Value>) -> Result<()> {
+        let mut is_group = false;
+        for file in METADATA_FILES {
+            let key = format!("{}{}", prefix, file);
+            if let Some(mut value) = read_json(&*self.inner, &key)? {
+                if let Some(value) = value.as_object_mut() {
+                    value.remove("consolidated_metadata");
+                }
+                is_group |= file == ".zgroup" || value["node_type"] == "group";
+                nodes.insert(key, value);
+            }
+        }
+        if is_group {
+            for child in self.inner.list_dir(&StorePrefix::new(prefix)?)?.prefixes() {
+                self.collect(child.as_str(), nodes)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the consolidated metadata before a metadata document changes.
+    fn invalidate(&self) -> Result<(), StorageError> {
+        let mut metadata = self.metadata.write().unwrap();
+        if metadata.take().is_none() {
+            return Ok(());
+        }
+        match self.version {
+            ZarrVersion::V3 => {
+                let key = StoreKey::new("zarr.json").map_err(other)?;
+                if let Some(mut root) = read_json(&*self.inner, key.as_str()).map_err(other)? {
+                    if let Some(root) = root.as_object_mut() {
+                        root.remove("consolidated_metadata");
+                    }
+                    self.inner.set(&key, serde_json::to_vec(&root).map_err(other)?.into())?;
+                }
+            }
+            ZarrVersion::V2 => {
+                self.inner.erase(&StoreKey::new(".zmetadata").map_err(other)?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_json(store: &dyn ReadableWritableListableStorageTraits, key: &str) -> Result<Option<Value>> {
+    match store.get(&StoreKey::new(key)?)? {
+        Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+fn is_metadata_key(key: &str) -> bool {
+    METADATA_FILES.contains(&key.rsplit('/').next().unwrap_or(key))
+}
+
+fn other<E: ToString>(e: E) -> StorageError {
+    StorageError::Other(e.to_string())
+}
+
+impl ReadableStorageTraits for ConsolidatedStore {
+    fn get(&self, key: &StoreKey) -> Result<MaybeBytes, StorageError> {
+        if is_metadata_key(key.as_str()) {
+            if let Some(metadata) = self.metadata.read().unwrap().as_ref() {
+                return metadata
+                    .get(key.as_str())
+                    .map(|value| serde_json::to_vec(value).map(Bytes::from).map_err(other))
+                    .transpose();
+            }
+        }
+        self.inner.get(key)
+    }
+
+    fn get_partial_values_key(
+        &self,
+        key: &StoreKey,
+        byte_ranges: &[ByteRange],
+    ) -> Result<Option<Vec<Bytes>>, StorageError> {
+        self.inner.get_partial_values_key(key, byte_ranges)
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        self.inner.size_key(key)
+    }
+}
+
+impl ListableStorageTraits for ConsolidatedStore {
+    fn list(&self) -> Result<StoreKeys, StorageError> {
+        self.inner.list()
+    }
+
+    fn list_prefix(&self, prefix: &StorePrefix) -> Result<StoreKeys, StorageError> {
+        self.inner.list_prefix(prefix)
+    }
+
+    /// Children of a group are listed from the consolidated metadata, when
+    /// available. Chunks are not included in this case.
+    fn list_dir(&self, prefix: &StorePrefix) -> Result<StoreKeysPrefixes, StorageError> {
+        let metadata = self.metadata.read().unwrap();
+        let Some(metadata) = metadata.as_ref() else {
+            return self.inner.list_dir(prefix);
+        };
+        let mut keys = Vec::new();
+        let mut prefixes = BTreeSet::new();
+        for key in metadata.keys() {
+            if let Some(rest) = key.strip_prefix(prefix.as_str()) {
+                match rest.split_once('/') {
+                    Some((dir, _)) => {
+                        prefixes.insert(format!("{}{}/", prefix.as_str(), dir));
+                    }
+                    None => keys.push(StoreKey::new(key.as_str()).map_err(other)?),
+                }
+            }
+        }
+        let prefixes = prefixes
+            .into_iter()
+            .map(|p| StorePrefix::new(p).map_err(other))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(StoreKeysPrefixes::new(keys, prefixes))
+    }
+
+    fn size_prefix(&self, prefix: &StorePrefix) -> Result<u64, StorageError> {
+        self.inner.size_prefix(prefix)
+    }
+
+    fn size(&self) -> Result<u64, StorageError> {
+        self.inner.size()
+    }
+}
+
+impl WritableStorageTraits for ConsolidatedStore {
+    fn set(&self, key: &StoreKey, value: Bytes) -> Result<(), StorageError> {
+        if is_metadata_key(key.as_str()) {
+            self.invalidate()?;
+        }
+        self.inner.set(key, value)
+    }
+
+    fn set_partial_values(
+        &self,
+        key_offset_values: &[StoreKeyOffsetValue],
+    ) -> Result<(), StorageError> {
+        self.inner.set_partial_values(key_offset_values)
+    }
+
+    fn erase(&self, key: &StoreKey) -> Result<(), StorageError> {
+        if is_metadata_key(key.as_str()) {
+            self.invalidate()?;
+        }
+        self.inner.erase(key)
+    }
+
+    fn erase_prefix(&self, prefix: &StorePrefix) -> Result<(), StorageError> {
+        self.invalidate()?;
+        self.inner.erase_prefix(prefix)
+    }
+}
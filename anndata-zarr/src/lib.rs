@@ -1,27 +1,65 @@
+mod consolidated;
+#[cfg(feature = "object-store")]
+mod remote_store;
+mod zip_store;
+
 use anndata::{
     backend::*,
     data::{DynArray, DynCowArray, SelectInfoBounds, SelectInfoElem, SelectInfoElemBounds, Shape},
 };
 
-use anyhow::{bail, Context, Result};
-use ndarray::{Array, ArrayD, ArrayView, CowArray, Dimension, IxDyn, SliceInfoElem};
+use anyhow::{bail, ensure, Context, Result};
+use ndarray::{Array, ArrayD, CowArray, Dimension, IxDyn};
 use std::{
-    borrow::Cow, ops::{Deref, Index}, path::{Path, PathBuf}
+    borrow::Cow, ops::{Deref, Range}, path::{Path, PathBuf}
 };
-use std::{sync::Arc, vec};
-use zarrs::{array::{data_type::DataType, Element}, array_subset::ArraySubset, storage::StorePrefix};
+use std::{collections::BTreeMap, sync::Arc, vec};
+use serde_json::json;
+use zarrs::{array::data_type::DataType, array_subset::ArraySubset, storage::{StoreKey, StorePrefix}};
 use zarrs::filesystem::FilesystemStore;
 use zarrs::group::Group;
 use zarrs::{array::ElementOwned, storage::ReadableWritableListableStorageTraits};
-use zarrs::array::codec::bytes_to_bytes::zstd::ZstdCodec;
+use zarrs::array::codec::{
+    array_to_bytes::sharding::ShardingCodecBuilder,
+    bytes_to_bytes::blosc::{BloscCodec, BloscCompressionLevel, BloscShuffleMode},
+    bytes_to_bytes::gzip::GzipCodec,
+    bytes_to_bytes::zstd::ZstdCodec,
+    BytesToBytesCodecTraits,
+};
+use consolidated::ConsolidatedStore;
+use zip_store::{is_zip, ZipStore};
 
 /// The Zarr backend.
 pub struct Zarr;
 
+/// Version of the Zarr format used when creating groups and arrays.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ZarrVersion {
+    /// `.zgroup`, `.zarray` and `.zattrs` files, as written by Python anndata
+    /// with zarr-python 2.
+    V2,
+    /// `zarr.json` files.
+    #[default]
+    V3,
+}
+
 #[derive(Clone)]
 pub struct ZarrStore {
     inner: Arc<dyn ReadableWritableListableStorageTraits>,
     path: PathBuf,
+    version: ZarrVersion,
+    /// The same storage as `inner`, for writing the consolidated metadata on close.
+    consolidated: Arc<ConsolidatedStore>,
+    /// Set when the store is a zip archive, whose changes must be flushed on close.
+    zip: Option<Arc<ZipStore>>,
+}
+
+impl ZarrStore {
+    /// Returns the version of the Zarr format used by the store. New groups
+    /// and arrays are created with the same version.
+    pub fn version(&self) -> ZarrVersion {
+        self.version
+    }
 }
 
 impl Deref for ZarrStore {
@@ -53,6 +91,28 @@ impl Backend for Zarr {
     type Dataset = ZarrDataset;
 
     fn new<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        Zarr::new_with_version(path, ZarrVersion::default())
+    }
+
+    /// Opens a file as read-only, file must exist.
+    fn open<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        open_store(path, false)
+    }
+
+    /// Opens a file as read/write, file must exist.
+    fn open_rw<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        open_store(path, true)
+    }
+}
+
+impl Zarr {
+    /// Create a new store using the given version of the Zarr format.
+    ///
+    /// Paths ending with `.zip`, e.g., `data.zarr.zip`, create a store inside
+    /// a zip archive. All data written to a zip store is buffered in memory and
+    /// the whole archive is rewritten when the store is closed, so zip stores
+    /// are only suited to small data.
+    pub fn new_with_version<P: AsRef<Path>>(path: P, version: ZarrVersion) -> Result<ZarrStore> {
         if path.as_ref().try_exists()? {
             let metadata = std::fs::metadata(&path)?;
             if metadata.is_file() {
@@ -62,42 +122,136 @@ impl Backend for Zarr {
             }
         }
 
-        let inner = Arc::new(FilesystemStore::new(path.as_ref())?);
-        zarrs::group::GroupBuilder::new().build(inner.clone(), "/")?.store_metadata()?;
-        Ok(ZarrStore {
+        let zip = is_zip(&path).then(|| Arc::new(ZipStore::new(path.as_ref().to_path_buf())));
+        let storage: Arc<dyn ReadableWritableListableStorageTraits> = match &zip {
+            Some(zip) => zip.clone(),
+            None => Arc::new(FilesystemStore::new(path.as_ref())?),
+        };
+        let consolidated = Arc::new(ConsolidatedStore::new(storage, version, true));
+        let store = ZarrStore {
             path: path.as_ref().to_path_buf(),
-            inner,
-        })
+            inner: consolidated.clone(),
+            version,
+            consolidated,
+            zip,
+        };
+        create_group(&store, "/")?;
+        Ok(store)
     }
+}
 
-    /// Opens a file as read-only, file must exist.
-    fn open<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
-        Ok(ZarrStore {
-            path: path.as_ref().to_path_buf(),
-            inner: Arc::new(FilesystemStore::new(path)?),
-        })
+#[cfg(feature = "object-store")]
+impl Zarr {
+    /// Open a store in object storage as read-only, e.g., `s3://bucket/atlas.zarr`.
+    ///
+    /// `options` configures the object store. For S3-compatible storage, these
+    /// include `aws_endpoint`, `aws_region`, `aws_access_key_id`,
+    /// `aws_secret_access_key` and `aws_allow_http`. See
+    /// [`object_store::parse_url_opts`] for the supported URLs and options.
+    pub fn open_url<I, K, V>(url: &str, options: I) -> Result<ZarrStore>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<String>,
+    {
+        let (store, prefix) = parse_url(url, options)?;
+        let storage = remote_store::RemoteStore::new(store, prefix.as_ref())?;
+        open_storage(Arc::new(storage), PathBuf::from(url), false, None)
     }
 
-    /// Opens a file as read/write, file must exist.
-    fn open_rw<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
-        Ok(ZarrStore {
-            path: path.as_ref().to_path_buf(),
-            inner: Arc::new(FilesystemStore::new(path)?),
-        })
+    /// Open a store located at `prefix` in an object store as read-only.
+    pub fn open_object_store(
+        store: Arc<dyn object_store::ObjectStore>,
+        prefix: &str,
+    ) -> Result<ZarrStore> {
+        let storage = remote_store::RemoteStore::new(store, prefix)?;
+        open_storage(Arc::new(storage), PathBuf::from(prefix), false, None)
     }
 }
 
+/// Build the object store of `url` and return it with the location of the
+/// Zarr hierarchy within it.
+#[cfg(feature = "object-store")]
+fn parse_url<I, K, V>(
+    url: &str,
+    options: I,
+) -> Result<(Arc<dyn object_store::ObjectStore>, object_store::path::Path)>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: Into<String>,
+{
+    let parsed = url::Url::parse(url).with_context(|| format!("invalid URL: {}", url))?;
+    let (store, prefix) = object_store::parse_url_opts(&parsed, options)
+        .with_context(|| format!("cannot configure the object store of {}", url))?;
+    Ok((Arc::from(store), prefix))
+}
+
+fn open_store<P: AsRef<Path>>(path: P, writable: bool) -> Result<ZarrStore> {
+    // A store kept in a single file can only be a zip archive.
+    let zip = if is_zip(&path) || path.as_ref().is_file() {
+        let zip = ZipStore::open(path.as_ref().to_path_buf(), writable)
+            .with_context(|| format!("cannot open zip archive: {}", path.as_ref().display()))?;
+        Some(Arc::new(zip))
+    } else {
+        None
+    };
+    let storage: Arc<dyn ReadableWritableListableStorageTraits> = match &zip {
+        Some(zip) => zip.clone(),
+        None => Arc::new(FilesystemStore::new(path.as_ref())?),
+    };
+    open_storage(storage, path.as_ref().to_path_buf(), writable, zip)
+}
+
+fn open_storage(
+    storage: Arc<dyn ReadableWritableListableStorageTraits>,
+    path: PathBuf,
+    writable: bool,
+    zip: Option<Arc<ZipStore>>,
+) -> Result<ZarrStore> {
+    let version = if storage.get(&metadata_key("/", "zarr.json")?)?.is_none()
+        && storage.get(&metadata_key("/", ".zgroup")?)?.is_some()
+    {
+        ZarrVersion::V2
+    } else {
+        ZarrVersion::V3
+    };
+    let consolidated = Arc::new(ConsolidatedStore::open(storage, version, writable)?);
+    Ok(ZarrStore {
+        path,
+        inner: consolidated.clone(),
+        version,
+        consolidated,
+        zip,
+    })
+}
+
 impl StoreOp<Zarr> for ZarrStore {
     /// Returns the file path.
     fn filename(&self) -> PathBuf {
         self.path.clone()
     }
 
-    /// Close the file.
+    /// Close the file. The consolidated metadata is written if the hierarchy
+    /// has changed.
     fn close(self) -> Result<()> {
+        self.consolidated.consolidate()?;
+        if let Some(zip) = &self.zip {
+            zip.flush()?;
+        }
         drop(self);
         Ok(())
     }
+
+    /// Write the consolidated metadata and, for zip archives, the pending
+    /// changes.
+    fn flush(&self) -> Result<()> {
+        self.consolidated.consolidate()?;
+        if let Some(zip) = &self.zip {
+            zip.flush()?;
+        }
+        Ok(())
+    }
 }
 
 impl GroupOp<Zarr> for ZarrStore {
@@ -109,13 +263,7 @@ impl GroupOp<Zarr> for ZarrStore {
 
     /// Create a new group.
     fn new_group(&self, name: &str) -> Result<<Zarr as Backend>::Group> {
-        let path = canoincalize_path(name);
-        let group = zarrs::group::GroupBuilder::new().build(self.inner.clone(), &path)?;
-        group.store_metadata()?;
-        Ok(ZarrGroup {
-            group,
-            store: self.clone(),
-        })
+        create_group(self, &canoincalize_path(name))
     }
 
     /// Open an existing group.
@@ -136,8 +284,8 @@ impl GroupOp<Zarr> for ZarrStore {
     ) -> Result<<Zarr as Backend>::Dataset> {
         let path = canoincalize_path(name);
         let shape = shape.as_ref();
-        let sizes: Vec<u64> = match config.block_size {
-            Some(s) => s.as_ref().into_iter().map(|x| (*x).max(1) as u64).collect(),
+        let sizes: Vec<u64> = match &config.block_size {
+            Some(s) => s.as_ref().iter().map(|x| (*x).max(1) as u64).collect(),
             _ => {
                 if shape.len() == 1 {
                     vec![shape[0].min(10000).max(1) as u64]
@@ -146,40 +294,7 @@ impl GroupOp<Zarr> for ZarrStore {
                 }
             }
         };
-        let chunk_size = zarrs::array::chunk_grid::ChunkGrid::new(
-            zarrs::array::chunk_grid::regular::RegularChunkGrid::new(sizes.try_into().unwrap()),
-        );
-
-        let (datatype, fill) = match T::DTYPE {
-            ScalarType::U8 => (DataType::UInt8, 0u8.into()),
-            ScalarType::U16 => (DataType::UInt16, 0u16.into()),
-            ScalarType::U32 => (DataType::UInt32, 0u32.into()),
-            ScalarType::U64 => (DataType::UInt64, 0u64.into()),
-            ScalarType::I8 => (DataType::Int8, 0i8.into()),
-            ScalarType::I16 => (DataType::Int16, 0i16.into()),
-            ScalarType::I32 => (DataType::Int32, 0i32.into()),
-            ScalarType::I64 => (DataType::Int64, 0i64.into()),
-            ScalarType::F32 => (DataType::Float32, zarrs::array::ZARR_NAN_F32.into()),
-            ScalarType::F64 => (DataType::Float64, zarrs::array::ZARR_NAN_F64.into()),
-            ScalarType::Bool => (DataType::Bool, false.into()),
-            ScalarType::String => (DataType::String, "".into()),
-        };
-
-        let array = zarrs::array::ArrayBuilder::new(
-            shape.iter().map(|x| *x as u64).collect(),
-            datatype,
-            chunk_size,
-            fill,
-        )
-        .bytes_to_bytes_codecs(vec![
-            Arc::new(ZstdCodec::new(7, false))
-        ])
-        .build(self.inner.clone(), &path)?;
-        array.store_metadata()?;
-        Ok(ZarrDataset {
-            dataset: array,
-            store: self.clone(),
-        })
+        create_array::<T>(self, &path, shape, sizes, &config)
     }
 
     fn open_dataset(&self, name: &str) -> Result<<Zarr as Backend>::Dataset> {
@@ -196,6 +311,10 @@ impl GroupOp<Zarr> for ZarrStore {
         Ok(())
     }
 
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        rename_prefix(self, from, to)
+    }
+
     /// Check if a group or dataset exists.
     fn exists(&self, name: &str) -> Result<bool> {
         let path = format!("/{}", name);
@@ -222,12 +341,7 @@ impl GroupOp<Zarr> for ZarrGroup {
     /// Create a new group.
     fn new_group(&self, name: &str) -> Result<<Zarr as Backend>::Group> {
         let path = self.group.path().as_path().join(name);
-        let group = zarrs::group::GroupBuilder::new().build(self.store.inner.clone(), path.to_str().unwrap())?;
-        group.store_metadata()?;
-        Ok(ZarrGroup {
-            group,
-            store: self.store.clone(),
-        })
+        create_group(&self.store, path.to_str().unwrap())
     }
 
     /// Open an existing group.
@@ -248,8 +362,8 @@ impl GroupOp<Zarr> for ZarrGroup {
         config: WriteConfig,
     ) -> Result<<Zarr as Backend>::Dataset> {
         let shape = shape.as_ref();
-        let sizes: Vec<u64> = match config.block_size {
-            Some(s) => s.as_ref().into_iter().map(|x| (*x).max(1) as u64).collect(),
+        let sizes: Vec<u64> = match &config.block_size {
+            Some(s) => s.as_ref().iter().map(|x| (*x).max(1) as u64).collect(),
             _ => {
                 if shape.len() == 1 {
                     vec![shape[0].min(20000).max(1) as u64]
@@ -258,41 +372,8 @@ impl GroupOp<Zarr> for ZarrGroup {
                 }
             }
         };
-        let chunk_size = zarrs::array::chunk_grid::ChunkGrid::new(
-            zarrs::array::chunk_grid::regular::RegularChunkGrid::new(sizes.try_into().unwrap()),
-        );
-
-        let (datatype, fill) = match T::DTYPE {
-            ScalarType::U8 => (DataType::UInt8, 0u8.into()),
-            ScalarType::U16 => (DataType::UInt16, 0u16.into()),
-            ScalarType::U32 => (DataType::UInt32, 0u32.into()),
-            ScalarType::U64 => (DataType::UInt64, 0u64.into()),
-            ScalarType::I8 => (DataType::Int8, 0i8.into()),
-            ScalarType::I16 => (DataType::Int16, 0i16.into()),
-            ScalarType::I32 => (DataType::Int32, 0i32.into()),
-            ScalarType::I64 => (DataType::Int64, 0i64.into()),
-            ScalarType::F32 => (DataType::Float32, zarrs::array::ZARR_NAN_F32.into()),
-            ScalarType::F64 => (DataType::Float64, zarrs::array::ZARR_NAN_F64.into()),
-            ScalarType::Bool => (DataType::Bool, false.into()),
-            ScalarType::String => (DataType::String, "".into()),
-        };
-
         let path = self.group.path().as_path().join(name);
-        let array = zarrs::array::ArrayBuilder::new(
-            shape.iter().map(|x| *x as u64).collect(),
-            datatype,
-            chunk_size,
-            fill,
-        )
-        .bytes_to_bytes_codecs(vec![
-            Arc::new(ZstdCodec::new(7, false))
-        ])
-        .build(self.store.inner.clone(), path.to_str().unwrap())?;
-        array.store_metadata()?;
-        Ok(ZarrDataset {
-            dataset: array,
-            store: self.store.clone(),
-        })
+        create_array::<T>(&self.store, path.to_str().unwrap(), shape, sizes, &config)
     }
 
     fn open_dataset(&self, name: &str) -> Result<<Zarr as Backend>::Dataset> {
@@ -311,6 +392,11 @@ impl GroupOp<Zarr> for ZarrGroup {
         Ok(())
     }
 
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let path = self.group.path().as_str();
+        rename_prefix(&self.store, &format!("{}/{}", path, from), &format!("{}/{}", path, to))
+    }
+
     /// Check if a group or dataset exists.
     fn exists(&self, name: &str) -> Result<bool> {
         let path = self
@@ -352,6 +438,19 @@ impl AttributeOp<Zarr> for ZarrGroup {
             .with_context(|| format!("Attribute {} not found", name))?.clone()
         )
     }
+
+    fn has_attr(&self, name: &str) -> Result<bool> {
+        Ok(self.group.attributes().contains_key(name))
+    }
+
+    /// Delete an attribute. The metadata is only rewritten if the attribute
+    /// exists.
+    fn delete_attr(&mut self, name: &str) -> Result<()> {
+        if self.group.attributes_mut().remove(name).is_some() {
+            self.group.store_metadata()?;
+        }
+        Ok(())
+    }
 }
 
 impl AttributeOp<Zarr> for ZarrDataset {
@@ -380,6 +479,19 @@ impl AttributeOp<Zarr> for ZarrDataset {
             .with_context(|| format!("Attribute {} not found", name))?.clone()
         )
     }
+
+    fn has_attr(&self, name: &str) -> Result<bool> {
+        Ok(self.dataset.attributes().contains_key(name))
+    }
+
+    /// Delete an attribute. The metadata is only rewritten if the attribute
+    /// exists.
+    fn delete_attr(&mut self, name: &str) -> Result<()> {
+        if self.dataset.attributes_mut().remove(name).is_some() {
+            self.dataset.store_metadata()?;
+        }
+        Ok(())
+    }
 }
 
 impl DatasetOp<Zarr> for ZarrDataset {
@@ -393,8 +505,11 @@ impl DatasetOp<Zarr> for ZarrDataset {
             DataType::Int16 => Ok(ScalarType::I16),
             DataType::Int32 => Ok(ScalarType::I32),
             DataType::Int64 => Ok(ScalarType::I64),
+            DataType::Float16 => Ok(ScalarType::F16),
             DataType::Float32 => Ok(ScalarType::F32),
             DataType::Float64 => Ok(ScalarType::F64),
+            DataType::Complex64 => Ok(ScalarType::Complex32),
+            DataType::Complex128 => Ok(ScalarType::Complex64),
             DataType::Bool => Ok(ScalarType::Bool),
             DataType::String => Ok(ScalarType::String),
             ty => bail!("Unsupported type: {:?}", ty),
@@ -416,7 +531,8 @@ impl DatasetOp<Zarr> for ZarrDataset {
         Ok(())
     }
 
-    /// TODO: current implementation reads the entire array and then selects the slice.
+    /// Contiguous selections are read as a single subset. Otherwise, only the
+    /// chunks containing selected elements are retrieved.
     fn read_array_slice<T: BackendData, S, D>(&self, selection: &[S]) -> Result<Array<T, D>>
     where
         S: AsRef<SelectInfoElem>,
@@ -429,20 +545,12 @@ impl DatasetOp<Zarr> for ZarrDataset {
             D: Dimension,
         {
             let sel = SelectInfoBounds::new(&selection, &dataset.shape());
-            if let Some(subset) = to_array_subset(sel) {
-                let arr = dataset
-                    .dataset
-                    .retrieve_array_subset_ndarray(&subset)?
-                    .into_dimensionality::<D>()?;
-                Ok(arr)
+            let arr = if let Some(subset) = to_array_subset(&sel) {
+                dataset.dataset.retrieve_array_subset_ndarray(&subset)?
             } else {
-                // Read the entire array and then select the slice.
-                let arr = dataset
-                    .dataset
-                    .retrieve_array_subset_ndarray(&dataset.dataset.subset_all())?
-                    .into_dimensionality::<D>()?;
-                Ok(select(arr.view(), selection))
-            }
+                read_chunks(dataset, &sel)?
+            };
+            Ok(arr.into_dimensionality::<D>()?)
         }
 
         let array: DynArray = match T::DTYPE {
@@ -454,8 +562,11 @@ impl DatasetOp<Zarr> for ZarrDataset {
             ScalarType::I16 => read_arr::<i16, _, D>(self, selection)?.into(),
             ScalarType::I32 => read_arr::<i32, _, D>(self, selection)?.into(),
             ScalarType::I64 => read_arr::<i64, _, D>(self, selection)?.into(),
+            ScalarType::F16 => read_arr::<f16, _, D>(self, selection)?.into(),
             ScalarType::F32 => read_arr::<f32, _, D>(self, selection)?.into(),
             ScalarType::F64 => read_arr::<f64, _, D>(self, selection)?.into(),
+            ScalarType::Complex32 => read_arr::<Complex32, _, D>(self, selection)?.into(),
+            ScalarType::Complex64 => read_arr::<Complex64, _, D>(self, selection)?.into(),
             ScalarType::Bool => read_arr::<bool, _, D>(self, selection)?.into(),
             ScalarType::String => read_arr::<String, _, D>(self, selection)?.into(),
         };
@@ -474,30 +585,37 @@ impl DatasetOp<Zarr> for ZarrDataset {
             selection: &[S],
         ) -> Result<()>
         where
-            T: Element + 'static,
+            T: ElementOwned + Clone,
             S: AsRef<SelectInfoElem>,
         {
-            let selection = SelectInfoBounds::new(&selection, &container.shape());
-            let starts: Vec<_> = selection
+            let shape = container.shape();
+            ensure!(
+                selection.len() == shape.ndim(),
+                "the selection has {} dimensions, but the array has {}",
+                selection.len(),
+                shape.ndim()
+            );
+            selection
                 .iter()
-                .flat_map(|x| {
-                    if let SelectInfoElemBounds::Slice(slice) = x {
-                        if slice.step == 1 {
-                            Some(slice.start as u64)
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-            if starts.len() == selection.ndim() {
+                .zip(shape.as_ref())
+                .try_for_each(|(x, n)| x.as_ref().bound_check(*n))?;
+            let selection = SelectInfoBounds::new(&selection, &shape);
+            ensure!(
+                selection.out_shape().as_ref() == arr.shape(),
+                "cannot write an array of shape {:?} to a selection of shape {:?}",
+                arr.shape(),
+                selection.out_shape().as_ref()
+            );
+            // zarrs cannot store zero-sized subsets, and there is nothing to write.
+            if arr.is_empty() {
+                return Ok(());
+            }
+            if let Some(subset) = to_array_subset(&selection) {
                 container
                     .dataset
-                    .store_array_subset_ndarray(starts.as_slice(), arr.into_owned())?;
+                    .store_array_subset_ndarray(subset.start(), arr.into_owned())?;
             } else {
-                panic!("Not implemented");
+                write_chunks(container, &selection, arr)?;
             }
             Ok(())
         }
@@ -511,50 +629,369 @@ impl DatasetOp<Zarr> for ZarrDataset {
             DynCowArray::I16(x) => write_array_impl(self, x, selection),
             DynCowArray::I32(x) => write_array_impl(self, x, selection),
             DynCowArray::I64(x) => write_array_impl(self, x, selection),
+            DynCowArray::F16(x) => write_array_impl(self, x, selection),
             DynCowArray::F32(x) => write_array_impl(self, x, selection),
             DynCowArray::F64(x) => write_array_impl(self, x, selection),
+            DynCowArray::Complex32(x) => write_array_impl(self, x, selection),
+            DynCowArray::Complex64(x) => write_array_impl(self, x, selection),
             DynCowArray::Bool(x) => write_array_impl(self, x, selection),
             DynCowArray::String(x) => write_array_impl(self, x, selection),
         }
     }
 }
 
-fn select<'a, S, T, D>(arr: ArrayView<'a, T, D>, info: &[S]) -> Array<T, D>
+fn create_group(store: &ZarrStore, path: &str) -> Result<ZarrGroup> {
+    let group = match store.version {
+        ZarrVersion::V3 => {
+            let group = zarrs::group::GroupBuilder::new().build(store.inner.clone(), path)?;
+            group.store_metadata()?;
+            group
+        }
+        ZarrVersion::V2 => {
+            let metadata = json!({"zarr_format": 2});
+            store.set(&metadata_key(path, ".zgroup")?, serde_json::to_vec(&metadata)?.into())?;
+            zarrs::group::Group::open(store.inner.clone(), path)?
+        }
+    };
+    Ok(ZarrGroup {
+        group,
+        store: store.clone(),
+    })
+}
+
+fn create_array<T: BackendData>(
+    store: &ZarrStore,
+    path: &str,
+    shape: &[usize],
+    chunks: Vec<u64>,
+    config: &WriteConfig,
+) -> Result<ZarrDataset> {
+    // A contiguous array is stored as a single uncompressed chunk.
+    let contiguous;
+    let (chunks, config) = if config.layout == Layout::Contiguous {
+        contiguous = WriteConfig {
+            compression: None,
+            shard_size: None,
+            ..config.clone()
+        };
+        let chunks = shape.iter().map(|x| (*x).max(1) as u64).collect();
+        (chunks, &contiguous)
+    } else {
+        (chunks, config)
+    };
+
+    let array = match store.version {
+        ZarrVersion::V3 => {
+            let (datatype, fill) = match T::DTYPE {
+                ScalarType::U8 => (DataType::UInt8, 0u8.into()),
+                ScalarType::U16 => (DataType::UInt16, 0u16.into()),
+                ScalarType::U32 => (DataType::UInt32, 0u32.into()),
+                ScalarType::U64 => (DataType::UInt64, 0u64.into()),
+                ScalarType::I8 => (DataType::Int8, 0i8.into()),
+                ScalarType::I16 => (DataType::Int16, 0i16.into()),
+                ScalarType::I32 => (DataType::Int32, 0i32.into()),
+                ScalarType::I64 => (DataType::Int64, 0i64.into()),
+                ScalarType::F16 => (DataType::Float16, zarrs::array::ZARR_NAN_F16.into()),
+                ScalarType::F32 => (DataType::Float32, zarrs::array::ZARR_NAN_F32.into()),
+                ScalarType::F64 => (DataType::Float64, zarrs::array::ZARR_NAN_F64.into()),
+                ScalarType::Complex32 => (DataType::Complex64, Complex32::new(0.0, 0.0).into()),
+                ScalarType::Complex64 => (DataType::Complex128, Complex64::new(0.0, 0.0).into()),
+                ScalarType::Bool => (DataType::Bool, false.into()),
+                ScalarType::String => (DataType::String, "".into()),
+            };
+            let codecs = bytes_to_bytes_codecs::<T>(config)?;
+
+            // With sharding, the chunk grid of the array is made of shards,
+            // and the chunks become the inner chunks of the sharding codec.
+            let grid = match &config.shard_size {
+                Some(shard) => shard_shape(shard, &chunks)?,
+                None => chunks.clone(),
+            };
+            let chunk_grid = zarrs::array::chunk_grid::ChunkGrid::new(
+                zarrs::array::chunk_grid::regular::RegularChunkGrid::new(grid.try_into()?),
+            );
+            let mut builder = zarrs::array::ArrayBuilder::new(
+                shape.iter().map(|x| *x as u64).collect(),
+                datatype.clone(),
+                chunk_grid,
+                fill,
+            );
+            if config.shard_size.is_some() {
+                let mut sharding = ShardingCodecBuilder::new(chunks.try_into()?);
+                sharding.bytes_to_bytes_codecs(codecs);
+                builder.array_to_bytes_codec(Arc::new(sharding.build()));
+            } else {
+                builder.bytes_to_bytes_codecs(codecs);
+            }
+            let array = builder.build(store.inner.clone(), path)?;
+            array.store_metadata()?;
+            array
+        }
+        ZarrVersion::V2 => {
+            ensure!(
+                config.shard_size.is_none(),
+                "sharding is only supported by Zarr v3 stores"
+            );
+            let (dtype, fill) = match T::DTYPE {
+                ScalarType::U8 => ("|u1", json!(0)),
+                ScalarType::U16 => ("<u2", json!(0)),
+                ScalarType::U32 => ("<u4", json!(0)),
+                ScalarType::U64 => ("<u8", json!(0)),
+                ScalarType::I8 => ("|i1", json!(0)),
+                ScalarType::I16 => ("<i2", json!(0)),
+                ScalarType::I32 => ("<i4", json!(0)),
+                ScalarType::I64 => ("<i8", json!(0)),
+                ScalarType::F16 => ("<f2", json!("NaN")),
+                ScalarType::F32 => ("<f4", json!("NaN")),
+                ScalarType::F64 => ("<f8", json!("NaN")),
+                ScalarType::Complex32 => ("<c8", json!([0.0, 0.0])),
+                ScalarType::Complex64 => ("<c16", json!([0.0, 0.0])),
+                ScalarType::Bool => ("|b1", json!(false)),
+                ScalarType::String => ("|O", json!("")),
+            };
+            let filters = if T::DTYPE == ScalarType::String {
+                json!([{"id": "vlen-utf8"}])
+            } else {
+                Value::Null
+            };
+            let metadata = json!({
+                "zarr_format": 2,
+                "shape": shape,
+                "chunks": chunks,
+                "dtype": dtype,
+                "compressor": compressor_v2::<T>(config),
+                "fill_value": fill,
+                "order": "C",
+                "filters": filters,
+            });
+            store.set(&metadata_key(path, ".zarray")?, serde_json::to_vec(&metadata)?.into())?;
+            zarrs::array::Array::open(store.inner.clone(), path)?
+        }
+    };
+    Ok(ZarrDataset {
+        dataset: array,
+        store: store.clone(),
+    })
+}
+
+/// Compression codecs of v3 arrays.
+fn bytes_to_bytes_codecs<T: BackendData>(
+    config: &WriteConfig,
+) -> Result<Vec<Arc<dyn BytesToBytesCodecTraits>>> {
+    let codec: Arc<dyn BytesToBytesCodecTraits> = match config.compression {
+        None => return Ok(Vec::new()),
+        Some(Compression::Gzip(lvl)) => Arc::new(GzipCodec::new(lvl.into())?),
+        Some(Compression::Zst(lvl)) => Arc::new(ZstdCodec::new(lvl.into(), false)),
+        Some(Compression::Blosc(compressor, lvl)) => blosc_codec::<T>(compressor, lvl, config.shuffle)?,
+    };
+    Ok(vec![codec])
+}
+
+/// Blosc codec of v3 arrays.
+fn blosc_codec<T: BackendData>(
+    compressor: BloscCompressor,
+    lvl: u8,
+    shuffle: Shuffle,
+) -> Result<Arc<dyn BytesToBytesCodecTraits>> {
+    let cname = match compressor {
+        BloscCompressor::BloscLz => zarrs::array::codec::bytes_to_bytes::blosc::BloscCompressor::BloscLZ,
+        BloscCompressor::Lz4 => zarrs::array::codec::bytes_to_bytes::blosc::BloscCompressor::LZ4,
+        BloscCompressor::Lz4hc => zarrs::array::codec::bytes_to_bytes::blosc::BloscCompressor::LZ4HC,
+        BloscCompressor::Zlib => zarrs::array::codec::bytes_to_bytes::blosc::BloscCompressor::Zlib,
+        BloscCompressor::Zstd => zarrs::array::codec::bytes_to_bytes::blosc::BloscCompressor::Zstd,
+    };
+    // Variable-length strings have no fixed element size to shuffle by.
+    let (shuffle, typesize) = match (T::DTYPE, shuffle) {
+        (ScalarType::String, _) | (_, Shuffle::None) => (BloscShuffleMode::NoShuffle, None),
+        (_, Shuffle::Byte) => (BloscShuffleMode::Shuffle, Some(std::mem::size_of::<T>())),
+        (_, Shuffle::Bit) => (BloscShuffleMode::BitShuffle, Some(std::mem::size_of::<T>())),
+    };
+    let level = BloscCompressionLevel::try_from(lvl)
+        .map_err(|_| anyhow::anyhow!("invalid blosc compression level: {}", lvl))?;
+    Ok(Arc::new(BloscCodec::new(cname, level, None, shuffle, typesize)?))
+}
+
+/// Compressor of v2 arrays, in the numcodecs format.
+fn compressor_v2<T: BackendData>(config: &WriteConfig) -> Value {
+    match config.compression {
+        None => Value::Null,
+        Some(Compression::Gzip(lvl)) => json!({"id": "gzip", "level": lvl}),
+        Some(Compression::Zst(lvl)) => json!({"id": "zstd", "level": lvl}),
+        Some(Compression::Blosc(compressor, lvl)) => blosc_v2::<T>(compressor, lvl, config.shuffle),
+    }
+}
+
+/// Blosc compressor of v2 arrays.
+fn blosc_v2<T: BackendData>(compressor: BloscCompressor, lvl: u8, shuffle: Shuffle) -> Value {
+    let cname = match compressor {
+        BloscCompressor::BloscLz => "blosclz",
+        BloscCompressor::Lz4 => "lz4",
+        BloscCompressor::Lz4hc => "lz4hc",
+        BloscCompressor::Zlib => "zlib",
+        BloscCompressor::Zstd => "zstd",
+    };
+    let shuffle = match (T::DTYPE, shuffle) {
+        (ScalarType::String, _) | (_, Shuffle::None) => 0,
+        (_, Shuffle::Byte) => 1,
+        (_, Shuffle::Bit) => 2,
+    };
+    json!({"id": "blosc", "cname": cname, "clevel": lvl, "shuffle": shuffle, "blocksize": 0})
+}
+
+/// Round the shard shape up to a multiple of the chunk shape.
+fn shard_shape(shard: &Shape, chunks: &[u64]) -> Result<Vec<u64>> {
+    ensure!(
+        shard.ndim() == chunks.len(),
+        "the shard shape {:?} does not match the dimensions of the array ({})",
+        shard.as_ref(),
+        chunks.len()
+    );
+    Ok(shard
+        .as_ref()
+        .iter()
+        .zip(chunks)
+        .map(|(s, c)| (*s as u64).div_ceil(*c).max(1) * c)
+        .collect())
+}
+
+/// Key of a metadata file, e.g., `.zarray`, of the node at `path`.
+fn metadata_key(path: &str, name: &str) -> Result<StoreKey> {
+    let path = path.trim_matches('/');
+    let key = if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", path, name)
+    };
+    Ok(StoreKey::new(key)?)
+}
+
+/// Read an arbitrary selection. Only the chunks containing selected elements
+/// are retrieved.
+fn read_chunks<T>(dataset: &ZarrDataset, sel: &SelectInfoBounds) -> Result<ArrayD<T>>
 where
-    S: AsRef<SelectInfoElem>,
-    T: Clone,
-    D: Dimension,
+    T: ElementOwned + BackendData,
 {
-    let arr = arr.into_dyn();
-    let slices = info
-        .as_ref()
-        .into_iter()
-        .map(|x| match x.as_ref() {
-            SelectInfoElem::Slice(slice) => Some(SliceInfoElem::from(slice.clone())),
-            _ => None,
+    let out_shape = sel.out_shape();
+    let mut out: ArrayD<Option<T>> = ArrayD::from_elem(out_shape.as_ref(), None);
+    for_each_block(dataset, sel, |selected, ranges| {
+        let block: ArrayD<T> = dataset
+            .dataset
+            .retrieve_array_subset_ndarray(&ArraySubset::new_with_ranges(ranges))?;
+        block_indices(selected, ranges).for_each(|(local, pos)| {
+            out[pos.as_slice()] = Some(block[local.as_slice()].clone());
+        });
+        Ok(())
+    })?;
+    let (values, _) = out.into_raw_vec_and_offset();
+    let values = values.into_iter().map(Option::unwrap).collect();
+    Ok(ArrayD::from_shape_vec(out_shape.as_ref(), values)?)
+}
+
+/// Write `arr` to an arbitrary selection. The chunks containing selected
+/// elements are updated one block at a time.
+fn write_chunks<T>(dataset: &ZarrDataset, sel: &SelectInfoBounds, arr: CowArray<'_, T, IxDyn>) -> Result<()>
+where
+    T: ElementOwned + Clone,
+{
+    for_each_block(dataset, sel, |selected, ranges| {
+        let subset = ArraySubset::new_with_ranges(ranges);
+        let mut block: ArrayD<T> = dataset.dataset.retrieve_array_subset_ndarray(&subset)?;
+        block_indices(selected, ranges).for_each(|(local, pos)| {
+            block[local.as_slice()] = arr[pos.as_slice()].clone();
+        });
+        dataset.dataset.store_array_subset_ndarray(subset.start(), block)?;
+        Ok(())
+    })
+}
+
+/// Group the selected indices of each axis by chunk, and call `f` once for
+/// every combination of touched chunks. `f` receives, for each axis, the
+/// (position in the selection, index in the array) pairs falling in the chunk,
+/// and the range spanned by these indices.
+fn for_each_block<F>(dataset: &ZarrDataset, sel: &SelectInfoBounds, mut f: F) -> Result<()>
+where
+    F: FnMut(&[&Vec<(usize, usize)>], &[Range<u64>]) -> Result<()>,
+{
+    if sel.out_shape().as_ref().contains(&0) {
+        return Ok(());
+    }
+    let chunk_shape = dataset.dataset.chunk_shape(&vec![0; sel.ndim()])?;
+    let groups: Vec<Vec<Vec<(usize, usize)>>> = sel
+        .iter()
+        .zip(chunk_shape.iter())
+        .map(|(axis, chunk_size)| {
+            let chunk_size = chunk_size.get() as usize;
+            let mut groups: BTreeMap<usize, Vec<(usize, usize)>> = BTreeMap::new();
+            (0..axis.len()).for_each(|i| {
+                let idx = axis.index(i);
+                groups.entry(idx / chunk_size).or_default().push((i, idx));
+            });
+            groups.into_values().collect()
         })
-        .collect::<Option<Vec<_>>>();
-    if let Some(slices) = slices {
-        arr.slice(slices.as_slice()).into_owned()
-    } else {
-        let shape = arr.shape();
-        let select: Vec<_> = info
-            .as_ref()
-            .into_iter()
-            .zip(shape)
-            .map(|(x, n)| SelectInfoElemBounds::new(x.as_ref(), *n))
+        .collect();
+
+    let mut current = vec![0; groups.len()];
+    loop {
+        let selected: Vec<_> = current.iter().zip(&groups).map(|(i, g)| &g[*i]).collect();
+        let ranges: Vec<_> = selected
+            .iter()
+            .map(|pairs| {
+                let start = pairs.iter().map(|x| x.1).min().unwrap();
+                let end = pairs.iter().map(|x| x.1).max().unwrap() + 1;
+                start as u64..end as u64
+            })
             .collect();
-        let new_shape = select.iter().map(|x| x.len()).collect::<Vec<_>>();
-        ArrayD::from_shape_fn(new_shape, |idx| {
-            let new_idx: Vec<_> = (0..idx.ndim())
-                .into_iter()
-                .map(|i| select[i].index(idx[i]))
-                .collect();
-            arr.index(new_idx.as_slice()).clone()
-        })
+        f(&selected, &ranges)?;
+
+        // Advance to the next combination of chunks.
+        let mut axis = groups.len();
+        loop {
+            if axis == 0 {
+                return Ok(());
+            }
+            axis -= 1;
+            current[axis] += 1;
+            if current[axis] < groups[axis].len() {
+                break;
+            }
+            current[axis] = 0;
+        }
     }
-    .into_dimensionality::<D>()
-    .unwrap()
+}
+
+/// Pairs of (index in the block, position in the selection) of all selected
+/// elements of a block.
+fn block_indices<'a>(
+    selected: &'a [&'a Vec<(usize, usize)>],
+    ranges: &'a [Range<u64>],
+) -> impl Iterator<Item = (Vec<usize>, Vec<usize>)> + 'a {
+    let shape: Vec<usize> = selected.iter().map(|x| x.len()).collect();
+    ndarray::indices(shape).into_iter().map(move |idx| {
+        (0..idx.ndim())
+            .map(|a| {
+                let (pos, i) = selected[a][idx[a]];
+                (i - ranges[a].start as usize, pos)
+            })
+            .unzip()
+    })
+}
+
+/// Move all keys under `from` to `to`. Stores have no rename operation, so
+/// the values are copied before the old keys are erased.
+fn rename_prefix(store: &ZarrStore, from: &str, to: &str) -> Result<()> {
+    let from = str_to_prefix(from);
+    let to = str_to_prefix(to);
+    ensure!(from != StorePrefix::root(), "cannot rename the root group");
+    ensure!(store.list_prefix(&to)?.is_empty(), "'{}' already exists", to.as_str());
+    let keys = store.list_prefix(&from)?;
+    ensure!(!keys.is_empty(), "no group or dataset named '{}'", from.as_str());
+    for key in keys {
+        let value = store.get(&key)?.with_context(|| format!("cannot read '{}'", key))?;
+        let suffix = key.as_str().strip_prefix(from.as_str()).unwrap();
+        store.set(&StoreKey::new(format!("{}{}", to.as_str(), suffix))?, value)?;
+    }
+    store.erase_prefix(&from)?;
+    Ok(())
 }
 
 fn str_to_prefix(s: &str) -> StorePrefix {
@@ -574,7 +1011,7 @@ fn canoincalize_path<'a>(path: &'a str) -> Cow<'a, str> {
     }
 }
 
-fn to_array_subset(info: SelectInfoBounds) -> Option<ArraySubset> {
+fn to_array_subset(info: &SelectInfoBounds) -> Option<ArraySubset> {
     let ranges = info.iter().map(|x| {
         if let SelectInfoElemBounds::Slice(slice) = x {
             if slice.step == 1 {
@@ -597,10 +1034,10 @@ fn to_array_subset(info: SelectInfoBounds) -> Option<ArraySubset> {
 mod tests {
     use super::*;
     use anndata::s;
-    use ndarray::{array, concatenate, Array2, Axis, Ix2};
+    use ndarray::{array, concatenate, Array1, Array2, Axis, Ix1, Ix2};
     use ndarray_rand::rand_distr::Uniform;
     use ndarray_rand::RandomExt;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use tempfile::tempdir;
 
     pub fn with_tmp_dir<T, F: FnMut(PathBuf) -> T>(mut func: F) -> T {
@@ -648,6 +1085,511 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_v2() -> Result<()> {
+        use anndata::{AnnData, AnnDataOp, ArrayElemOp, AxisArraysOp};
+
+        with_tmp_path(|path| {
+            // A store laid out like the ones written by Python anndata with zarr-python 2.
+            let write = |key: &str, content: &[u8]| -> Result<()> {
+                let file = path.join(key);
+                std::fs::create_dir_all(file.parent().unwrap())?;
+                std::fs::write(file, content)?;
+                Ok(())
+            };
+            let zarray = |shape: &[usize], dtype: &str, fill: Value, filters: Value| {
+                serde_json::to_vec(&json!({
+                    "zarr_format": 2,
+                    "shape": shape,
+                    "chunks": shape,
+                    "dtype": dtype,
+                    "compressor": null,
+                    "fill_value": fill,
+                    "order": "C",
+                    "filters": filters,
+                }))
+                .unwrap()
+            };
+            write(".zgroup", br#"{"zarr_format": 2}"#)?;
+            write(".zattrs", br#"{"encoding-type": "anndata", "encoding-version": "0.1.0"}"#)?;
+            write("X/.zarray", &zarray(&[2, 2], "<f4", json!(0.0), Value::Null))?;
+            write("X/.zattrs", br#"{"encoding-type": "array", "encoding-version": "0.2.0"}"#)?;
+            let data: Vec<u8> = [1.0f32, 2.0, 3.0, 4.0].iter().flat_map(|x| x.to_le_bytes()).collect();
+            write("X/0.0", &data)?;
+            write("obs/.zgroup", br#"{"zarr_format": 2}"#)?;
+            write(
+                "obs/.zattrs",
+                br#"{"_index": "_index", "column-order": [], "encoding-type": "dataframe", "encoding-version": "0.2.0"}"#,
+            )?;
+            write(
+                "obs/_index/.zarray",
+                &zarray(&[2], "|O", Value::Null, json!([{"id": "vlen-utf8"}])),
+            )?;
+            write(
+                "obs/_index/.zattrs",
+                br#"{"encoding-type": "string-array", "encoding-version": "0.2.0"}"#,
+            )?;
+            let mut chunk = 2u32.to_le_bytes().to_vec();
+            for name in ["c1", "c2"] {
+                chunk.extend((name.len() as u32).to_le_bytes());
+                chunk.extend(name.as_bytes());
+            }
+            write("obs/_index/0", &chunk)?;
+
+            let store = Zarr::open(&path)?;
+            assert_eq!(store.version(), ZarrVersion::V2);
+            assert_eq!(
+                store.open_group("obs")?.open_dataset("_index")?.read_array::<String, Ix1>()?,
+                array!["c1".to_string(), "c2".to_string()],
+            );
+
+            let adata = AnnData::<Zarr>::open(store)?;
+            assert_eq!(adata.obs_names().into_vec(), vec!["c1", "c2"]);
+            let x: Array2<f32> = adata.x().get()?.unwrap();
+            assert_eq!(x, array![[1.0, 2.0], [3.0, 4.0]]);
+
+            // New elements are written with the version of the store.
+            adata.obsm().add("pca", array![[1.0f64], [2.0]])?;
+            assert!(path.join("obsm/pca/.zarray").exists());
+            assert!(path.join("obsm/pca/.zattrs").exists());
+            assert!(!path.join("obsm/pca/zarr.json").exists());
+            let pca: Array2<f64> = adata.obsm().get_item("pca")?.unwrap();
+            assert_eq!(pca, array![[1.0], [2.0]]);
+
+            let store = Zarr::new_with_version(path.join("new"), ZarrVersion::V2)?;
+            let dataset = store.new_array_dataset(
+                "names",
+                array!["a".to_string(), "b".to_string()].view().into(),
+                WriteConfig::default(),
+            )?;
+            assert!(path.join("new/.zgroup").exists());
+            assert_eq!(dataset.read_array::<String, Ix1>()?, array!["a".to_string(), "b".to_string()]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_zip() -> Result<()> {
+        use anndata::{AnnData, AnnDataOp, ArrayElemOp};
+
+        with_tmp_dir(|dir| {
+            let path = dir.join("data.zarr.zip");
+            let arr = Array::random((40, 30), Uniform::new(0, 100));
+            let store = Zarr::new(&path)?;
+            store
+                .new_group("group")?
+                .new_array_dataset("data", arr.view().into(), WriteConfig::default())?;
+            store.new_scalar_dataset("scalar", &4)?;
+            store.close()?;
+            assert!(path.is_file());
+
+            let store = Zarr::open(&path)?;
+            assert_eq!(store.open_dataset("group/data")?.read_array::<i32, Ix2>()?, arr);
+            assert!(store.new_group("other").is_err());
+            drop(store);
+
+            let store = Zarr::open_rw(&path)?;
+            let update = Array2::<i32>::zeros((2, 30));
+            store.open_dataset("group/data")?.write_array_slice(
+                update.view().into(),
+                s![vec![39, 0], ..].as_ref(),
+            )?;
+            store.delete("scalar")?;
+            store.close()?;
+
+            let store = Zarr::open(&path)?;
+            let mut expected = arr.clone();
+            expected.row_mut(0).fill(0);
+            expected.row_mut(39).fill(0);
+            assert_eq!(store.open_dataset("group/data")?.read_array::<i32, Ix2>()?, expected);
+            assert!(!store.exists("scalar")?);
+            drop(store);
+
+            // Archives created by other tools may compress their entries.
+            let dir_store = dir.join("data.zarr");
+            let adata = AnnData::<Zarr>::new(&dir_store)?;
+            adata.set_x(arr.clone())?;
+            adata.close()?;
+            let path = dir.join("deflated.zarr.zip");
+            let mut writer = zip::ZipWriter::new(std::fs::File::create(&path)?);
+            let options = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated);
+            let mut stack = vec![dir_store.clone()];
+            while let Some(entry) = stack.pop() {
+                for entry in std::fs::read_dir(entry)? {
+                    let entry = entry?.path();
+                    if entry.is_dir() {
+                        stack.push(entry);
+                    } else {
+                        let name = entry.strip_prefix(&dir_store)?.to_str().unwrap().replace('\\', "/");
+                        writer.start_file(name, options)?;
+                        std::io::Write::write_all(&mut writer, &std::fs::read(&entry)?)?;
+                    }
+                }
+            }
+            writer.finish()?;
+
+            let adata = AnnData::<Zarr>::open(Zarr::open(&path)?)?;
+            let x: Array2<i32> = adata.x().get()?.unwrap();
+            assert_eq!(x, arr);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_consolidated() -> Result<()> {
+        with_tmp_dir(|dir| {
+            for version in [ZarrVersion::V3, ZarrVersion::V2] {
+                let path = dir.join(format!("{:?}.zarr", version));
+                let (group_metadata, consolidated_key) = match version {
+                    ZarrVersion::V3 => ("group/zarr.json", "zarr.json"),
+                    ZarrVersion::V2 => ("group/.zgroup", ".zmetadata"),
+                };
+                let consolidated = || {
+                    let metadata = std::fs::read_to_string(path.join(consolidated_key)).ok()?;
+                    (version == ZarrVersion::V2 || metadata.contains("consolidated_metadata"))
+                        .then_some(metadata)
+                };
+
+                let arr = Array::random((40, 30), Uniform::new(0, 100));
+                let store = Zarr::new_with_version(&path, version)?;
+                let mut group = store.new_group("group")?;
+                group.new_array_dataset("data", arr.view().into(), WriteConfig::default())?;
+                group.new_json_attr("key", &Value::from("value"))?;
+                store.close()?;
+                assert!(consolidated().unwrap().contains("group/data"));
+
+                // Metadata documents of the nodes are not read.
+                let metadata = std::fs::read(path.join(group_metadata))?;
+                std::fs::remove_file(path.join(group_metadata))?;
+                let store = Zarr::open(&path)?;
+                assert_eq!(store.list()?, vec!["group"]);
+                let group = store.open_group("group")?;
+                assert_eq!(group.list()?, vec!["data"]);
+                assert!(group.exists("data")?);
+                assert_eq!(group.get_json_attr("key")?, Value::from("value"));
+                assert_eq!(group.open_dataset("data")?.read_array::<i32, Ix2>()?, arr);
+                drop(group);
+                drop(store);
+                std::fs::write(path.join(group_metadata), metadata)?;
+
+                // Changing the hierarchy drops the consolidated metadata until
+                // the store is closed.
+                let store = Zarr::open_rw(&path)?;
+                store.open_group("group")?.delete("data")?;
+                assert!(consolidated().is_none());
+                assert!(!store.open_group("group")?.exists("data")?);
+                store.close()?;
+                assert!(!consolidated().unwrap().contains("group/data"));
+                let store = Zarr::open(&path)?;
+                assert!(store.exists("group")?);
+                assert!(!store.open_group("group")?.exists("data")?);
+            }
+            Ok(())
+        })
+    }
+
+    /// Upload the files under `dir/name` to `remote`, below `prefix`.
+    #[cfg(feature = "object-store")]
+    fn upload(
+        remote: &dyn object_store::ObjectStore,
+        dir: &Path,
+        name: &str,
+        prefix: &str,
+    ) -> Result<()> {
+        let runtime = tokio::runtime::Runtime::new()?;
+        let mut stack = vec![dir.join(name)];
+        while let Some(entry) = stack.pop() {
+            for entry in std::fs::read_dir(entry)? {
+                let entry = entry?.path();
+                if entry.is_dir() {
+                    stack.push(entry);
+                } else {
+                    let key = entry.strip_prefix(dir)?.to_str().unwrap().replace('\\', "/");
+                    let location = object_store::path::Path::from(format!("{}/{}", prefix, key));
+                    runtime.block_on(remote.put(&location, std::fs::read(&entry)?.into()))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a small AnnData object to `dir/atlas.zarr` and return its X.
+    #[cfg(feature = "object-store")]
+    fn write_atlas(dir: &Path) -> Result<Array2<i32>> {
+        use anndata::{data::DataFrameIndex, AnnData, AnnDataOp};
+
+        let arr = Array::random((40, 30), Uniform::new(0, 100));
+        let adata = AnnData::<Zarr>::new(dir.join("atlas.zarr"))?;
+        adata.set_x(arr.clone())?;
+        adata.set_obs_names((0..40).map(|i| i.to_string()).collect::<DataFrameIndex>())?;
+        adata.close()?;
+        Ok(arr)
+    }
+
+    #[cfg(feature = "object-store")]
+    fn check_atlas(store: ZarrStore, arr: &Array2<i32>) -> Result<()> {
+        use anndata::{AnnData, AnnDataOp, ArrayElemOp};
+
+        assert!(store.list()?.contains(&"X".to_string()));
+        assert!(store.new_group("group").is_err());
+        let adata = AnnData::<Zarr>::open(store)?;
+        assert_eq!(adata.n_obs(), 40);
+        assert_eq!(adata.obs_names().into_vec()[39], "39");
+        let x: Array2<i32> = adata.x().get()?.unwrap();
+        assert_eq!(&x, arr);
+        let x: Array2<i32> = adata.x().slice(s![vec![39, 2], 3..10])?.unwrap();
+        assert_eq!(x, arr.select(Axis(0), &[39, 2]).slice(ndarray::s![.., 3..10]));
+        adata.close()
+    }
+
+    #[cfg(feature = "object-store")]
+    #[test]
+    fn test_object_store() -> Result<()> {
+        use object_store::memory::InMemory;
+
+        with_tmp_dir(|dir| {
+            let arr = write_atlas(&dir)?;
+            let remote = Arc::new(InMemory::new());
+            upload(remote.as_ref(), &dir, "atlas.zarr", "bucket")?;
+            check_atlas(Zarr::open_object_store(remote, "bucket/atlas.zarr")?, &arr)
+        })
+    }
+
+    /// Stores can be used from async code, which runs inside a tokio runtime.
+    #[cfg(feature = "object-store")]
+    #[test]
+    fn test_object_store_in_runtime() -> Result<()> {
+        use object_store::memory::InMemory;
+
+        with_tmp_dir(|dir| {
+            let arr = write_atlas(&dir)?;
+            let remote = Arc::new(InMemory::new());
+            upload(remote.as_ref(), &dir, "atlas.zarr", "bucket")?;
+            let multi_thread = tokio::runtime::Runtime::new()?;
+            let task = {
+                let (remote, arr) = (remote.clone(), arr.clone());
+                multi_thread.spawn(async move {
+                    check_atlas(Zarr::open_object_store(remote, "bucket/atlas.zarr")?, &arr)
+                })
+            };
+            multi_thread.block_on(task)??;
+            let current_thread = tokio::runtime::Builder::new_current_thread().build()?;
+            current_thread.block_on(async {
+                check_atlas(Zarr::open_object_store(remote.clone(), "bucket/atlas.zarr")?, &arr)
+            })
+        })
+    }
+
+    #[cfg(feature = "object-store")]
+    #[test]
+    fn test_parse_url() -> Result<()> {
+        let options = [
+            ("aws_endpoint", "http://localhost:9000"),
+            ("aws_region", "us-east-1"),
+            ("aws_access_key_id", "key"),
+            ("aws_secret_access_key", "secret"),
+            ("aws_allow_http", "true"),
+        ];
+        let (store, prefix) = parse_url("s3://bucket/data/atlas.zarr", options)?;
+        assert_eq!(prefix.as_ref(), "data/atlas.zarr");
+        assert!(store.to_string().contains("bucket"));
+
+        let (_, prefix) = parse_url("s3://bucket/", options)?;
+        assert_eq!(prefix.as_ref(), "");
+        let (_, prefix) = parse_url("memory:///atlas.zarr", options)?;
+        assert_eq!(prefix.as_ref(), "atlas.zarr");
+
+        assert!(parse_url("atlas.zarr", options).is_err());
+        assert!(parse_url("unknown://bucket/atlas.zarr", options).is_err());
+        Ok(())
+    }
+
+    /// Reads a store from S3-compatible storage, e.g., a local MinIO server:
+    ///
+    /// ```sh
+    /// ANNDATA_TEST_S3_ENDPOINT=http://localhost:9000 ANNDATA_TEST_S3_BUCKET=test \
+    /// AWS_ACCESS_KEY_ID=minioadmin AWS_SECRET_ACCESS_KEY=minioadmin \
+    /// cargo test -p anndata-zarr --features object-store -- --ignored test_s3
+    /// ```
+    #[cfg(feature = "object-store")]
+    #[test]
+    #[ignore = "requires an S3-compatible server"]
+    fn test_s3() -> Result<()> {
+        let var = |name: &str| std::env::var(name).with_context(|| format!("{} is not set", name));
+        let bucket = var("ANNDATA_TEST_S3_BUCKET")?;
+        let options = [
+            ("aws_endpoint", var("ANNDATA_TEST_S3_ENDPOINT")?),
+            ("aws_region", var("AWS_REGION").unwrap_or("us-east-1".to_string())),
+            ("aws_access_key_id", var("AWS_ACCESS_KEY_ID")?),
+            ("aws_secret_access_key", var("AWS_SECRET_ACCESS_KEY")?),
+            ("aws_allow_http", "true".to_string()),
+        ];
+
+        with_tmp_dir(|dir| {
+            let arr = write_atlas(&dir)?;
+            let prefix = format!("anndata-rs-test-{}", std::process::id());
+            let url = format!("s3://{}/{}/atlas.zarr", bucket, prefix);
+            let (remote, _) = parse_url(&url, options.clone())?;
+            upload(remote.as_ref(), &dir, "atlas.zarr", &prefix)?;
+            check_atlas(Zarr::open_url(&url, options.clone())?, &arr)
+        })
+    }
+
+    #[test]
+    fn test_read_fancy() -> Result<()> {
+        use anndata::data::Selectable;
+
+        with_tmp_path(|path| {
+            let store = Zarr::new(&path)?;
+            let config = WriteConfig {
+                block_size: Some(vec![7, 4].as_slice().into()),
+                ..Default::default()
+            };
+            let arr = Array::random((50, 30), Uniform::new(0, 100));
+            let dataset = store.new_array_dataset("test", arr.view().into(), config)?;
+
+            let selections = [
+                s![vec![49, 3, 3, 20, 0], ..],
+                s![.., vec![29, 1, 4]],
+                s![vec![8, 7, 45], 2..30],
+                s![(1..50).step_by(3).collect::<Vec<_>>(), 5..6],
+                s![ndarray::Slice::new(0, None, -2), vec![0]],
+                s![Vec::<usize>::new(), ..],
+            ];
+            for select in selections {
+                assert_eq!(
+                    dataset.read_array_slice::<i32, _, Ix2>(select.as_ref())?,
+                    Selectable::select(&arr, select.as_ref()),
+                );
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_write_fancy() -> Result<()> {
+        with_tmp_path(|path| {
+            let store = Zarr::new(&path)?;
+            let config = WriteConfig {
+                block_size: Some(vec![7, 4].as_slice().into()),
+                ..Default::default()
+            };
+            let mut expected = Array::random((50, 30), Uniform::new(0, 100));
+            let dataset = store.new_array_dataset("test", expected.view().into(), config)?;
+
+            let rows = vec![49, 3, 20, 0];
+            let update = Array::random((4, 30), Uniform::new(100, 200));
+            dataset.write_array_slice(update.view().into(), s![rows.clone(), ..].as_ref())?;
+            rows.iter()
+                .enumerate()
+                .for_each(|(i, r)| expected.row_mut(*r).assign(&update.row(i)));
+            assert_eq!(dataset.read_array::<i32, Ix2>()?, expected);
+
+            let update = Array::random((25, 2), Uniform::new(100, 200));
+            dataset.write_array_slice(
+                update.view().into(),
+                s![ndarray::Slice::new(0, None, 2), vec![29, 5]].as_ref(),
+            )?;
+            (0..25).for_each(|i| {
+                expected[[2 * i, 29]] = update[[i, 0]];
+                expected[[2 * i, 5]] = update[[i, 1]];
+            });
+            assert_eq!(dataset.read_array::<i32, Ix2>()?, expected);
+
+            // Out-of-bounds writes and mismatched shapes are errors.
+            assert!(dataset
+                .write_array_slice(update.view().into(), s![vec![50], ..].as_ref())
+                .is_err());
+            assert!(dataset
+                .write_array_slice(update.view().into(), s![vec![1, 2], ..].as_ref())
+                .is_err());
+            Ok(())
+        })
+    }
+
+    fn count_files(dir: &Path) -> usize {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    count_files(&path)
+                } else {
+                    1
+                }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_codecs() -> Result<()> {
+        with_tmp_path(|path| {
+            let store = Zarr::new(&path)?;
+            let arr = Array::random((40, 30), Uniform::new(0, 100));
+            let names: Array1<String> = (0..200).map(|i| i.to_string()).collect();
+            let compressions = [
+                ("none", None),
+                ("gzip", Some(Compression::Gzip(5))),
+                ("zstd", Some(Compression::Zst(3))),
+                ("blosc", Some(Compression::Blosc(BloscCompressor::Lz4, 5))),
+            ];
+            for (name, compression) in compressions {
+                for shuffle in [Shuffle::None, Shuffle::Byte, Shuffle::Bit] {
+                    let config = WriteConfig {
+                        compression,
+                        shuffle,
+                        block_size: Some(vec![10, 10].as_slice().into()),
+                        ..Default::default()
+                    };
+                    let key = format!("{}_{:?}", name, shuffle);
+                    let dataset = store.new_array_dataset(&key, arr.view().into(), config.clone())?;
+                    assert_eq!(dataset.read_array::<i32, Ix2>()?, arr);
+                    let metadata = std::fs::read_to_string(path.join(&key).join("zarr.json"))?;
+                    if compression.is_some() {
+                        assert!(metadata.contains(name));
+                    }
+                    if name == "lz4" {
+                        let mode = match shuffle {
+                            Shuffle::None => "\"shuffle\":\"noshuffle\"",
+                            Shuffle::Byte => "\"shuffle\":\"shuffle\"",
+                            Shuffle::Bit => "\"shuffle\":\"bitshuffle\"",
+                        };
+                        let metadata: String = metadata.split_whitespace().collect();
+                        assert!(metadata.contains(mode));
+                    }
+
+                    let config = WriteConfig { block_size: None, ..config };
+                    let dataset = store.new_array_dataset(&format!("{}_names", key), names.view().into(), config)?;
+                    assert_eq!(dataset.read_array::<String, Ix1>()?, names);
+                }
+            }
+
+            // 40 x 30 elements in 10 x 10 chunks, grouped in shards of 20 x 20
+            // (rounded up from 15 x 20): 2 x 2 shards.
+            let config = WriteConfig {
+                block_size: Some(vec![10, 10].as_slice().into()),
+                shard_size: Some(vec![15, 20].as_slice().into()),
+                ..Default::default()
+            };
+            let dataset = store.new_array_dataset("sharded", arr.view().into(), config)?;
+            assert_eq!(dataset.read_array::<i32, Ix2>()?, arr);
+            assert_eq!(count_files(&path.join("sharded").join("c")), 4);
+            assert_eq!(
+                dataset.read_array_slice::<i32, _, Ix2>(s![vec![39, 3], 5..25].as_ref())?,
+                arr.select(Axis(0), &[39, 3]).slice(ndarray::s![.., 5..25]),
+            );
+
+            let store = Zarr::new_with_version(path.join("v2"), ZarrVersion::V2)?;
+            let config = WriteConfig {
+                shard_size: Some(vec![20, 20].as_slice().into()),
+                ..Default::default()
+            };
+            assert!(store.new_array_dataset("sharded", arr.view().into(), config).is_err());
+            Ok(())
+        })
+    }
+
     #[test]
     fn test_write_empty() -> Result<()> {
         with_tmp_path(|path| {
@@ -0,0 +1,223 @@
+use anyhow::Result;
+use futures::TryStreamExt;
+use object_store::{path::Path, ObjectStore};
+use std::{future::Future, sync::Arc};
+use tokio::runtime::{Handle, Runtime, RuntimeFlavor};
+use zarrs::storage::{
+    byte_range::ByteRange, Bytes, ListableStorageTraits, MaybeBytes, ReadableStorageTraits,
+    StorageError, StoreKey, StoreKeyOffsetValue, StoreKeys, StoreKeysPrefixes, StorePrefix,
+    WritableStorageTraits,
+};
+
+/// A read-only Zarr store kept in an object store, e.g., S3.
+///
+/// Requests are asynchronous and run on a runtime owned by the store. The
+/// store can be used from within another tokio runtime, e.g., in an async
+/// service, but each request blocks the calling thread.
+pub(crate) struct RemoteStore {
+    store: Arc<dyn ObjectStore>,
+    /// Location of the root of the Zarr hierarchy, without trailing delimiter.
+    prefix: String,
+    /// Always set, except while the store is being dropped.
+    runtime: Option<Runtime>,
+}
+
+impl RemoteStore {
+    pub fn new(store: Arc<dyn ObjectStore>, prefix: &str) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            store,
+            prefix: prefix.trim_matches('/').to_string(),
+            runtime: Some(runtime),
+        })
+    }
+
+    /// Run `future` to completion on the runtime of the store.
+    ///
+    /// Blocking on a runtime panics when the thread already runs inside one.
+    /// Worker threads of a multi-threaded runtime are handed over with
+    /// `block_in_place`, and a current-thread runtime is left alone by running
+    /// the future on a separate thread.
+    fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let runtime = self.runtime.as_ref().unwrap();
+        match Handle::try_current() {
+            Err(_) => runtime.block_on(future),
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| runtime.block_on(future))
+            }
+            Ok(_) => std::thread::scope(|scope| {
+                scope
+                    .spawn(|| runtime.block_on(future))
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            }),
+        }
+    }
+
+    fn location(&self, key: &str) -> Path {
+        if self.prefix.is_empty() {
+            Path::from(key)
+        } else {
+            Path::from(format!("{}/{}", self.prefix, key))
+        }
+    }
+
+    /// Convert a location back to a store key.
+    fn key(&self, location: &Path) -> Result<String, StorageError> {
+        let location = location.as_ref();
+        let key = if self.prefix.is_empty() {
+            Some(location)
+        } else {
+            location
+                .strip_prefix(self.prefix.as_str())
+                .and_then(|x| x.strip_prefix('/'))
+        };
+        key.map(|x| x.to_string())
+            .ok_or_else(|| StorageError::Other(format!("{} is outside of {}", location, self.prefix)))
+    }
+
+    fn object_size(&self, location: &Path) -> Result<Option<u64>, StorageError> {
+        match self.block_on(self.store.head(location)) {
+            Ok(meta) => Ok(Some(meta.size)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(other(e)),
+        }
+    }
+}
+
+impl Drop for RemoteStore {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which panics inside another runtime.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+fn other<E: ToString>(e: E) -> StorageError {
+    StorageError::Other(e.to_string())
+}
+
+impl ReadableStorageTraits for RemoteStore {
+    fn get(&self, key: &StoreKey) -> Result<MaybeBytes, StorageError> {
+        let location = self.location(key.as_str());
+        let result = self.block_on(async { self.store.get(&location).await?.bytes().await });
+        match result {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(other(e)),
+        }
+    }
+
+    fn get_partial_values_key(
+        &self,
+        key: &StoreKey,
+        byte_ranges: &[ByteRange],
+    ) -> Result<Option<Vec<Bytes>>, StorageError> {
+        let location = self.location(key.as_str());
+        // The size is only needed for ranges relative to the end of the object.
+        let size = if byte_ranges
+            .iter()
+            .all(|range| matches!(range, ByteRange::FromStart(_, Some(_))))
+        {
+            0
+        } else {
+            match self.object_size(&location)? {
+                Some(size) => size,
+                None => return Ok(None),
+            }
+        };
+        let ranges: Vec<_> = byte_ranges
+            .iter()
+            .map(|range| match *range {
+                ByteRange::FromStart(offset, None) => offset..size,
+                ByteRange::FromStart(offset, Some(length)) => offset..offset + length,
+                ByteRange::Suffix(length) => size.saturating_sub(length)..size,
+            })
+            .collect();
+        match self.block_on(self.store.get_ranges(&location, &ranges)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(other(e)),
+        }
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        self.object_size(&self.location(key.as_str()))
+    }
+}
+
+impl ListableStorageTraits for RemoteStore {
+    fn list(&self) -> Result<StoreKeys, StorageError> {
+        self.list_prefix(&StorePrefix::root())
+    }
+
+    fn list_prefix(&self, prefix: &StorePrefix) -> Result<StoreKeys, StorageError> {
+        let location = self.location(prefix.as_str());
+        let objects: Vec<_> = self
+            .block_on(self.store.list(Some(&location)).try_collect())
+            .map_err(other)?;
+        objects
+            .iter()
+            .map(|meta| StoreKey::new(self.key(&meta.location)?).map_err(other))
+            .collect()
+    }
+
+    fn list_dir(&self, prefix: &StorePrefix) -> Result<StoreKeysPrefixes, StorageError> {
+        let location = self.location(prefix.as_str());
+        let result = self
+            .block_on(self.store.list_with_delimiter(Some(&location)))
+            .map_err(other)?;
+        let keys = result
+            .objects
+            .iter()
+            .map(|meta| StoreKey::new(self.key(&meta.location)?).map_err(other))
+            .collect::<Result<Vec<_>, _>>()?;
+        let prefixes = result
+            .common_prefixes
+            .iter()
+            .map(|location| StorePrefix::new(format!("{}/", self.key(location)?)).map_err(other))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(StoreKeysPrefixes::new(keys, prefixes))
+    }
+
+    fn size_prefix(&self, prefix: &StorePrefix) -> Result<u64, StorageError> {
+        let location = self.location(prefix.as_str());
+        let objects: Vec<_> = self
+            .block_on(self.store.list(Some(&location)).try_collect())
+            .map_err(other)?;
+        Ok(objects.iter().map(|meta| meta.size).sum())
+    }
+
+    fn size(&self) -> Result<u64, StorageError> {
+        self.size_prefix(&StorePrefix::root())
+    }
+}
+
+impl WritableStorageTraits for RemoteStore {
+    fn set(&self, _key: &StoreKey, _value: Bytes) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn set_partial_values(
+        &self,
+        _key_offset_values: &[StoreKeyOffsetValue],
+    ) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn erase(&self, _key: &StoreKey) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn erase_prefix(&self, _prefix: &StorePrefix) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+}
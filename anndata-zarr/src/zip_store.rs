@@ -0,0 +1,313 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, RwLock},
+};
+use zarrs::storage::{
+    byte_range::ByteRange, Bytes, ListableStorageTraits, MaybeBytes, ReadableStorageTraits,
+    StorageError, StoreKey, StoreKeyOffsetValue, StoreKeys, StoreKeysPrefixes, StorePrefix,
+    WritableStorageTraits,
+};
+use zip::{result::ZipError, write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+/// Returns true if the path points to a zipped Zarr store, e.g., `data.zarr.zip`.
+pub(crate) fn is_zip<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().is_some_and(|ext| ext == "zip")
+}
+
+/// A Zarr store kept in a single zip archive.
+///
+/// Entries are read from the archive on demand. Changes are buffered in memory
+/// and the archive is rewritten by [`ZipStore::flush`], which is also called
+/// when the store is dropped. Existing entries are copied over without being
+/// decompressed, and new entries are stored uncompressed as Zarr chunks are
+/// compressed already.
+///
+/// Every chunk written to the store is therefore kept in memory until the
+/// store is closed, and each flush rewrites the whole archive. Writable zip
+/// stores are only suited to small data; write large data to a directory store
+/// and zip it afterwards.
+pub(crate) struct ZipStore {
+    path: PathBuf,
+    writable: bool,
+    archive: Mutex<Option<ZipArchive<File>>>,
+    /// Pending changes. `None` marks an erased entry.
+    changes: RwLock<BTreeMap<String, Option<Bytes>>>,
+}
+
+impl ZipStore {
+    /// Create an empty store. The archive is written on the first flush.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            writable: true,
+            archive: Mutex::new(None),
+            changes: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Open an existing archive.
+    pub fn open(path: PathBuf, writable: bool) -> anyhow::Result<Self> {
+        let archive = ZipArchive::new(File::open(&path)?)?;
+        Ok(Self {
+            path,
+            writable,
+            archive: Mutex::new(Some(archive)),
+            changes: RwLock::new(BTreeMap::new()),
+        })
+    }
+
+    /// Write pending changes to the archive.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        let mut changes = self.changes.write().unwrap();
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let mut archive = self.archive.lock().unwrap();
+
+        // Write to a temporary file first so that a failure leaves the
+        // original archive untouched.
+        let tmp = self.path.with_extension("zip.tmp");
+        let mut writer = ZipWriter::new(File::create(&tmp)?);
+        if let Some(archive) = archive.as_mut() {
+            for i in 0..archive.len() {
+                let file = archive.by_index_raw(i)?;
+                if !changes.contains_key(file.name()) {
+                    writer.raw_copy_file(file)?;
+                }
+            }
+        }
+        for (key, value) in changes.iter() {
+            if let Some(value) = value {
+                let options = SimpleFileOptions::default()
+                    .compression_method(CompressionMethod::Stored)
+                    .large_file(value.len() as u64 >= u32::MAX as u64);
+                writer.start_file(key.as_str(), options)?;
+                writer.write_all(value)?;
+            }
+        }
+        writer.finish()?;
+
+        *archive = None;
+        std::fs::rename(&tmp, &self.path)?;
+        *archive = Some(ZipArchive::new(File::open(&self.path)?)?);
+        changes.clear();
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<(), StorageError> {
+        if self.writable {
+            Ok(())
+        } else {
+            Err(StorageError::ReadOnly)
+        }
+    }
+
+    fn read_entry(&self, key: &str) -> Result<MaybeBytes, StorageError> {
+        if let Some(value) = self.changes.read().unwrap().get(key) {
+            return Ok(value.clone());
+        }
+        let mut archive = self.archive.lock().unwrap();
+        let Some(archive) = archive.as_mut() else {
+            return Ok(None);
+        };
+        let mut file = match archive.by_name(key) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(zip_error(e)),
+        };
+        let mut buf = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut buf)?;
+        Ok(Some(buf.into()))
+    }
+
+    fn entry_size(&self, key: &str) -> Result<Option<u64>, StorageError> {
+        if let Some(value) = self.changes.read().unwrap().get(key) {
+            return Ok(value.as_ref().map(|x| x.len() as u64));
+        }
+        let mut archive = self.archive.lock().unwrap();
+        let Some(archive) = archive.as_mut() else {
+            return Ok(None);
+        };
+        let size = match archive.by_name(key) {
+            Ok(file) => Ok(Some(file.size())),
+            Err(ZipError::FileNotFound) => Ok(None),
+            Err(e) => Err(zip_error(e)),
+        };
+        size
+    }
+
+    /// All entries in the store, taking pending changes into account.
+    fn keys(&self) -> BTreeSet<String> {
+        let changes = self.changes.read().unwrap();
+        let mut keys: BTreeSet<String> = self
+            .archive
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|archive| {
+                archive
+                    .file_names()
+                    .filter(|name| !name.ends_with('/') && !changes.contains_key(*name))
+                    .map(|name| name.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        keys.extend(
+            changes
+                .iter()
+                .filter(|(_, value)| value.is_some())
+                .map(|(key, _)| key.clone()),
+        );
+        keys
+    }
+}
+
+impl Drop for ZipStore {
+    fn drop(&mut self) {
+        // Errors cannot be returned here. Use `StoreOp::close` to catch them.
+        if let Err(e) = self.flush() {
+            log::error!("failed to write zip archive '{}': {}", self.path.display(), e);
+        }
+    }
+}
+
+fn zip_error(e: ZipError) -> StorageError {
+    StorageError::Other(e.to_string())
+}
+
+impl ReadableStorageTraits for ZipStore {
+    fn get(&self, key: &StoreKey) -> Result<MaybeBytes, StorageError> {
+        self.read_entry(key.as_str())
+    }
+
+    fn get_partial_values_key(
+        &self,
+        key: &StoreKey,
+        byte_ranges: &[ByteRange],
+    ) -> Result<Option<Vec<Bytes>>, StorageError> {
+        let Some(value) = self.read_entry(key.as_str())? else {
+            return Ok(None);
+        };
+        let size = value.len() as u64;
+        byte_ranges
+            .iter()
+            .map(|range| {
+                let (start, end) = match *range {
+                    ByteRange::FromStart(offset, None) => (offset, size),
+                    ByteRange::FromStart(offset, Some(length)) => (offset, offset + length),
+                    ByteRange::Suffix(length) => (size.saturating_sub(length), size),
+                };
+                if start > end || end > size {
+                    Err(StorageError::Other(format!(
+                        "byte range {:?} is out of bounds for {} ({} bytes)",
+                        range, key, size
+                    )))
+                } else {
+                    Ok(value.slice(start as usize..end as usize))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some)
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        self.entry_size(key.as_str())
+    }
+}
+
+impl ListableStorageTraits for ZipStore {
+    fn list(&self) -> Result<StoreKeys, StorageError> {
+        self.list_prefix(&StorePrefix::root())
+    }
+
+    fn list_prefix(&self, prefix: &StorePrefix) -> Result<StoreKeys, StorageError> {
+        self.keys()
+            .into_iter()
+            .filter(|key| key.starts_with(prefix.as_str()))
+            .map(|key| StoreKey::new(key).map_err(|e| StorageError::Other(e.to_string())))
+            .collect()
+    }
+
+    fn list_dir(&self, prefix: &StorePrefix) -> Result<StoreKeysPrefixes, StorageError> {
+        let mut keys = Vec::new();
+        let mut prefixes = BTreeSet::new();
+        for key in self.keys() {
+            if let Some(rest) = key.strip_prefix(prefix.as_str()) {
+                match rest.split_once('/') {
+                    Some((dir, _)) => {
+                        prefixes.insert(format!("{}{}/", prefix.as_str(), dir));
+                    }
+                    None => keys.push(
+                        StoreKey::new(key).map_err(|e| StorageError::Other(e.to_string()))?,
+                    ),
+                }
+            }
+        }
+        let prefixes = prefixes
+            .into_iter()
+            .map(|p| StorePrefix::new(p).map_err(|e| StorageError::Other(e.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(StoreKeysPrefixes::new(keys, prefixes))
+    }
+
+    fn size_prefix(&self, prefix: &StorePrefix) -> Result<u64, StorageError> {
+        self.list_prefix(prefix)?
+            .iter()
+            .map(|key| Ok(self.size_key(key)?.unwrap_or(0)))
+            .sum()
+    }
+
+    fn size(&self) -> Result<u64, StorageError> {
+        self.size_prefix(&StorePrefix::root())
+    }
+}
+
+impl WritableStorageTraits for ZipStore {
+    fn set(&self, key: &StoreKey, value: Bytes) -> Result<(), StorageError> {
+        self.check_writable()?;
+        self.changes
+            .write()
+            .unwrap()
+            .insert(key.as_str().to_string(), Some(value));
+        Ok(())
+    }
+
+    fn set_partial_values(
+        &self,
+        key_offset_values: &[StoreKeyOffsetValue],
+    ) -> Result<(), StorageError> {
+        self.check_writable()?;
+        for key_offset_value in key_offset_values {
+            let key = key_offset_value.key();
+            let offset = key_offset_value.offset() as usize;
+            let update = key_offset_value.value();
+            let mut value = self.read_entry(key.as_str())?.map(|x| x.to_vec()).unwrap_or_default();
+            if value.len() < offset + update.len() {
+                value.resize(offset + update.len(), 0);
+            }
+            value[offset..offset + update.len()].copy_from_slice(update);
+            self.set(key, value.into())?;
+        }
+        Ok(())
+    }
+
+    fn erase(&self, key: &StoreKey) -> Result<(), StorageError> {
+        self.check_writable()?;
+        self.changes
+            .write()
+            .unwrap()
+            .insert(key.as_str().to_string(), None);
+        Ok(())
+    }
+
+    fn erase_prefix(&self, prefix: &StorePrefix) -> Result<(), StorageError> {
+        self.check_writable()?;
+        for key in self.list_prefix(prefix)? {
+            self.erase(&key)?;
+        }
+        Ok(())
+    }
+}
@@ -1,19 +1,31 @@
+mod append;
 mod dataset;
+mod raw;
+mod view;
 
+pub use append::{ObsData, VarData};
 pub use dataset::{AnnDataSet, StackedAnnData};
+pub use raw::{InnerRaw, Raw, RawData};
+pub use view::{AnnDataView, ViewArrayElem, ViewAxisArrays, ViewChunkedArrayElem};
 use smallvec::SmallVec;
 
 use crate::{
-    backend::{Backend, DataContainer, GroupOp, StoreOp},
+    backend::{AttributeOp, Backend, DataContainer, GroupOp, StoreOp, WriteConfig, WritePolicy},
     container::{ArrayElem, Axis, AxisArrays, DataFrameElem, Dim, ElemCollection, Slot},
     data::*,
     traits::AnnDataOp,
 };
 
-use anyhow::{anyhow, ensure, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use itertools::Itertools;
+use log::warn;
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 
+/// Name of the root attribute recording an in-place operation that has not
+/// finished. Its value is the name of the operation.
+const DIRTY_ATTR: &str = "dirty";
+
 /// Represents an annotated data object backed by a specified backend.
 pub struct AnnData<B: Backend> {
     /// The file storage backend.
@@ -40,6 +52,8 @@ pub struct AnnData<B: Backend> {
     pub(crate) uns: ElemCollection<B>,
     /// Layers of data.
     pub(crate) layers: AxisArrays<B>,
+    /// Unfiltered data matrix and variables.
+    pub(crate) raw: Raw<B>,
 }
 
 impl<B: Backend> std::fmt::Debug for AnnData<B> {
@@ -97,6 +111,9 @@ impl<B: Backend> std::fmt::Display for AnnData<B> {
                 write!(f, "\n    layers: '{}'", keys)?;
             }
         }
+        if let Some(n_vars) = self.raw.lock().as_ref().map(|x| x.n_vars()) {
+            write!(f, "\n    raw: n_vars = {}", n_vars)?;
+        }
         Ok(())
     }
 }
@@ -132,6 +149,73 @@ pub(crate) fn new_layers<B: Backend>(group: B::Group, n_obs: &Dim, n_vars: &Dim)
     AxisArrays::new(group, Axis::RowColumn, n_obs, Some(n_vars))
 }
 
+/// Run `write` on a temporary sibling of `filename` and move the result to
+/// `filename` on success. The temporary file is removed if `write` fails.
+fn write_atomic<F>(filename: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&Path) -> Result<()>,
+{
+    let name = filename
+        .file_name()
+        .with_context(|| format!("'{}' is not a valid filename", filename.display()))?;
+    // The file extension is kept, as some backends depend on it.
+    let mut tmp_name = OsString::from(format!(".tmp-{}-", std::process::id()));
+    tmp_name.push(name);
+    let tmp = filename.with_file_name(tmp_name);
+    remove_path(&tmp)?;
+
+    if let Err(e) = write(&tmp) {
+        let _ = remove_path(&tmp);
+        return Err(e);
+    }
+    let result = if tmp.is_dir() {
+        let mut old_name = OsString::from(format!(".old-{}-", std::process::id()));
+        old_name.push(name);
+        replace_dir(&tmp, filename, &filename.with_file_name(old_name))
+    } else {
+        std::fs::rename(&tmp, filename)
+    };
+    if result.is_err() {
+        let _ = remove_path(&tmp);
+    }
+    result.with_context(|| {
+        format!(
+            "failed to rename '{}' to '{}'",
+            tmp.display(),
+            filename.display()
+        )
+    })
+}
+
+/// Move the directory `tmp` to `filename`. An existing store at `filename`
+/// cannot be replaced atomically, so it is moved to `old` first and only
+/// removed once `tmp` is in place. It is moved back if `tmp` cannot be moved.
+fn replace_dir(tmp: &Path, filename: &Path, old: &Path) -> std::io::Result<()> {
+    if std::fs::symlink_metadata(filename).is_err() {
+        return std::fs::rename(tmp, filename);
+    }
+    remove_path(old)?;
+    std::fs::rename(filename, old)?;
+    if let Err(e) = std::fs::rename(tmp, filename) {
+        let _ = std::fs::rename(old, filename);
+        return Err(e);
+    }
+    if let Err(e) = remove_path(old) {
+        warn!("failed to remove the previous store at '{}': {}", old.display(), e);
+    }
+    Ok(())
+}
+
+/// Remove a file or a directory, if it exists.
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
 impl<B: Backend> AnnData<B> {
     /// Get the data matrix.
     pub fn get_x(&self) -> &ArrayElem<B> {
@@ -148,6 +232,11 @@ impl<B: Backend> AnnData<B> {
         &self.var
     }
 
+    /// Get the raw slot.
+    pub fn raw(&self) -> &Raw<B> {
+        &self.raw
+    }
+
     /// Open an existing AnnData store.
     pub fn open(file: B::Store) -> Result<Self> {
         let n_obs = Dim::empty();
@@ -211,7 +300,13 @@ impl<B: Backend> AnnData<B> {
             _ => AxisArrays::empty(),
         };
 
-        Ok(Self {
+        let raw = if file.exists("raw")? {
+            Slot::new(InnerRaw::open(file.open_group("raw")?, &n_obs)?)
+        } else {
+            Slot::none()
+        };
+
+        let adata = Self {
             file,
             n_obs,
             n_vars,
@@ -224,7 +319,47 @@ impl<B: Backend> AnnData<B> {
             varp,
             uns,
             layers,
-        })
+            raw,
+        };
+        if let Some(op) = adata.interrupted_operation()? {
+            warn!(
+                "'{}' may be corrupted: '{}' did not finish",
+                adata.filename().display(),
+                op,
+            );
+        }
+        Ok(adata)
+    }
+
+    /// Returns the name of the in-place operation, e.g., `set_x_from_iter` or
+    /// `append_obs`, that was interrupted while modifying the file, in which case
+    /// the file may be corrupted.
+    pub fn interrupted_operation(&self) -> Result<Option<String>> {
+        let group = self.file.open_group("/")?;
+        if group.has_attr(DIRTY_ATTR)? {
+            Ok(Some(group.get_attr(DIRTY_ATTR)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Record that the in-place operation `op` has started. The marker is only
+    /// removed by [`AnnData::mark_clean`], so it persists if the process dies.
+    ///
+    /// Attributes cannot be written in SWMR mode, so no marker is recorded then.
+    pub(crate) fn mark_dirty(&self, op: &str) -> Result<()> {
+        if self.file.is_swmr_write()? {
+            return Ok(());
+        }
+        self.file.open_group("/")?.new_attr(DIRTY_ATTR, op)
+    }
+
+    /// Record that the in-place operation has finished.
+    pub(crate) fn mark_clean(&self) -> Result<()> {
+        if self.file.is_swmr_write()? {
+            return Ok(());
+        }
+        self.file.open_group("/")?.delete_attr(DIRTY_ATTR)
     }
 
     /// Create a new AnnData file.
@@ -242,6 +377,7 @@ impl<B: Backend> AnnData<B> {
             varp: new_varp(new_mapping(&file, "varp")?, &n_vars)?,
             uns: ElemCollection::new(new_mapping(&file, "uns")?)?,
             layers: new_layers(new_mapping(&file, "layers")?, &n_obs, &n_vars)?,
+            raw: Slot::none(),
             file,
             n_obs,
             n_vars,
@@ -250,58 +386,105 @@ impl<B: Backend> AnnData<B> {
 
     /// Write the AnnData object to a new file.
     pub fn write<O: Backend, P: AsRef<Path>>(&self, filename: P) -> Result<()> {
+        self.write_with_policy::<O, _>(filename, &WritePolicy::default())
+    }
+
+    /// Write the AnnData object to a new file, using the configuration given by
+    /// `policy` for each element.
+    pub fn write_with_policy<O: Backend, P: AsRef<Path>>(
+        &self,
+        filename: P,
+        policy: &WritePolicy,
+    ) -> Result<()> {
         let file = O::new(filename)?;
         let _obs_lock = self.n_obs.lock();
         let _vars_lock = self.n_vars.lock();
         self.get_x()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "X"))
+            .map(|x| x.export_with_config::<O, _>(&file, "X", policy.get("X")))
             .transpose()?;
         self.get_obs()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "obs"))
+            .map(|x| x.export_with_config::<O, _>(&file, "obs", policy.get("obs")))
             .transpose()?;
         self.get_var()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "var"))
+            .map(|x| x.export_with_config::<O, _>(&file, "var", policy.get("var")))
             .transpose()?;
         self.obsm()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "obsm"))
+            .map(|x| x.export_with_policy::<O, _>(&file, "obsm", "obsm", policy))
             .transpose()?;
         self.obsp()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "obsp"))
+            .map(|x| x.export_with_policy::<O, _>(&file, "obsp", "obsp", policy))
             .transpose()?;
         self.varm()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "varm"))
+            .map(|x| x.export_with_policy::<O, _>(&file, "varm", "varm", policy))
             .transpose()?;
         self.varp()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "varp"))
+            .map(|x| x.export_with_policy::<O, _>(&file, "varp", "varp", policy))
             .transpose()?;
         self.uns()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "uns"))
+            .map(|x| x.export_with_policy::<O, _>(&file, "uns", "uns", policy))
             .transpose()?;
         self.layers()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "layers"))
+            .map(|x| x.export_with_policy::<O, _>(&file, "layers", "layers", policy))
+            .transpose()?;
+        self.raw()
+            .lock()
+            .as_ref()
+            .map(|x| x.export_with_policy::<O, _>(&file, "raw", policy))
             .transpose()?;
         file.close()?;
         Ok(())
     }
 
+    /// Write the AnnData object to a temporary file next to `filename`, which is
+    /// renamed to `filename` once everything has been written. If the process
+    /// dies halfway, `filename` is left untouched.
+    ///
+    /// Renaming a file is atomic. A directory, e.g., a Zarr store, cannot
+    /// atomically replace an existing one, so the existing store is first moved
+    /// to a sibling named `.old-<pid>-<name>` and only removed once the new one
+    /// is in place. If the process dies in between, the previous store is left
+    /// at that location.
+    pub fn write_atomic<O: Backend, P: AsRef<Path>>(&self, filename: P) -> Result<()> {
+        write_atomic(filename.as_ref(), |tmp| self.write::<O, _>(tmp))
+    }
+
+    /// Sets the 'X' element, writing it with the given configuration. An existing
+    /// 'X' is deleted first rather than overwritten in place.
+    pub fn set_x_with_config<D: Into<ArrayData>>(&self, data: D, config: &WriteConfig) -> Result<()> {
+        let data = data.into();
+        let shape = data.shape();
+        ensure!(
+            shape.ndim() >= 2,
+            "X must be a N dimensional array, where N >= 2"
+        );
+        self.n_obs.try_set(shape[0])?;
+        self.n_vars.try_set(shape[1])?;
+
+        self.mark_dirty("set_x_with_config")?;
+        self.x.clear()?;
+        let new_elem = ArrayElem::try_from(data.write_with_config(&self.file, "X", config)?)?;
+        self.x.swap(&new_elem);
+        self.mark_clean()
+    }
+
     /// Write a subset of the AnnData object to a new file.
     pub fn write_select<O, S, P>(&self, selection: S, filename: P) -> Result<()>
     where
@@ -365,15 +548,67 @@ impl<B: Backend> AnnData<B> {
             .as_mut()
             .map(|x| x.export_select(slice.as_slice(), &file, "layers"))
             .transpose()?;
+        self.raw()
+            .lock()
+            .as_ref()
+            .map(|x| x.export_select::<O, _>(slice[0], &file, "raw"))
+            .transpose()?;
         file.close()?;
         Ok(())
     }
 
+    /// Write a subset of the AnnData object to a new file, atomically. See
+    /// [`AnnData::write_atomic`].
+    pub fn write_select_atomic<O, S, P>(&self, selection: S, filename: P) -> Result<()>
+    where
+        O: Backend,
+        S: AsRef<[SelectInfoElem]>,
+        P: AsRef<Path>,
+    {
+        write_atomic(filename.as_ref(), |tmp| {
+            self.write_select::<O, _, _>(selection, tmp)
+        })
+    }
+
     /// Get the filename of the AnnData file.
     pub fn filename(&self) -> PathBuf {
         self.file.filename()
     }
 
+    /// Write buffered changes to the file.
+    pub fn flush(&self) -> Result<()> {
+        self.file.flush()
+    }
+
+    /// Switch the file to single-writer/multiple-reader (SWMR) mode, so that
+    /// other processes can read it while `X` is grown by
+    /// [`AnnData::append_x_rows`]. Elements can no longer be added or removed
+    /// afterwards. Readers call [`AnnData::refresh`] to see the new rows.
+    pub fn start_swmr_write(&self) -> Result<()> {
+        self.file.start_swmr_write()
+    }
+
+    /// Reload the shape of `X` and update the number of observations. This lets
+    /// a reader follow an AnnData object that is being grown by another process
+    /// with [`AnnData::append_x_rows`].
+    pub fn refresh(&self) -> Result<()> {
+        let mut obs_lock = self.n_obs.lock();
+        if let Some(x) = self.x.lock().as_mut() {
+            x.refresh()?;
+            let n_obs = x.shape()[0];
+            if obs_lock.get() != n_obs {
+                ensure!(
+                    self.obs.is_none(),
+                    "the number of observations changed from {} to {}, but obs was not updated",
+                    obs_lock.get(),
+                    n_obs,
+                );
+                obs_lock.set(n_obs);
+            }
+        }
+        Ok(())
+    }
+
     /// Close the AnnData object and release all resources.
     pub fn close(self) -> Result<()> {
         macro_rules! close {
@@ -388,10 +623,22 @@ impl<B: Backend> AnnData<B> {
         self.obs.drop();
         self.var.drop();
         close!(obsm, obsp, varm, varp, uns);
+        if let Some(raw) = self.raw.lock().as_ref() {
+            raw.close();
+        }
+        self.raw.drop();
         self.file.close()
     }
 
-    /// Subset the AnnData object based on a selection.
+    /// Returns a lazy, read-only view of the selected observations and variables.
+    /// Unlike [`AnnData::subset`], the data is left untouched.
+    pub fn view<S: AsRef<[SelectInfoElem]>>(&self, selection: S) -> Result<AnnDataView<&Self>> {
+        AnnDataView::new(self, selection)
+    }
+
+    /// Subset the AnnData object based on a selection. The file is marked as
+    /// dirty until all elements have been subset, see
+    /// [`AnnData::interrupted_operation`].
     pub fn subset<S>(&self, selection: S) -> Result<()>
     where
         S: AsRef<[SelectInfoElem]>,
@@ -406,6 +653,7 @@ impl<B: Backend> AnnData<B> {
         let obs_ix = &slice[0];
         let var_ix = &slice[1];
 
+        self.mark_dirty("subset")?;
         self.x
             .lock()
             .as_mut()
@@ -450,6 +698,12 @@ impl<B: Backend> AnnData<B> {
             .map(|layers| layers.subset(&[obs_ix, var_ix]))
             .transpose()?;
 
+        self.raw
+            .lock()
+            .as_ref()
+            .map(|raw| raw.subset(obs_ix))
+            .transpose()?;
+
         if !obs_lock.is_empty() {
             obs_lock.set(SelectInfoElemBounds::new(obs_ix, obs_lock.get()).len());
         }
@@ -457,6 +711,6 @@ impl<B: Backend> AnnData<B> {
             vars_lock.set(SelectInfoElemBounds::new(var_ix, vars_lock.get()).len());
         }
 
-        Ok(())
+        self.mark_clean()
     }
 }
\ No newline at end of file
@@ -0,0 +1,476 @@
+use crate::{
+    anndata::AnnData,
+    backend::{AttributeOp, Backend, BackendData, DataContainer, GroupOp, Layout, ScalarType, WriteConfig},
+    container::{ArrayElem, AxisArrays, DataFrameElem, InnerDataFrameElem},
+    data::*,
+    traits::AnnDataOp,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use polars::prelude::DataFrame;
+use std::collections::{HashMap, HashSet};
+
+/// New observations to be appended to an AnnData object by [`AnnData::append_obs`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObsData {
+    /// Data matrix of the new observations.
+    pub x: ArrayData,
+    /// Observations metadata. It must have the same columns as the existing
+    /// `obs`, or be empty if `obs` has no columns.
+    pub obs: DataFrame,
+    /// Names of the new observations.
+    pub obs_names: DataFrameIndex,
+    /// Names of the columns of `x` and `layers`. If given, the columns are
+    /// reordered to match the variables of the AnnData object.
+    pub var_names: Option<DataFrameIndex>,
+    /// Observation multi-dimensional annotation.
+    pub obsm: HashMap<String, ArrayData>,
+    /// Layers of data.
+    pub layers: HashMap<String, ArrayData>,
+    /// Rows of the data matrix of `raw`, with one column per raw variable in
+    /// the order of `raw`. Required if `raw` has a data matrix.
+    pub raw_x: Option<ArrayData>,
+}
+
+impl ObsData {
+    pub fn new<D: Into<ArrayData>>(obs_names: DataFrameIndex, x: D) -> Self {
+        Self {
+            x: x.into(),
+            obs: DataFrame::empty(),
+            obs_names,
+            var_names: None,
+            obsm: HashMap::new(),
+            layers: HashMap::new(),
+            raw_x: None,
+        }
+    }
+}
+
+/// New variables to be appended to an AnnData object by [`AnnData::append_vars`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarData {
+    /// Data matrix of the new variables, with one column per variable.
+    pub x: ArrayData,
+    /// Variables metadata. It must have the same columns as the existing
+    /// `var`, or be empty if `var` has no columns.
+    pub var: DataFrame,
+    /// Names of the new variables.
+    pub var_names: DataFrameIndex,
+    /// Names of the rows of `x` and `layers`. If given, the rows are
+    /// reordered to match the observations of the AnnData object.
+    pub obs_names: Option<DataFrameIndex>,
+    /// Variable multi-dimensional annotation.
+    pub varm: HashMap<String, ArrayData>,
+    /// Layers of data.
+    pub layers: HashMap<String, ArrayData>,
+}
+
+impl VarData {
+    pub fn new<D: Into<ArrayData>>(var_names: DataFrameIndex, x: D) -> Self {
+        Self {
+            x: x.into(),
+            var: DataFrame::empty(),
+            var_names,
+            obs_names: None,
+            varm: HashMap::new(),
+            layers: HashMap::new(),
+        }
+    }
+}
+
+impl<B: Backend> AnnData<B> {
+    /// Append observations to the AnnData object. `X`, `obsm`, `layers` and
+    /// `raw/X` are grown in place, without rewriting the existing rows. The
+    /// columns of `obs` are rewritten.
+    ///
+    /// Every element aligned with the observations must receive the new rows,
+    /// i.e., the keys of `obsm` and `layers` must match the existing ones, and
+    /// `raw_x` must be given if `raw` has a data matrix. The new names must be
+    /// unique and differ from the existing ones. Objects with `obsp` cannot be
+    /// appended to. All inputs are checked before anything is written.
+    ///
+    /// The file is marked as dirty until all elements have been written, see
+    /// [`AnnData::interrupted_operation`].
+    pub fn append_obs(&self, data: ObsData) -> Result<()> {
+        let mut obs_lock = self.n_obs.lock();
+        let vars_lock = self.n_vars.lock();
+        let n_obs = obs_lock.get();
+        let n_vars = vars_lock.get();
+        let m = data.obs_names.len();
+
+        ensure!(!self.x.is_none(), "cannot append observations as X is not set");
+        ensure!(
+            self.obsp.is_empty(),
+            "cannot append observations to an AnnData object with obsp"
+        );
+
+        let (x, layers) = match data.var_names {
+            Some(names) => {
+                let ix = align(&names, self.var_names(), n_vars, "variables")?;
+                reorder(1, ix, data.x, data.layers)
+            }
+            None => (data.x, data.layers),
+        };
+
+        // Validate all inputs.
+        check_names(self.obs_names(), n_obs, &data.obs_names, "observations")?;
+        check_append(&self.x, 0, &x, m, "X")?;
+        check_axis_arrays(&self.obsm, 0, &data.obsm, m, "obsm")?;
+        check_axis_arrays(&self.layers, 0, &layers, m, "layers")?;
+        check_dataframe(&self.obs, &data.obs, m, "obs")?;
+        let raw = self.raw.lock();
+        let raw_x = raw.as_ref().map(|raw| &raw.x).filter(|x| !x.is_none());
+        match (raw_x, &data.raw_x) {
+            (Some(elem), Some(x)) => check_append(elem, 0, x, m, "raw/X")?,
+            (Some(_), None) => bail!("raw_x must be given as raw has a data matrix"),
+            (None, Some(_)) => bail!("raw_x is given but raw has no data matrix"),
+            (None, None) => {}
+        }
+        if m == 0 {
+            return Ok(());
+        }
+
+        self.mark_dirty("append_obs")?;
+        self.x.inner().append_axis(0, &x)?;
+        append_axis_arrays(&self.obsm, 0, &data.obsm)?;
+        append_axis_arrays(&self.layers, 0, &layers)?;
+        if let (Some(elem), Some(x)) = (raw_x, &data.raw_x) {
+            elem.inner().append_axis(0, x)?;
+        }
+        if self.obs.is_none() {
+            let index = DataFrameIndex::from(n_obs)
+                .into_iter()
+                .chain(data.obs_names)
+                .collect();
+            let df = InnerDataFrameElem::new(&self.file, "obs", Some(index), &DataFrame::empty())?;
+            self.obs.insert(df);
+        } else {
+            self.obs.inner().append_rows(&data.obs_names, &data.obs)?;
+        }
+        obs_lock.set(n_obs + m);
+        self.mark_clean()
+    }
+
+    /// Append variables to the AnnData object. `X`, `varm` and `layers` are
+    /// grown in place. Every row of a csr matrix receives new entries, so its
+    /// `data` and `indices` arrays are rewritten in place, chunk by chunk. The
+    /// columns of `var` are rewritten.
+    ///
+    /// Every element aligned with the variables must receive the new columns,
+    /// i.e., the keys of `varm` and `layers` must match the existing ones.
+    /// The new names must be unique and differ from the existing ones. Objects
+    /// with `varp` cannot be appended to. `raw` has its own variables and is
+    /// left untouched. All inputs are checked before anything is written.
+    pub fn append_vars(&self, data: VarData) -> Result<()> {
+        let obs_lock = self.n_obs.lock();
+        let mut vars_lock = self.n_vars.lock();
+        let n_obs = obs_lock.get();
+        let n_vars = vars_lock.get();
+        let m = data.var_names.len();
+
+        ensure!(!self.x.is_none(), "cannot append variables as X is not set");
+        ensure!(
+            self.varp.is_empty(),
+            "cannot append variables to an AnnData object with varp"
+        );
+
+        let (x, layers) = match data.obs_names {
+            Some(names) => {
+                let ix = align(&names, self.obs_names(), n_obs, "observations")?;
+                reorder(0, ix, data.x, data.layers)
+            }
+            None => (data.x, data.layers),
+        };
+
+        // Validate all inputs.
+        check_names(self.var_names(), n_vars, &data.var_names, "variables")?;
+        check_append(&self.x, 1, &x, m, "X")?;
+        check_axis_arrays(&self.varm, 0, &data.varm, m, "varm")?;
+        check_axis_arrays(&self.layers, 1, &layers, m, "layers")?;
+        check_dataframe(&self.var, &data.var, m, "var")?;
+        if m == 0 {
+            return Ok(());
+        }
+
+        self.mark_dirty("append_vars")?;
+        self.x.inner().append_axis(1, &x)?;
+        append_axis_arrays(&self.varm, 0, &data.varm)?;
+        append_axis_arrays(&self.layers, 1, &layers)?;
+        if self.var.is_none() {
+            let index = DataFrameIndex::from(n_vars)
+                .into_iter()
+                .chain(data.var_names)
+                .collect();
+            let df = InnerDataFrameElem::new(&self.file, "var", Some(index), &DataFrame::empty())?;
+            self.var.insert(df);
+        } else {
+            self.var.inner().append_rows(&data.var_names, &data.var)?;
+        }
+        vars_lock.set(n_vars + m);
+        self.mark_clean()
+    }
+
+    /// Create an empty, dense `X` with `n_vars` columns, which can then be grown
+    /// by [`AnnData::append_x_rows`].
+    pub fn new_empty_x<T: BackendData>(&self, n_vars: usize, config: WriteConfig) -> Result<()> {
+        ensure!(self.x.is_none(), "X is already set");
+        ensure!(
+            config.layout != Layout::Contiguous,
+            "X cannot be grown if it has a contiguous layout"
+        );
+        self.n_obs.try_set(0)?;
+        self.n_vars.try_set(n_vars)?;
+
+        let config = WriteConfig {
+            block_size: config
+                .block_size
+                .or_else(|| Some(vec![1000, n_vars.clamp(1, 1000)].into())),
+            ..config
+        };
+        let dataset = self
+            .file
+            .new_empty_dataset::<T>("X", &(0, n_vars).into(), config)?;
+        let encoding_type = if T::DTYPE == ScalarType::String {
+            "string-array"
+        } else {
+            "array"
+        };
+        let mut container = DataContainer::<B>::Dataset(dataset);
+        container.new_attr("encoding-type", encoding_type)?;
+        container.new_attr("encoding-version", "0.2.0")?;
+        self.x.swap(&ArrayElem::try_from(container)?);
+        Ok(())
+    }
+
+    /// Append rows to a dense `X`, leaving all other elements untouched. Unlike
+    /// [`AnnData::append_obs`], no objects or attributes are written, so rows
+    /// can still be appended after [`AnnData::start_swmr_write`].
+    ///
+    /// As the number of observations changes, the object must not have `obs`,
+    /// `obsm`, `obsp`, `layers` or `raw`.
+    pub fn append_x_rows<D: Into<ArrayData>>(&self, data: D) -> Result<()> {
+        let mut obs_lock = self.n_obs.lock();
+        let n_obs = obs_lock.get();
+        let data = data.into();
+
+        ensure!(!self.x.is_none(), "cannot append rows as X is not set");
+        ensure!(
+            matches!(data, ArrayData::Array(_)),
+            "cannot append rows of type {}, only dense arrays are supported",
+            data.data_type(),
+        );
+        ensure!(
+            self.obs.is_none()
+                && self.obsm.is_empty()
+                && self.obsp.is_empty()
+                && self.layers.is_empty()
+                && self.raw.is_none(),
+            "cannot append rows to X alone as other elements have observations, use append_obs instead"
+        );
+        let m = data.shape()[0];
+        check_append(&self.x, 0, &data, m, "X")?;
+        if m == 0 {
+            return Ok(());
+        }
+
+        self.x.inner().append_axis(0, &data)?;
+        obs_lock.set(n_obs + m);
+        Ok(())
+    }
+}
+
+/// Return the positions of the `n` names in `target` within `names`, or `None`
+/// if the names are already in the same order.
+fn align(
+    names: &DataFrameIndex,
+    target: DataFrameIndex,
+    n: usize,
+    label: &str,
+) -> Result<Option<SelectInfoElem>> {
+    if names.len() == n && target.len() == n && *names == target {
+        return Ok(None);
+    }
+    ensure!(
+        target.len() == n,
+        "cannot align {} as the AnnData object has no names for them",
+        label
+    );
+    ensure!(
+        names.len() == n,
+        "cannot align {} {} to the {} {} of the AnnData object",
+        names.len(),
+        label,
+        n,
+        label,
+    );
+    let ix = target
+        .into_iter()
+        .map(|name| {
+            names
+                .get_index(&name)
+                .with_context(|| format!("'{}' is missing from the new {}", name, label))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(ix.into()))
+}
+
+/// Select `ix` along `axis` of `x` and `layers`.
+fn reorder(
+    axis: usize,
+    ix: Option<SelectInfoElem>,
+    x: ArrayData,
+    layers: HashMap<String, ArrayData>,
+) -> (ArrayData, HashMap<String, ArrayData>) {
+    match ix {
+        Some(ix) => (
+            x.select_axis(axis, &ix),
+            layers
+                .into_iter()
+                .map(|(k, v)| (k, v.select_axis(axis, &ix)))
+                .collect(),
+        ),
+        None => (x, layers),
+    }
+}
+
+/// Check that the new `names` are unique and not among the names of the `n`
+/// existing entries. Entries without names are named by their position, as in
+/// the index written for them.
+fn check_names(
+    existing: DataFrameIndex,
+    n: usize,
+    names: &DataFrameIndex,
+    label: &str,
+) -> Result<()> {
+    let existing = if existing.len() == n {
+        existing
+    } else {
+        DataFrameIndex::from(n)
+    };
+    let mut seen: HashSet<String> = existing.into_iter().collect();
+    names.clone().into_iter().try_for_each(|name| {
+        ensure!(
+            !seen.contains(&name),
+            "cannot append {} as the name '{}' is already used",
+            label,
+            name
+        );
+        seen.insert(name);
+        Ok(())
+    })
+}
+
+/// Check that `data` can be appended to `elem` as `m` new entries along `axis`.
+fn check_append<B: Backend>(
+    elem: &ArrayElem<B>,
+    axis: usize,
+    data: &ArrayData,
+    m: usize,
+    name: &str,
+) -> Result<()> {
+    let elem = elem.inner();
+    let shape = data.shape();
+    ensure!(
+        shape[axis] == m,
+        "'{}' has length {} along axis {}, but {} entries are appended",
+        name,
+        shape[axis],
+        axis,
+        m,
+    );
+    ensure!(
+        shape.ndim() == elem.shape().ndim()
+            && (0..shape.ndim()).all(|i| i == axis || shape[i] == elem.shape()[i]),
+        "cannot append data of shape {} to '{}' of shape {}",
+        shape,
+        name,
+        elem.shape(),
+    );
+    ensure!(
+        data.data_type() == elem.dtype(),
+        "cannot append {} to '{}' of type {}",
+        data.data_type(),
+        name,
+        elem.dtype(),
+    );
+    ensure!(
+        elem.is_resizable()?,
+        "cannot append to '{}' as it was written with a contiguous layout, which cannot be resized",
+        name,
+    );
+    Ok(())
+}
+
+fn check_axis_arrays<B: Backend>(
+    arrays: &AxisArrays<B>,
+    axis: usize,
+    data: &HashMap<String, ArrayData>,
+    m: usize,
+    name: &str,
+) -> Result<()> {
+    let arrays = arrays.lock();
+    let n = arrays.as_ref().map_or(0, |x| x.len());
+    ensure!(
+        n == data.len()
+            && data
+                .keys()
+                .all(|k| arrays.as_ref().is_some_and(|x| x.contains_key(k))),
+        "the keys of '{}' do not match those of the AnnData object",
+        name
+    );
+    if let Some(arrays) = arrays.as_ref() {
+        arrays.iter().try_for_each(|(k, elem)| {
+            check_append(elem, axis, &data[k], m, &format!("{}/{}", name, k))
+        })?;
+    }
+    Ok(())
+}
+
+fn check_dataframe<B: Backend>(
+    elem: &DataFrameElem<B>,
+    data: &DataFrame,
+    m: usize,
+    name: &str,
+) -> Result<()> {
+    ensure!(
+        data.height() == 0 || data.height() == m,
+        "'{}' has {} rows, but {} entries are appended",
+        name,
+        data.height(),
+        m,
+    );
+    match elem.lock().as_ref() {
+        Some(elem) if elem.width() > 0 || data.width() > 0 => {
+            let columns = elem.column_types()?;
+            ensure!(
+                data.height() == m
+                    && columns.len() == data.width()
+                    && columns.iter().all(|(name, dtype)| {
+                        data.column(name).is_ok_and(|x| x.dtype() == dtype)
+                    }),
+                "the columns of '{}' do not match those of the AnnData object",
+                name
+            );
+        }
+        None => ensure!(
+            data.width() == 0,
+            "cannot append columns to '{}' as it does not exist",
+            name
+        ),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn append_axis_arrays<B: Backend>(
+    arrays: &AxisArrays<B>,
+    axis: usize,
+    data: &HashMap<String, ArrayData>,
+) -> Result<()> {
+    if let Some(arrays) = arrays.lock().as_ref() {
+        arrays
+            .iter()
+            .try_for_each(|(k, elem)| elem.inner().append_axis(axis, &data[k]))?;
+    }
+    Ok(())
+}
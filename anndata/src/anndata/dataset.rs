@@ -1,5 +1,5 @@
 use crate::{
-    anndata::AnnData,
+    anndata::{AnnData, AnnDataView},
     backend::Backend,
     container::{
         Axis, AxisArrays, Dim, Slot, StackedArrayElem, StackedAxisArrays, StackedDataFrame,
@@ -247,6 +247,12 @@ impl<B: Backend> AnnDataSet<B> {
         })
     }
 
+    /// Returns a lazy, read-only view of the selected observations and variables.
+    /// Unlike [`AnnDataSet::write_select`], the rows keep the order of the selection.
+    pub fn view<S: AsRef<[SelectInfoElem]>>(&self, selection: S) -> Result<AnnDataView<&Self>> {
+        AnnDataView::new(self, selection)
+    }
+
     /// AnnDataSet will not move data across underlying AnnData objects. So the
     /// orders of rows in the resultant AnnDataSet object may not be consistent
     /// with the input `obs_indices`. This function will return a vector that can
@@ -305,9 +311,7 @@ impl<B: Backend> AnnDataSet<B> {
         self.annotation.write::<O, _>(&out)?;
         let adata = AnnData::open(O::open_rw(&out)?)?;
         if copy_x {
-            adata.set_x_from_iter::<_, ArrayData>(
-                self.anndatas.inner().x.chunked(500).map(|x| x.0),
-            )?;
+            self.copy_x(&adata)?;
         }
         Ok(adata)
     }
@@ -330,9 +334,7 @@ impl<B: Backend> AnnDataSet<B> {
     /// Convert AnnDataSet to AnnData object
     pub fn into_adata(self, copy_x: bool) -> Result<AnnData<B>> {
         if copy_x {
-            self.annotation.set_x_from_iter::<_, ArrayData>(
-                self.anndatas.inner().x.chunked(500).map(|x| x.0),
-            )?;
+            self.copy_x(&self.annotation)?;
         }
         for ann in self.anndatas.extract().unwrap().elems.into_values() {
             ann.close()?;
@@ -340,6 +342,30 @@ impl<B: Backend> AnnDataSet<B> {
         Ok(self.annotation)
     }
 
+    /// Write the stacked X of the underlying AnnData objects to `adata`
+    /// chunk by chunk. `adata` is left without X if reading a chunk fails.
+    fn copy_x<O: Backend>(&self, adata: &AnnData<O>) -> Result<()> {
+        let mut error = None;
+        let chunks = self
+            .anndatas
+            .inner()
+            .x
+            .chunked::<ArrayData>(500)
+            .map_while(|chunk| match chunk {
+                Ok((data, _, _)) => Some(data),
+                Err(e) => {
+                    error = Some(e);
+                    None
+                }
+            });
+        let result = adata.set_x_from_iter(chunks);
+        if let Some(e) = error {
+            adata.del_x()?;
+            return Err(e);
+        }
+        result
+    }
+
     pub fn close(self) -> Result<()> {
         self.annotation.close()?;
         for ann in self.anndatas.extract().unwrap().elems.into_values() {
@@ -0,0 +1,392 @@
+use crate::{
+    anndata::{new_mapping, new_varm},
+    backend::{Backend, DataContainer, GroupOp, WritePolicy},
+    container::{ArrayElem, AxisArrays, DataFrameElem, Dim, InnerDataFrameElem, Slot},
+    data::*,
+};
+
+use anyhow::{ensure, Context, Result};
+use polars::prelude::DataFrame;
+use std::collections::HashMap;
+
+/// In-memory representation of the `.raw` slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawData {
+    /// Data matrix. The number of rows must match that of the parent object.
+    pub x: ArrayData,
+    /// Variables metadata.
+    pub var: DataFrame,
+    /// Names of the variables.
+    pub var_names: DataFrameIndex,
+    /// Variable multi-dimensional annotation.
+    pub varm: HashMap<String, ArrayData>,
+}
+
+impl RawData {
+    pub fn new<D: Into<ArrayData>>(x: D) -> Self {
+        let x = x.into();
+        let n_vars = x.shape()[1];
+        Self {
+            x,
+            var: DataFrame::empty(),
+            var_names: n_vars.into(),
+            varm: HashMap::new(),
+        }
+    }
+}
+
+/// The `.raw` slot of an AnnData object. It shares the observations with the
+/// parent object but has its own set of variables, typically the unfiltered
+/// ones.
+pub struct InnerRaw<B: Backend> {
+    pub(crate) group: B::Group,
+    pub(crate) n_vars: Dim,
+    pub(crate) x: ArrayElem<B>,
+    pub(crate) var: DataFrameElem<B>,
+    pub(crate) varm: AxisArrays<B>,
+}
+
+impl<B: Backend> std::fmt::Debug for InnerRaw<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl<B: Backend> std::fmt::Display for InnerRaw<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Raw with n_vars = {}", self.n_vars)
+    }
+}
+
+pub type Raw<B> = Slot<InnerRaw<B>>;
+
+impl<B: Backend> InnerRaw<B> {
+    /// Open the raw group. `n_obs` is the number of observations of the parent.
+    pub(crate) fn open(group: B::Group, n_obs: &Dim) -> Result<Self> {
+        let n_vars = Dim::empty();
+
+        let x = if group.exists("X")? {
+            let x = ArrayElem::try_from(DataContainer::open(&group, "X")?)?;
+            n_obs.try_set(x.inner().shape()[0])?;
+            n_vars.try_set(x.inner().shape()[1])?;
+            x
+        } else {
+            Slot::none()
+        };
+
+        let var = if group.exists("var")? {
+            let var = DataFrameElem::try_from(DataContainer::open(&group, "var")?)?;
+            n_vars.try_set(var.inner().height())?;
+            var
+        } else {
+            Slot::none()
+        };
+
+        let varm = match group.open_group("varm") {
+            Ok(g) => new_varm(g, &n_vars)?,
+            _ => AxisArrays::empty(),
+        };
+
+        Ok(Self {
+            group,
+            n_vars,
+            x,
+            var,
+            varm,
+        })
+    }
+
+    /// Write `data` to a new raw group at `location`.
+    pub(crate) fn new<G: GroupOp<B>>(
+        location: &G,
+        name: &str,
+        n_obs: &Dim,
+        data: RawData,
+    ) -> Result<Self> {
+        let shape = data.x.shape();
+        check_x(&shape, &data.var_names)?;
+        n_obs.try_set(shape[0])?;
+
+        let group = location.new_group(name)?;
+        let x = ArrayElem::try_from(data.x.write(&group, "X")?)?;
+        let raw = Self::with_x(group, x, data.var_names, &data.var)?;
+        data.varm
+            .into_iter()
+            .try_for_each(|(k, v)| raw.varm.inner().add_data(&k, v))?;
+        Ok(raw)
+    }
+
+    /// Create a new raw group at `location`, writing the data matrix chunk by
+    /// chunk. The group is deleted if the matrix does not match `var_names` or
+    /// the number of observations of the parent.
+    pub(crate) fn new_from_iter<G, I, D>(
+        location: &G,
+        name: &str,
+        n_obs: &Dim,
+        iter: I,
+        var_names: DataFrameIndex,
+        var: &DataFrame,
+    ) -> Result<Self>
+    where
+        G: GroupOp<B>,
+        I: Iterator<Item = D>,
+        D: ArrayChunk,
+    {
+        let group = location.new_group(name)?;
+        let x = ArrayElem::try_from(ArrayChunk::write_by_chunk(iter, &group, "X")?)?;
+        let shape = x.inner().shape().clone();
+        if let Err(e) = check_x(&shape, &var_names).and_then(|_| n_obs.try_set(shape[0])) {
+            x.drop();
+            DataContainer::<B>::delete(DataContainer::Group(group))?;
+            return Err(e);
+        }
+        Self::with_x(group, x, var_names, var)
+    }
+
+    /// Write the variables metadata and an empty `varm` next to `x`.
+    fn with_x(
+        group: B::Group,
+        x: ArrayElem<B>,
+        var_names: DataFrameIndex,
+        var: &DataFrame,
+    ) -> Result<Self> {
+        let n_vars = Dim::new(var_names.len());
+        let var = Slot::new(InnerDataFrameElem::new(&group, "var", Some(var_names), var)?);
+        let varm = new_varm(new_mapping(&group, "varm")?, &n_vars)?;
+        Ok(Self {
+            group,
+            n_vars,
+            x,
+            var,
+            varm,
+        })
+    }
+
+    /// Returns the data matrix.
+    pub fn get_x(&self) -> &ArrayElem<B> {
+        &self.x
+    }
+
+    /// Returns the variables metadata.
+    pub fn get_var(&self) -> &DataFrameElem<B> {
+        &self.var
+    }
+
+    /// Returns the variable multi-dimensional annotation.
+    pub fn varm(&self) -> &AxisArrays<B> {
+        &self.varm
+    }
+
+    pub fn n_vars(&self) -> usize {
+        self.n_vars.get()
+    }
+
+    pub fn var_names(&self) -> DataFrameIndex {
+        self.var
+            .lock()
+            .as_ref()
+            .map_or(self.n_vars().into(), |var| var.index.clone())
+    }
+
+    /// Read the data matrix into memory.
+    pub fn read_x(&self) -> Result<ArrayData> {
+        self.x
+            .lock()
+            .as_mut()
+            .map(|x| x.data())
+            .transpose()?
+            .context("raw slot does not contain X")
+    }
+
+    /// Read the rows of the data matrix selected by `slice` into memory.
+    pub fn read_x_slice<S: AsRef<SelectInfoElem>>(&self, slice: S) -> Result<ArrayData> {
+        self.x
+            .lock()
+            .as_mut()
+            .map(|x| x.select_axis(0, slice))
+            .transpose()?
+            .context("raw slot does not contain X")
+    }
+
+    /// Read the variables metadata into memory.
+    pub fn read_var(&self) -> Result<DataFrame> {
+        self.var
+            .lock()
+            .as_mut()
+            .map_or(Ok(DataFrame::empty()), |x| x.data().cloned())
+    }
+
+    /// Read the whole raw slot into memory.
+    pub fn data(&self) -> Result<RawData> {
+        let x = self.read_x()?;
+        let var = self.read_var()?;
+        let varm = self
+            .varm
+            .lock()
+            .as_ref()
+            .map(|arrays| {
+                arrays
+                    .iter()
+                    .map(|(k, v)| Ok((k.clone(), v.inner().data()?)))
+                    .collect::<Result<HashMap<_, _>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        Ok(RawData {
+            x,
+            var,
+            var_names: self.var_names(),
+            varm,
+        })
+    }
+
+    /// Export the raw slot using the configurations given by `policy`, which
+    /// are looked up with paths under `name`, e.g., `raw/X`.
+    pub(crate) fn export_with_policy<O: Backend, G: GroupOp<O>>(
+        &self,
+        location: &G,
+        name: &str,
+        policy: &WritePolicy,
+    ) -> Result<()> {
+        let group = location.new_group(name)?;
+        self.x
+            .lock()
+            .as_ref()
+            .map(|x| {
+                let config = policy.get(&format!("{}/X", name));
+                x.export_with_config::<O, _>(&group, "X", config)
+            })
+            .transpose()?;
+        self.var
+            .lock()
+            .as_ref()
+            .map(|x| {
+                let config = policy.get(&format!("{}/var", name));
+                x.export_with_config::<O, _>(&group, "var", config)
+            })
+            .transpose()?;
+        self.varm
+            .lock()
+            .as_ref()
+            .map(|x| {
+                let path = format!("{}/varm", name);
+                x.export_with_policy::<O, _>(&group, "varm", &path, policy)
+            })
+            .transpose()?;
+        Ok(())
+    }
+
+    /// Export the raw slot with the observations selected by `obs_ix`. All
+    /// variables are kept.
+    pub(crate) fn export_select<O: Backend, G: GroupOp<O>>(
+        &self,
+        obs_ix: &SelectInfoElem,
+        location: &G,
+        name: &str,
+    ) -> Result<()> {
+        let group = location.new_group(name)?;
+        self.x
+            .lock()
+            .as_mut()
+            .map(|x| x.export_axis::<O, _>(0, obs_ix, &group, "X"))
+            .transpose()?;
+        self.var
+            .lock()
+            .as_ref()
+            .map(|x| x.export::<O, _>(&group, "var"))
+            .transpose()?;
+        self.varm
+            .lock()
+            .as_ref()
+            .map(|x| x.export::<O, _>(&group, "varm"))
+            .transpose()?;
+        Ok(())
+    }
+
+    /// Subset the observations of the raw slot in place.
+    pub(crate) fn subset(&self, obs_ix: &SelectInfoElem) -> Result<()> {
+        self.x
+            .lock()
+            .as_mut()
+            .map(|x| x.subset_axis(0, obs_ix))
+            .transpose()?;
+        Ok(())
+    }
+
+    pub(crate) fn close(&self) {
+        self.x.drop();
+        self.var.drop();
+        if let Some(varm) = self.varm.lock().as_ref() {
+            varm.values().for_each(|x| x.drop());
+        }
+        self.varm.drop();
+    }
+}
+
+fn check_x(shape: &Shape, var_names: &DataFrameIndex) -> Result<()> {
+    ensure!(
+        shape.ndim() == 2,
+        "raw X must be a 2 dimensional array, but got {} dimensions",
+        shape.ndim()
+    );
+    ensure!(
+        var_names.len() == shape[1],
+        "the number of raw var names ({}) differs from the number of columns of raw X ({})",
+        var_names.len(),
+        shape[1]
+    );
+    Ok(())
+}
+
+impl<B: Backend> Raw<B> {
+    /// Delete and Remove the data from the element.
+    pub fn clear(&self) -> Result<()> {
+        if let Some(raw) = self.extract() {
+            raw.close();
+            DataContainer::<B>::delete(DataContainer::Group(raw.group))?;
+        }
+        Ok(())
+    }
+
+    /// Write a new raw slot with `write` under a temporary name next to `name`,
+    /// leaving the existing slot untouched. Returns the temporary name, to be
+    /// passed to [`Raw::replace`]. Nothing is left behind if `write` fails.
+    pub(crate) fn stage<G, F>(location: &G, name: &str, write: F) -> Result<String>
+    where
+        G: GroupOp<B>,
+        F: FnOnce(&G, &str) -> Result<InnerRaw<B>>,
+    {
+        let tmp = format!("{}.tmp", name);
+        if location.exists(&tmp)? {
+            location.delete(&tmp)?;
+        }
+        match write(location, &tmp) {
+            Ok(raw) => raw.close(),
+            Err(e) => {
+                if location.exists(&tmp).unwrap_or(false) {
+                    let _ = location.delete(&tmp);
+                }
+                return Err(e);
+            }
+        }
+        Ok(tmp)
+    }
+
+    /// Replace the raw slot stored at `name` in `location` by the one written
+    /// under `staged` by [`Raw::stage`].
+    pub(crate) fn replace<G>(
+        &self,
+        location: &G,
+        staged: &str,
+        name: &str,
+        n_obs: &Dim,
+    ) -> Result<()>
+    where
+        G: GroupOp<B>,
+    {
+        self.clear()?;
+        location.rename(staged, name)?;
+        self.insert(InnerRaw::open(location.open_group(name)?, n_obs)?);
+        Ok(())
+    }
+}
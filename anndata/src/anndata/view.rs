@@ -0,0 +1,524 @@
+use crate::{
+    anndata::RawData,
+    backend::DataType,
+    data::{array::slice::SliceBounds, *},
+    traits::{AnnDataOp, ArrayElemOp, AxisArraysOp},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use ndarray::Slice;
+use polars::prelude::DataFrame;
+use smallvec::{smallvec, SmallVec};
+use std::sync::Arc;
+
+/// A lazy view of the selected observations and variables of an AnnData
+/// object, e.g., `&AnnData<B>` or `&AnnDataSet<B>`.
+///
+/// Creating a view does not read any data. Selections are resolved against the
+/// underlying elements when the data is accessed. Views are read-only: all
+/// methods that modify the object return an error.
+#[derive(Clone)]
+pub struct AnnDataView<A> {
+    adata: A,
+    /// Selected observations, in the form returned by `resolve`.
+    obs: Arc<SelectInfoElem>,
+    /// Selected variables, in the form returned by `resolve`.
+    var: Arc<SelectInfoElem>,
+}
+
+impl<A: AnnDataOp> AnnDataView<A> {
+    /// Creates a view of `adata`. The selection must contain exactly two
+    /// elements: the observations and the variables.
+    pub fn new<S: AsRef<[SelectInfoElem]>>(adata: A, selection: S) -> Result<Self> {
+        let selection = selection.as_ref();
+        ensure!(selection.len() == 2, "selection must be 2D");
+        let obs = resolve(&selection[0], adata.n_obs())
+            .map_err(|e| anyhow!("AnnDataView obs {}", e))?;
+        let var = resolve(&selection[1], adata.n_vars())
+            .map_err(|e| anyhow!("AnnDataView var {}", e))?;
+        Ok(Self {
+            adata,
+            obs: Arc::new(obs),
+            var: Arc::new(var),
+        })
+    }
+
+    /// Creates a view of this view. The selection is relative to this view,
+    /// and is composed with its selections so that the new view refers to the
+    /// underlying object directly.
+    pub fn view<S: AsRef<[SelectInfoElem]>>(&self, selection: S) -> Result<Self>
+    where
+        A: Clone,
+    {
+        let selection = selection.as_ref();
+        ensure!(selection.len() == 2, "selection must be 2D");
+        let obs = compose(&self.obs, &selection[0])
+            .map_err(|e| anyhow!("AnnDataView obs {}", e))?;
+        let var = compose(&self.var, &selection[1])
+            .map_err(|e| anyhow!("AnnDataView var {}", e))?;
+        Ok(Self {
+            adata: self.adata.clone(),
+            obs: Arc::new(obs),
+            var: Arc::new(var),
+        })
+    }
+
+    /// Returns the underlying object.
+    pub fn inner(&self) -> &A {
+        &self.adata
+    }
+
+    /// Returns the selection of observations and variables in the underlying object.
+    pub fn selection(&self) -> [SelectInfoElem; 2] {
+        [(*self.obs).clone(), (*self.var).clone()]
+    }
+
+    fn axis_arrays<T>(
+        &self,
+        arrays: T,
+        select: SmallVec<[Arc<SelectInfoElem>; 2]>,
+    ) -> ViewAxisArrays<T> {
+        ViewAxisArrays { arrays, select }
+    }
+}
+
+/// Resolves a selection against an axis of length `bound`. The result is either
+/// a list of indices or a slice with a step of one and an explicit end, so that
+/// its length and elements are known without the bound.
+fn resolve(select: &SelectInfoElem, bound: usize) -> Result<SelectInfoElem> {
+    select.bound_check(bound)?;
+    let select = match select {
+        SelectInfoElem::Slice(slice) if slice.step == 1 => {
+            let bounds = SliceBounds::new(slice, bound);
+            let start = bounds.start.min(bound);
+            SelectInfoElem::Slice(Slice {
+                start: start as isize,
+                end: Some(bounds.end.clamp(start, bound) as isize),
+                step: 1,
+            })
+        }
+        _ => SelectInfoElem::Index(SelectInfoElemBounds::new(select, bound).to_vec()),
+    };
+    Ok(select)
+}
+
+/// Returns the number of elements in a resolved selection.
+fn selection_len(select: &SelectInfoElem) -> usize {
+    match select {
+        SelectInfoElem::Index(idx) => idx.len(),
+        SelectInfoElem::Slice(slice) => (slice.end.unwrap() - slice.start) as usize,
+    }
+}
+
+/// Selects `inner` among the elements selected by `outer`, which must be resolved.
+fn compose(outer: &SelectInfoElem, inner: &SelectInfoElem) -> Result<SelectInfoElem> {
+    let n = selection_len(outer);
+    let inner = resolve(inner, n)?;
+    let select = match (outer, &inner) {
+        (SelectInfoElem::Slice(a), SelectInfoElem::Slice(b)) => SelectInfoElem::Slice(Slice {
+            start: a.start + b.start,
+            end: b.end.map(|end| a.start + end),
+            step: 1,
+        }),
+        _ => {
+            let outer = SelectInfoElemBounds::new(outer, n);
+            SelectInfoElemBounds::new(&inner, n)
+                .iter()
+                .map(|i| outer.index(i))
+                .collect()
+        }
+    };
+    Ok(select)
+}
+
+/// Maps a selection relative to a view of `elem` to a selection of `elem`, and
+/// reads the data.
+fn select_view<E, D>(
+    elem: &E,
+    view: &[Arc<SelectInfoElem>],
+    selection: &[SelectInfoElem],
+) -> Result<Option<D>>
+where
+    E: ArrayElemOp,
+    D: TryFrom<ArrayData>,
+    <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+{
+    let Some(shape) = elem.shape() else {
+        return Ok(None);
+    };
+    let selection = (0..shape.ndim().max(selection.len()))
+        .map(|i| match (view.get(i), selection.get(i)) {
+            (Some(outer), Some(inner)) => compose(outer, inner),
+            (Some(outer), None) => Ok((**outer).clone()),
+            (None, Some(inner)) => Ok(inner.clone()),
+            (None, None) => Ok(SelectInfoElem::full()),
+        })
+        .collect::<Result<SmallVec<[_; 3]>>>()?;
+    elem.slice(selection)
+}
+
+/// An array element seen through an [`AnnDataView`].
+pub struct ViewArrayElem<E> {
+    elem: Arc<E>,
+    /// Selections of the leading axes. The remaining axes are not subset.
+    select: SmallVec<[Arc<SelectInfoElem>; 2]>,
+}
+
+impl<E> ViewArrayElem<E> {
+    fn new(elem: E, select: SmallVec<[Arc<SelectInfoElem>; 2]>) -> Self {
+        Self {
+            elem: Arc::new(elem),
+            select,
+        }
+    }
+}
+
+impl<E: ArrayElemOp> ArrayElemOp for ViewArrayElem<E> {
+    type ArrayIter<D> = ViewChunkedArrayElem<E, D>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: std::fmt::Debug;
+
+    fn is_none(&self) -> bool {
+        self.elem.is_none()
+    }
+
+    fn dtype(&self) -> Option<DataType> {
+        self.elem.dtype()
+    }
+
+    fn shape(&self) -> Option<Shape> {
+        self.elem.shape().map(|mut shape| {
+            self.select
+                .iter()
+                .take(shape.ndim())
+                .enumerate()
+                .for_each(|(i, s)| shape[i] = selection_len(s));
+            shape
+        })
+    }
+
+    fn get<D>(&self) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        select_view(&*self.elem, &self.select, &[])
+    }
+
+    fn slice<D, S>(&self, slice: S) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        S: AsRef<[SelectInfoElem]>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        select_view(&*self.elem, &self.select, slice.as_ref())
+    }
+
+    fn iter<D>(&self, chunk_size: usize) -> Self::ArrayIter<D>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
+    {
+        ViewChunkedArrayElem {
+            elem: self.elem.clone(),
+            select: self.select.clone(),
+            chunk_size,
+            num_items: self.shape().map_or(0, |shape| shape[0]),
+            current_position: 0,
+            phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+/// Iterator over chunks of rows of a [`ViewArrayElem`].
+pub struct ViewChunkedArrayElem<E, D> {
+    elem: Arc<E>,
+    select: SmallVec<[Arc<SelectInfoElem>; 2]>,
+    chunk_size: usize,
+    num_items: usize,
+    current_position: usize,
+    phantom: std::marker::PhantomData<D>,
+}
+
+impl<E, D> ViewChunkedArrayElem<E, D>
+where
+    E: ArrayElemOp,
+    D: TryFrom<ArrayData>,
+    <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
+{
+    /// Read rows `i..j` of the view, or the whole view if it is empty.
+    fn read(&self, i: usize, j: usize) -> Result<D> {
+        let selection = if i == j {
+            Vec::new()
+        } else {
+            vec![SelectInfoElem::from(i..j)]
+        };
+        let data: ArrayData = select_view(&*self.elem, &self.select, &selection)
+            .and_then(|x| x.context("the element is empty"))
+            .with_context(|| format!("failed to read rows {}..{} of the view", i, j))?;
+        data.try_into()
+            .map_err(|e| anyhow!("failed to convert rows {}..{} of the view: {:?}", i, j, e))
+    }
+}
+
+impl<E, D> Iterator for ViewChunkedArrayElem<E, D>
+where
+    E: ArrayElemOp,
+    D: TryFrom<ArrayData>,
+    <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
+{
+    type Item = Result<(D, usize, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_position >= self.num_items {
+            if self.current_position == 0 {
+                // return an empty array
+                self.current_position = 1;
+                Some(self.read(0, 0).map(|data| (data, 0, 0)))
+            } else {
+                None
+            }
+        } else {
+            let i = self.current_position;
+            let j = std::cmp::min(self.num_items, self.current_position + self.chunk_size);
+            self.current_position = j;
+            Some(self.read(i, j).map(|data| (data, i, j)))
+        }
+    }
+}
+
+impl<E, D> ExactSizeIterator for ViewChunkedArrayElem<E, D>
+where
+    E: ArrayElemOp,
+    D: TryFrom<ArrayData>,
+    <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
+{
+    fn len(&self) -> usize {
+        if self.num_items == 0 {
+            usize::from(self.current_position == 0)
+        } else {
+            (self.num_items - self.current_position).div_ceil(self.chunk_size)
+        }
+    }
+}
+
+/// Axis arrays, e.g., `obsm` or `layers`, seen through an [`AnnDataView`].
+pub struct ViewAxisArrays<T> {
+    arrays: T,
+    select: SmallVec<[Arc<SelectInfoElem>; 2]>,
+}
+
+impl<T: AxisArraysOp> AxisArraysOp for ViewAxisArrays<T> {
+    type ArrayElem = ViewArrayElem<T::ArrayElem>;
+
+    fn keys(&self) -> Vec<String> {
+        self.arrays.keys()
+    }
+
+    fn get(&self, key: &str) -> Option<Self::ArrayElem> {
+        self.arrays
+            .get(key)
+            .map(|elem| ViewArrayElem::new(elem, self.select.clone()))
+    }
+
+    fn add<D: Into<ArrayData>>(&self, _key: &str, _data: D) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+
+    fn add_iter<I, D>(&self, _key: &str, _data: I) -> Result<()>
+    where
+        I: Iterator<Item = D>,
+        D: ArrayChunk + Into<ArrayData>,
+    {
+        bail!("AnnDataView is read-only")
+    }
+
+    fn remove(&self, _key: &str) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+}
+
+impl<A: AnnDataOp> AnnDataOp for AnnDataView<A> {
+    type X = ViewArrayElem<A::X>;
+    type AxisArraysRef<'a> = ViewAxisArrays<A::AxisArraysRef<'a>> where Self: 'a;
+    type ElemCollectionRef<'a> = A::ElemCollectionRef<'a> where Self: 'a;
+
+    fn x(&self) -> Self::X {
+        ViewArrayElem::new(self.adata.x(), smallvec![self.obs.clone(), self.var.clone()])
+    }
+
+    fn set_x_from_iter<I, D>(&self, _iter: I) -> Result<()>
+    where
+        I: Iterator<Item = D>,
+        D: ArrayChunk + Into<ArrayData>,
+    {
+        bail!("AnnDataView is read-only")
+    }
+
+    fn set_x<D: Into<ArrayData>>(&self, _data: D) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+
+    fn del_x(&self) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+
+    fn n_obs(&self) -> usize {
+        selection_len(&self.obs)
+    }
+    fn n_vars(&self) -> usize {
+        selection_len(&self.var)
+    }
+
+    fn set_n_obs(&self, _n: usize) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn set_n_vars(&self, _n: usize) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+
+    fn obs_names(&self) -> DataFrameIndex {
+        let names = self.adata.obs_names();
+        if names.is_empty() {
+            names
+        } else {
+            names.select(&self.obs)
+        }
+    }
+    fn var_names(&self) -> DataFrameIndex {
+        let names = self.adata.var_names();
+        if names.is_empty() {
+            names
+        } else {
+            names.select(&self.var)
+        }
+    }
+
+    fn set_obs_names(&self, _index: DataFrameIndex) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn set_var_names(&self, _index: DataFrameIndex) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+
+    fn obs_ix<'a, I: IntoIterator<Item = &'a str>>(&self, names: I) -> Result<Vec<usize>> {
+        let index = self.obs_names();
+        names
+            .into_iter()
+            .map(|i| {
+                index
+                    .get_index(i)
+                    .context(format!("'{}' does not exist in obs_names", i))
+            })
+            .collect()
+    }
+    fn var_ix<'a, I: IntoIterator<Item = &'a str>>(&self, names: I) -> Result<Vec<usize>> {
+        let index = self.var_names();
+        names
+            .into_iter()
+            .map(|i| {
+                index
+                    .get_index(i)
+                    .context(format!("'{}' does not exist in var_names", i))
+            })
+            .collect()
+    }
+
+    fn read_obs(&self) -> Result<DataFrame> {
+        let obs = self.adata.read_obs()?;
+        if obs.width() == 0 {
+            Ok(obs)
+        } else {
+            Ok(Selectable::select_axis(&obs, 0, &*self.obs))
+        }
+    }
+    fn read_var(&self) -> Result<DataFrame> {
+        let var = self.adata.read_var()?;
+        if var.width() == 0 {
+            Ok(var)
+        } else {
+            Ok(Selectable::select_axis(&var, 0, &*self.var))
+        }
+    }
+
+    fn set_obs(&self, _obs: DataFrame) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn set_var(&self, _var: DataFrame) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn del_obs(&self) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn del_var(&self) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+
+    fn uns(&self) -> Self::ElemCollectionRef<'_> {
+        self.adata.uns()
+    }
+    fn obsm(&self) -> Self::AxisArraysRef<'_> {
+        self.axis_arrays(self.adata.obsm(), smallvec![self.obs.clone()])
+    }
+    fn obsp(&self) -> Self::AxisArraysRef<'_> {
+        self.axis_arrays(self.adata.obsp(), smallvec![self.obs.clone(), self.obs.clone()])
+    }
+    fn varm(&self) -> Self::AxisArraysRef<'_> {
+        self.axis_arrays(self.adata.varm(), smallvec![self.var.clone()])
+    }
+    fn varp(&self) -> Self::AxisArraysRef<'_> {
+        self.axis_arrays(self.adata.varp(), smallvec![self.var.clone(), self.var.clone()])
+    }
+    fn layers(&self) -> Self::AxisArraysRef<'_> {
+        self.axis_arrays(self.adata.layers(), smallvec![self.obs.clone(), self.var.clone()])
+    }
+
+    fn del_uns(&self) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn del_obsm(&self) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn del_obsp(&self) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn del_varm(&self) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn del_varp(&self) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn del_layers(&self) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+
+    fn get_raw(&self) -> Result<Option<RawData>> {
+        Ok(self.adata.get_raw()?.map(|mut raw| {
+            raw.x = raw.x.select_axis(0, &*self.obs);
+            raw
+        }))
+    }
+    fn set_raw(&self, _raw: RawData) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn del_raw(&self) -> Result<()> {
+        bail!("AnnDataView is read-only")
+    }
+    fn get_raw_var(&self) -> Result<Option<(DataFrameIndex, DataFrame)>> {
+        self.adata.get_raw_var()
+    }
+    fn read_raw_x(&self) -> Result<Option<ArrayData>> {
+        Ok(self.adata.read_raw_x()?.map(|x| x.select_axis(0, &*self.obs)))
+    }
+    fn read_raw_x_slice<S: AsRef<SelectInfoElem>>(&self, slice: S) -> Result<Option<ArrayData>> {
+        self.adata.read_raw_x_slice(compose(&self.obs, slice.as_ref())?)
+    }
+    fn set_raw_from_iter<I, D>(&self, _iter: I, _var_names: DataFrameIndex, _var: DataFrame) -> Result<()>
+    where
+        I: Iterator<Item = D>,
+        D: ArrayChunk + Into<ArrayData>,
+    {
+        bail!("AnnDataView is read-only")
+    }
+}
@@ -1,10 +1,13 @@
 mod datatype;
+mod mem;
 use crate::data::{ArrayConvert, DynArray, SelectInfo, SelectInfoElem, Shape};
-pub use datatype::{BackendData, DataType, ScalarType};
+pub use datatype::{BackendData, Complex32, Complex64, DataType, ScalarType, f16};
+pub use mem::{Mem, MemDataset, MemGroup, MemStore};
 
 use anyhow::{bail, Result};
 use core::fmt::{Debug, Formatter};
 use ndarray::{arr0, Array, CowArray, Dimension, Ix0, IxDyn};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 pub use serde_json::Value;
 use serde::Deserialize;
@@ -13,19 +16,164 @@ use serde::Deserialize;
 pub enum Compression {
     Gzip(u8),
     Zst(u8),
+    /// Blosc meta-compressor with the given internal compressor and level.
+    Blosc(BloscCompressor, u8),
+}
+
+/// Compressors available within Blosc.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BloscCompressor {
+    BloscLz,
+    Lz4,
+    Lz4hc,
+    Zlib,
+    Zstd,
+}
+
+/// Shuffle filter applied before Blosc compression. Shuffling groups the
+/// bytes (or bits) of the elements by significance, which usually improves
+/// the compression ratio of numeric data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Shuffle {
+    None,
+    #[default]
+    Byte,
+    Bit,
+}
+
+/// Storage layout of arrays.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// Arrays are split into chunks of `block_size` that are compressed
+    /// independently.
+    #[default]
+    Chunked,
+    /// Arrays are stored uncompressed in a single block, which is the fastest
+    /// to read as a whole. Compression and chunking settings are ignored, and
+    /// HDF5 datasets cannot be resized.
+    Contiguous,
+}
+
+/// Integer type of the `indptr` and `indices` arrays of sparse matrices.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum IndexWidth {
+    /// Use i32 when both the number of non-zeros and the minor dimension
+    /// fit in i32, and i64 otherwise. This is what scipy does.
+    #[default]
+    Auto,
+    /// Always use i32. Writing fails if the matrix is too large.
+    I32,
+    /// Always use i64.
+    I64,
+}
+
+/// Encoding type used for sparse matrices.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SparseEncoding {
+    /// `csr_matrix`/`csc_matrix`, readable by all versions of anndata.
+    #[default]
+    Matrix,
+    /// `csr_array`/`csc_array`.
+    Array,
 }
 
 #[derive(Debug, Clone)]
 pub struct WriteConfig {
     pub compression: Option<Compression>,
+    pub shuffle: Shuffle,
     pub block_size: Option<Shape>,
+    /// Shape of the shards of Zarr v3 arrays. A shard stores a grid of chunks
+    /// of `block_size` in a single object, which keeps the number of files
+    /// manageable for large arrays. It is rounded up to a multiple of the
+    /// chunk shape. Ignored by the HDF5 backend.
+    pub shard_size: Option<Shape>,
+    pub layout: Layout,
+    pub index_width: IndexWidth,
+    pub sparse_encoding: SparseEncoding,
 }
 
 impl Default for WriteConfig {
     fn default() -> Self {
         Self {
             compression: Some(Compression::Zst(3)),
+            shuffle: Shuffle::default(),
+            block_size: None,
+            shard_size: None,
+            layout: Layout::default(),
+            index_width: IndexWidth::default(),
+            sparse_encoding: SparseEncoding::default(),
+        }
+    }
+}
+
+impl WriteConfig {
+    /// Config of the 1-dimensional arrays backing sparse matrices. The block
+    /// size is left to the backend, and shards keep the same number of
+    /// elements as those of the matrix would.
+    pub(crate) fn flattened(&self) -> Self {
+        Self {
             block_size: None,
+            shard_size: self
+                .shard_size
+                .as_ref()
+                .map(|s| s.as_ref().iter().product::<usize>().into()),
+            ..self.clone()
+        }
+    }
+}
+
+/// Write configurations of individual elements, such as `X`, `obs` or
+/// `obsm/X_pca`. The configuration of an element is the one registered for the
+/// element itself or, failing that, for its closest parent, e.g., the one of
+/// `obsm` for `obsm/X_pca`. Other elements use the default configuration.
+///
+/// ```
+/// use anndata::backend::{BloscCompressor, Compression, WriteConfig, WritePolicy};
+///
+/// let policy = WritePolicy::default()
+///     .with("X", WriteConfig {
+///         compression: Some(Compression::Zst(9)),
+///         block_size: Some(vec![4096, 1000].into()),
+///         ..Default::default()
+///     })
+///     .with("obs", WriteConfig {
+///         compression: Some(Compression::Blosc(BloscCompressor::Lz4, 5)),
+///         ..Default::default()
+///     });
+/// assert_eq!(policy.get("obs/cell_type").block_size, None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WritePolicy {
+    default: WriteConfig,
+    elems: HashMap<String, WriteConfig>,
+}
+
+impl WritePolicy {
+    /// Create a policy that applies `config` to all elements.
+    pub fn new(config: WriteConfig) -> Self {
+        Self {
+            default: config,
+            elems: HashMap::new(),
+        }
+    }
+
+    /// Use `config` for the element at `path` and its children.
+    pub fn with(mut self, path: &str, config: WriteConfig) -> Self {
+        self.elems.insert(path.trim_matches('/').to_string(), config);
+        self
+    }
+
+    /// Returns the configuration of the element at `path`.
+    pub fn get(&self, path: &str) -> &WriteConfig {
+        let mut path = path.trim_matches('/');
+        loop {
+            if let Some(config) = self.elems.get(path) {
+                return config;
+            }
+            match path.rsplit_once('/') {
+                Some((parent, _)) => path = parent,
+                None => return &self.default,
+            }
         }
     }
 }
@@ -59,6 +207,25 @@ pub trait StoreOp<B: Backend + ?Sized> {
 
     /// Close the file.
     fn close(self) -> Result<()>;
+
+    /// Write buffered changes to the storage.
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns true if the store is being written in SWMR mode, see
+    /// [`StoreOp::start_swmr_write`].
+    fn is_swmr_write(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Switch the store to single-writer/multiple-reader (SWMR) mode, in which
+    /// other processes can read the store while it is being written. Existing
+    /// datasets can still be written and resized, but groups, datasets and
+    /// attributes can no longer be created or deleted.
+    fn start_swmr_write(&self) -> Result<()> {
+        bail!("the {} backend does not support SWMR writing", B::NAME)
+    }
 }
 
 pub trait GroupOp<B: Backend + ?Sized> {
@@ -83,6 +250,10 @@ pub trait GroupOp<B: Backend + ?Sized> {
     /// Delete a group or dataset.
     fn delete(&self, name: &str) -> Result<()>;
 
+    /// Move a group or dataset to a new name in this group. The destination
+    /// must not exist. Handles opened under the old name may become invalid.
+    fn rename(&self, from: &str, to: &str) -> Result<()>;
+
     /// Check if a group or dataset exists.
     fn exists(&self, name: &str) -> Result<bool>;
 
@@ -104,14 +275,17 @@ pub trait GroupOp<B: Backend + ?Sized> {
                 shape.iter().map(|&x| x.min(100)).collect()
             }
         });
-        let compression = if arr.len() > 100 {
-            config.compression
-        } else {
+        // Scalars and empty arrays cannot be chunked, and there is nothing
+        // to compress anyway.
+        let compression = if arr.ndim() == 0 || arr.is_empty() {
             None
+        } else {
+            config.compression
         };
         let new_config = WriteConfig {
-            compression: compression,
+            compression,
             block_size: Some(block_size),
+            ..config
         };
         let dataset = self.new_empty_dataset::<D>(name, &shape.into(), new_config)?;
         dataset.write_array(arr)?;
@@ -135,6 +309,12 @@ pub trait AttributeOp<B: Backend + ?Sized> {
 
     fn get_json_attr(&self, name: &str) -> Result<Value>;
 
+    /// Check if an attribute exists at the location.
+    fn has_attr(&self, name: &str) -> Result<bool>;
+
+    /// Delete an attribute, if it exists.
+    fn delete_attr(&mut self, name: &str) -> Result<()>;
+
     fn get_attr<'de, T>(&self, name: &str) -> Result<T>
     where
         T: Deserialize<'de>,
@@ -181,8 +361,11 @@ pub trait DatasetOp<B: Backend + ?Sized> {
             ScalarType::U16 => self.read_array_slice::<u16, _, IxDyn>(selection)?.into(),
             ScalarType::U32 => self.read_array_slice::<u32, _, IxDyn>(selection)?.into(),
             ScalarType::U64 => self.read_array_slice::<u64, _, IxDyn>(selection)?.into(),
+            ScalarType::F16 => self.read_array_slice::<f16, _, IxDyn>(selection)?.into(),
             ScalarType::F32 => self.read_array_slice::<f32, _, IxDyn>(selection)?.into(),
             ScalarType::F64 => self.read_array_slice::<f64, _, IxDyn>(selection)?.into(),
+            ScalarType::Complex32 => self.read_array_slice::<Complex32, _, IxDyn>(selection)?.into(),
+            ScalarType::Complex64 => self.read_array_slice::<Complex64, _, IxDyn>(selection)?.into(),
             ScalarType::Bool => self.read_array_slice::<bool, _, IxDyn>(selection)?.into(),
             ScalarType::String => self.read_array_slice::<String, _, IxDyn>(selection)?.into(),
         };
@@ -229,6 +412,31 @@ pub trait DatasetOp<B: Backend + ?Sized> {
         let ndim = arr.ndim();
         self.write_array_slice(arr, SelectInfo::full_slice(ndim).as_ref())
     }
+
+    /// Returns false if the dataset has a fixed shape, e.g., an HDF5 dataset
+    /// with a contiguous layout, in which case [`DatasetOp::reshape`] fails.
+    fn is_resizable(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    /// Reload the metadata of the dataset, so that changes made by another
+    /// process, e.g., a larger shape, become visible.
+    fn refresh(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns the names of the fields if the dataset is a compound (record)
+    /// array. Such arrays are only found in files written by old versions of
+    /// anndata.
+    fn record_fields(&self) -> Result<Option<Vec<String>>> {
+        Ok(None)
+    }
+
+    /// Read a field of a compound (record) array. Fields holding fixed-size
+    /// arrays are returned as 2-dimensional arrays.
+    fn read_record_field(&self, name: &str) -> Result<DynArray> {
+        bail!("cannot read field '{}': the dataset is not a record array", name)
+    }
 }
 
 pub enum DataContainer<B: Backend> {
@@ -284,6 +492,22 @@ impl<B: Backend> AttributeOp<B> for DataContainer<B> {
             DataContainer::Null => bail!("Null container"),
         }
     }
+
+    fn has_attr(&self, name: &str) -> Result<bool> {
+        match self {
+            DataContainer::Group(g) => g.has_attr(name),
+            DataContainer::Dataset(d) => d.has_attr(name),
+            DataContainer::Null => bail!("Null container"),
+        }
+    }
+
+    fn delete_attr(&mut self, name: &str) -> Result<()> {
+        match self {
+            DataContainer::Group(g) => g.delete_attr(name),
+            DataContainer::Dataset(d) => d.delete_attr(name),
+            DataContainer::Null => bail!("Null container"),
+        }
+    }
 }
 
 impl<B: Backend> DataContainer<B> {
@@ -327,17 +551,19 @@ impl<B: Backend> DataContainer<B> {
             "categorical" => DataType::Categorical,
             "string-array" => DataType::Array(ScalarType::String),
             "array" => DataType::Array(self.as_dataset()?.dtype()?),
-            "csc_matrix" => {
+            "csc_matrix" | "csc_array" => {
                 let ty = self.as_group()?.open_dataset("data")?.dtype()?;
                 DataType::CscMatrix(ty)
             }
-            "csr_matrix" => {
+            "csr_matrix" | "csr_array" => {
                 let ty = self.as_group()?.open_dataset("data")?.dtype()?;
                 DataType::CsrMatrix(ty)
             }
             "dataframe" => DataType::DataFrame,
             "mapping" | "dict" => DataType::Mapping,
-            "nullable-integer" | "nullable-boolean" => DataType::NullableArray,
+            "awkward-array" => DataType::AwkwardArray,
+            "nullable-integer" | "nullable-float" | "nullable-boolean"
+            | "nullable-string-array" | "nullable-string" => DataType::NullableArray,
             ty => bail!("Unsupported type '{}'", ty),
         };
         Ok(ty)
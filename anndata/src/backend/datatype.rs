@@ -5,6 +5,9 @@ use core::fmt::{Display, Formatter, Debug};
 use ndarray::{ArrayD, CowArray, IxDyn};
 use serde::{Serialize, Deserialize};
 
+pub use half::f16;
+pub use num::complex::{Complex32, Complex64};
+
 /// All data types that can be stored in an AnnData object.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum DataType {
@@ -16,6 +19,7 @@ pub enum DataType {
     Scalar(ScalarType),
     Categorical,
     NullableArray,
+    AwkwardArray,
 }
 
 impl DataType {
@@ -41,6 +45,7 @@ impl Display for DataType {
             DataType::Scalar(t) => write!(f, "Scalar({})", t),
             DataType::Mapping => write!(f, "Mapping"),
             DataType::NullableArray => write!(f, "Nullable array"),
+            DataType::AwkwardArray => write!(f, "Awkward array"),
         }
     }
 }
@@ -56,8 +61,11 @@ pub enum ScalarType {
     U16,
     U32,
     U64,
+    F16,
     F32,
     F64,
+    Complex32,
+    Complex64,
     Bool,
     String,
 }
@@ -73,8 +81,11 @@ impl Display for ScalarType {
             ScalarType::U16 => write!(f, "u16"),
             ScalarType::U32 => write!(f, "u32"),
             ScalarType::U64 => write!(f, "u64"),
+            ScalarType::F16 => write!(f, "f16"),
             ScalarType::F32 => write!(f, "f32"),
             ScalarType::F64 => write!(f, "f64"),
+            ScalarType::Complex32 => write!(f, "complex32"),
+            ScalarType::Complex64 => write!(f, "complex64"),
             ScalarType::Bool => write!(f, "bool"),
             ScalarType::String => write!(f, "string"),
         }
@@ -322,6 +333,34 @@ impl BackendData for u64 {
     }
 }
 
+impl BackendData for f16 {
+    const DTYPE: ScalarType = ScalarType::F16;
+
+    fn into_dyn(&self) -> DynScalar {
+        DynScalar::F16(*self)
+    }
+
+    fn into_dyn_arr<'a>(arr: CowArray<'a, Self, IxDyn>) -> DynCowArray<'a> {
+        DynCowArray::F16(arr)
+    }
+
+    fn from_dyn(x: DynScalar) -> Result<Self> {
+        if let DynScalar::F16(x) = x {
+            Ok(x)
+        } else {
+            bail!("Expecting f16")
+        }
+    }
+
+    fn from_dyn_arr(x: DynArray) -> Result<ArrayD<Self>> {
+        if let DynArray::F16(x) = x {
+            Ok(x)
+        } else {
+            bail!("Expecting f16 array")
+        }
+    }
+}
+
 impl BackendData for f32 {
     const DTYPE: ScalarType = ScalarType::F32;
 
@@ -378,6 +417,62 @@ impl BackendData for f64 {
     }
 }
 
+impl BackendData for Complex32 {
+    const DTYPE: ScalarType = ScalarType::Complex32;
+
+    fn into_dyn(&self) -> DynScalar {
+        DynScalar::Complex32(*self)
+    }
+
+    fn into_dyn_arr<'a>(arr: CowArray<'a, Self, IxDyn>) -> DynCowArray<'a> {
+        DynCowArray::Complex32(arr)
+    }
+
+    fn from_dyn(x: DynScalar) -> Result<Self> {
+        if let DynScalar::Complex32(x) = x {
+            Ok(x)
+        } else {
+            bail!("Expecting complex32")
+        }
+    }
+
+    fn from_dyn_arr(x: DynArray) -> Result<ArrayD<Self>> {
+        if let DynArray::Complex32(x) = x {
+            Ok(x)
+        } else {
+            bail!("Expecting complex32 array")
+        }
+    }
+}
+
+impl BackendData for Complex64 {
+    const DTYPE: ScalarType = ScalarType::Complex64;
+
+    fn into_dyn(&self) -> DynScalar {
+        DynScalar::Complex64(*self)
+    }
+
+    fn into_dyn_arr<'a>(arr: CowArray<'a, Self, IxDyn>) -> DynCowArray<'a> {
+        DynCowArray::Complex64(arr)
+    }
+
+    fn from_dyn(x: DynScalar) -> Result<Self> {
+        if let DynScalar::Complex64(x) = x {
+            Ok(x)
+        } else {
+            bail!("Expecting complex64")
+        }
+    }
+
+    fn from_dyn_arr(x: DynArray) -> Result<ArrayD<Self>> {
+        if let DynArray::Complex64(x) = x {
+            Ok(x)
+        } else {
+            bail!("Expecting complex64 array")
+        }
+    }
+}
+
 impl BackendData for String {
     const DTYPE: ScalarType = ScalarType::String;
 
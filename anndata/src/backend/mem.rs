@@ -0,0 +1,549 @@
+//! A backend keeping all groups and datasets in memory.
+
+use crate::backend::{
+    AttributeOp, Backend, BackendData, Complex32, Complex64, DatasetOp, GroupOp, ScalarType,
+    StoreOp, Value, WriteConfig, f16,
+};
+use crate::data::{DynArray, DynCowArray, DynScalar, Element, SelectInfoBounds, SelectInfoElem, Selectable, Shape};
+use crate::macros::dyn_map;
+
+use anyhow::{bail, ensure, Context, Result};
+use ndarray::{Array, ArrayD, CowArray, Dimension, IxDyn, SliceInfoElem};
+use parking_lot::RwLock;
+use serde_json::Map;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The in-memory backend. Nothing is written to disk, and the data is freed
+/// when the last handle to the store is dropped. Use [`crate::AnnData::write`]
+/// to persist an in-memory object with another backend.
+pub struct Mem;
+
+#[derive(Clone)]
+enum Node {
+    Group(Arc<RwLock<GroupNode>>),
+    Dataset(Arc<RwLock<DatasetNode>>),
+}
+
+#[derive(Default)]
+struct GroupNode {
+    children: BTreeMap<String, Node>,
+    attrs: Map<String, Value>,
+}
+
+struct DatasetNode {
+    data: DynArray,
+    attrs: Map<String, Value>,
+}
+
+#[derive(Clone)]
+pub struct MemStore {
+    filename: Arc<PathBuf>,
+    root: Arc<RwLock<GroupNode>>,
+}
+
+#[derive(Clone)]
+pub struct MemGroup {
+    store: MemStore,
+    path: PathBuf,
+    node: Arc<RwLock<GroupNode>>,
+}
+
+#[derive(Clone)]
+pub struct MemDataset {
+    store: MemStore,
+    path: PathBuf,
+    node: Arc<RwLock<DatasetNode>>,
+}
+
+impl Backend for Mem {
+    const NAME: &'static str = "memory";
+
+    type Store = MemStore;
+
+    type Group = MemGroup;
+
+    type Dataset = MemDataset;
+
+    /// Create a new, empty store. The path is only used as the name of the
+    /// store and is never touched.
+    fn new<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        Ok(MemStore {
+            filename: Arc::new(path.as_ref().to_path_buf()),
+            root: Arc::new(RwLock::new(GroupNode::default())),
+        })
+    }
+
+    fn open<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        bail!(
+            "cannot open '{}': in-memory stores only live as long as their handles",
+            path.as_ref().display()
+        )
+    }
+
+    fn open_rw<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        Self::open(path)
+    }
+}
+
+impl MemStore {
+    fn root_group(&self) -> MemGroup {
+        MemGroup {
+            store: self.clone(),
+            path: PathBuf::from("/"),
+            node: self.root.clone(),
+        }
+    }
+}
+
+impl StoreOp<Mem> for MemStore {
+    fn filename(&self) -> PathBuf {
+        self.filename.as_ref().clone()
+    }
+
+    fn close(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl GroupOp<Mem> for MemStore {
+    fn list(&self) -> Result<Vec<String>> {
+        self.root_group().list()
+    }
+
+    fn new_group(&self, name: &str) -> Result<MemGroup> {
+        self.root_group().new_group(name)
+    }
+
+    fn open_group(&self, name: &str) -> Result<MemGroup> {
+        self.root_group().open_group(name)
+    }
+
+    fn new_empty_dataset<T: BackendData>(
+        &self,
+        name: &str,
+        shape: &Shape,
+        config: WriteConfig,
+    ) -> Result<MemDataset> {
+        self.root_group().new_empty_dataset::<T>(name, shape, config)
+    }
+
+    fn open_dataset(&self, name: &str) -> Result<MemDataset> {
+        self.root_group().open_dataset(name)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        self.root_group().delete(name)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        self.root_group().rename(from, to)
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        self.root_group().exists(name)
+    }
+}
+
+impl MemGroup {
+    /// Find the group containing `name`, which may be a path. Absolute paths
+    /// are resolved from the root of the store. Returns the parent group and
+    /// the last component of the path, or `None` if `name` refers to the
+    /// starting group itself.
+    fn resolve(&self, name: &str) -> Result<Option<(MemGroup, String)>> {
+        let mut group = if name.starts_with('/') {
+            self.store.root_group()
+        } else {
+            self.clone()
+        };
+        let mut components: Vec<_> = name.split('/').filter(|x| !x.is_empty()).collect();
+        let Some(last) = components.pop() else {
+            return Ok(None);
+        };
+        for component in components {
+            group = group.open_group(component)?;
+        }
+        Ok(Some((group, last.to_string())))
+    }
+
+    fn get_node(&self, name: &str) -> Result<Option<Node>> {
+        match self.resolve(name)? {
+            None => Ok(Some(Node::Group(self.node.clone()))),
+            Some((parent, name)) => Ok(parent.node.read().children.get(&name).cloned()),
+        }
+    }
+
+    fn insert(&self, name: &str, node: Node) -> Result<PathBuf> {
+        let (parent, name) = self
+            .resolve(name)?
+            .with_context(|| format!("cannot create '{}': invalid name", name))?;
+        let mut parent_node = parent.node.write();
+        ensure!(
+            !parent_node.children.contains_key(&name),
+            "'{}' already exists in group '{}'",
+            name,
+            parent.path.display()
+        );
+        parent_node.children.insert(name.clone(), node);
+        Ok(parent.path.join(name))
+    }
+}
+
+impl GroupOp<Mem> for MemGroup {
+    fn list(&self) -> Result<Vec<String>> {
+        Ok(self.node.read().children.keys().cloned().collect())
+    }
+
+    fn new_group(&self, name: &str) -> Result<MemGroup> {
+        let node = Arc::new(RwLock::new(GroupNode::default()));
+        let path = self.insert(name, Node::Group(node.clone()))?;
+        Ok(MemGroup {
+            store: self.store.clone(),
+            path,
+            node,
+        })
+    }
+
+    fn open_group(&self, name: &str) -> Result<MemGroup> {
+        let Some((parent, name)) = self.resolve(name)? else {
+            return Ok(self.clone());
+        };
+        let guard = parent.node.read();
+        match guard.children.get(&name) {
+            Some(Node::Group(node)) => Ok(MemGroup {
+                store: self.store.clone(),
+                path: parent.path.join(&name),
+                node: node.clone(),
+            }),
+            Some(Node::Dataset(_)) => bail!("'{}' is a dataset, not a group", name),
+            None => bail!("no group named '{}' in '{}'", name, parent.path.display()),
+        }
+    }
+
+    fn new_empty_dataset<T: BackendData>(
+        &self,
+        name: &str,
+        shape: &Shape,
+        _config: WriteConfig,
+    ) -> Result<MemDataset> {
+        let data = ArrayD::from_elem(shape.as_ref(), fill_value::<T>());
+        macro_rules! fun {
+            ($variant:ident, $data:expr) => {
+                DynArray::$variant($data.into_owned())
+            };
+        }
+        let node = Arc::new(RwLock::new(DatasetNode {
+            data: dyn_map!(T::into_dyn_arr(data.into()), DynCowArray, fun),
+            attrs: Map::new(),
+        }));
+        let path = self.insert(name, Node::Dataset(node.clone()))?;
+        Ok(MemDataset {
+            store: self.store.clone(),
+            path,
+            node,
+        })
+    }
+
+    fn open_dataset(&self, name: &str) -> Result<MemDataset> {
+        let (parent, name) = self
+            .resolve(name)?
+            .context("cannot open a group as a dataset")?;
+        let guard = parent.node.read();
+        match guard.children.get(&name) {
+            Some(Node::Dataset(node)) => Ok(MemDataset {
+                store: self.store.clone(),
+                path: parent.path.join(&name),
+                node: node.clone(),
+            }),
+            Some(Node::Group(_)) => bail!("'{}' is a group, not a dataset", name),
+            None => bail!("no dataset named '{}' in '{}'", name, parent.path.display()),
+        }
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let (parent, name) = self.resolve(name)?.context("cannot delete the root group")?;
+        parent
+            .node
+            .write()
+            .children
+            .remove(&name)
+            .with_context(|| format!("no group or dataset named '{}'", name))?;
+        Ok(())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let (parent, name) = self.resolve(from)?.context("cannot rename the root group")?;
+        let node = parent
+            .node
+            .read()
+            .children
+            .get(&name)
+            .cloned()
+            .with_context(|| format!("no group or dataset named '{}'", from))?;
+        self.insert(to, node)?;
+        self.delete(from)
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        // Missing intermediate groups mean the element does not exist.
+        Ok(self.get_node(name).ok().flatten().is_some())
+    }
+}
+
+impl AttributeOp<Mem> for MemGroup {
+    fn store(&self) -> Result<MemStore> {
+        Ok(self.store.clone())
+    }
+
+    fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    fn new_json_attr(&mut self, name: &str, value: &Value) -> Result<()> {
+        self.node.write().attrs.insert(name.to_string(), value.clone());
+        Ok(())
+    }
+
+    fn get_json_attr(&self, name: &str) -> Result<Value> {
+        self.node
+            .read()
+            .attrs
+            .get(name)
+            .cloned()
+            .with_context(|| format!("Attribute {} not found", name))
+    }
+
+    fn has_attr(&self, name: &str) -> Result<bool> {
+        Ok(self.node.read().attrs.contains_key(name))
+    }
+
+    fn delete_attr(&mut self, name: &str) -> Result<()> {
+        self.node.write().attrs.remove(name);
+        Ok(())
+    }
+}
+
+impl AttributeOp<Mem> for MemDataset {
+    fn store(&self) -> Result<MemStore> {
+        Ok(self.store.clone())
+    }
+
+    fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    fn new_json_attr(&mut self, name: &str, value: &Value) -> Result<()> {
+        self.node.write().attrs.insert(name.to_string(), value.clone());
+        Ok(())
+    }
+
+    fn get_json_attr(&self, name: &str) -> Result<Value> {
+        self.node
+            .read()
+            .attrs
+            .get(name)
+            .cloned()
+            .with_context(|| format!("Attribute {} not found", name))
+    }
+
+    fn has_attr(&self, name: &str) -> Result<bool> {
+        Ok(self.node.read().attrs.contains_key(name))
+    }
+
+    fn delete_attr(&mut self, name: &str) -> Result<()> {
+        self.node.write().attrs.remove(name);
+        Ok(())
+    }
+}
+
+impl DatasetOp<Mem> for MemDataset {
+    fn dtype(&self) -> Result<ScalarType> {
+        Ok(self.node.read().data.data_type().scalar_type().unwrap())
+    }
+
+    fn shape(&self) -> Shape {
+        self.node.read().data.shape()
+    }
+
+    /// Resize the dataset. Existing elements are kept, new elements are
+    /// filled with zeros (or empty strings).
+    fn reshape(&mut self, shape: &Shape) -> Result<()> {
+        let mut node = self.node.write();
+        ensure!(
+            shape.ndim() == node.data.ndim(),
+            "cannot reshape a {}-dimensional dataset to {} dimensions",
+            node.data.ndim(),
+            shape.ndim()
+        );
+        macro_rules! fun {
+            ($variant:ident, $data:expr) => {
+                *$data = resize(&*$data, shape)
+            };
+        }
+        dyn_map!(&mut node.data, DynArray, fun);
+        Ok(())
+    }
+
+    fn read_array_slice<T: BackendData, S, D>(&self, selection: &[S]) -> Result<Array<T, D>>
+    where
+        S: AsRef<SelectInfoElem>,
+        D: Dimension,
+    {
+        let node = self.node.read();
+        check_bounds(&node.data.shape(), selection)?;
+        macro_rules! fun {
+            ($variant:ident, $data:expr) => {
+                T::from_dyn_arr(DynArray::$variant(Selectable::select($data, selection)))?
+            };
+        }
+        let arr = dyn_map!(&node.data, DynArray, fun);
+        Ok(arr.into_dimensionality::<D>()?)
+    }
+
+    fn write_array_slice<S, T, D>(&self, arr: CowArray<'_, T, D>, selection: &[S]) -> Result<()>
+    where
+        T: BackendData,
+        S: AsRef<SelectInfoElem>,
+        D: Dimension,
+    {
+        let mut node = self.node.write();
+        let shape = node.data.shape();
+        check_bounds(&shape, selection)?;
+        let dtype = node.data.data_type().scalar_type().unwrap();
+        let arr = T::into_dyn_arr(arr.into_dyn());
+        macro_rules! fun {
+            ($variant:ident, $data:expr) => {
+                match arr {
+                    DynCowArray::$variant(arr) => assign($data, arr, selection),
+                    _ => bail!("cannot write {} values to a dataset of type {}", T::DTYPE, dtype),
+                }
+            };
+        }
+        dyn_map!(&mut node.data, DynArray, fun)
+    }
+}
+
+fn check_bounds<S: AsRef<SelectInfoElem>>(shape: &Shape, selection: &[S]) -> Result<()> {
+    ensure!(
+        selection.len() == shape.ndim(),
+        "selection has {} dimensions, but the dataset has {}",
+        selection.len(),
+        shape.ndim()
+    );
+    selection
+        .iter()
+        .zip(shape.as_ref())
+        .try_for_each(|(sel, dim)| sel.as_ref().bound_check(*dim))
+}
+
+/// Write `arr` to the selected elements of `data`.
+fn assign<T: Clone, S: AsRef<SelectInfoElem>>(
+    data: &mut ArrayD<T>,
+    arr: CowArray<'_, T, IxDyn>,
+    selection: &[S],
+) -> Result<()> {
+    let shape: Shape = data.shape().into();
+    let bounds = SelectInfoBounds::new(&selection, &shape);
+    ensure!(
+        bounds.out_shape().as_ref() == arr.shape(),
+        "the selection has shape {:?}, but the array has shape {:?}",
+        bounds.out_shape(),
+        arr.shape()
+    );
+    let slices = selection
+        .iter()
+        .map(|x| match x.as_ref() {
+            SelectInfoElem::Slice(slice) => Some(SliceInfoElem::from(slice.clone())),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    if let Some(slices) = slices {
+        data.slice_mut(slices.as_slice()).assign(&arr);
+    } else {
+        arr.indexed_iter().for_each(|(idx, value)| {
+            let idx: Vec<_> = bounds
+                .as_ref()
+                .iter()
+                .enumerate()
+                .map(|(i, sel)| sel.index(idx[i]))
+                .collect();
+            data[idx.as_slice()] = value.clone();
+        });
+    }
+    Ok(())
+}
+
+fn resize<T: BackendData>(data: &ArrayD<T>, shape: &Shape) -> ArrayD<T> {
+    let mut new = ArrayD::from_elem(shape.as_ref(), fill_value::<T>());
+    let overlap: Vec<SliceInfoElem> = data
+        .shape()
+        .iter()
+        .zip(shape.as_ref())
+        .map(|(a, b)| (0..(*a).min(*b)).into())
+        .collect();
+    new.slice_mut(overlap.as_slice())
+        .assign(&data.slice(overlap.as_slice()));
+    new
+}
+
+fn fill_value<T: BackendData>() -> T {
+    let value = match T::DTYPE {
+        ScalarType::I8 => DynScalar::I8(0),
+        ScalarType::I16 => DynScalar::I16(0),
+        ScalarType::I32 => DynScalar::I32(0),
+        ScalarType::I64 => DynScalar::I64(0),
+        ScalarType::U8 => DynScalar::U8(0),
+        ScalarType::U16 => DynScalar::U16(0),
+        ScalarType::U32 => DynScalar::U32(0),
+        ScalarType::U64 => DynScalar::U64(0),
+        ScalarType::F16 => DynScalar::F16(f16::ZERO),
+        ScalarType::F32 => DynScalar::F32(0.0),
+        ScalarType::F64 => DynScalar::F64(0.0),
+        ScalarType::Complex32 => DynScalar::Complex32(Complex32::new(0.0, 0.0)),
+        ScalarType::Complex64 => DynScalar::Complex64(Complex64::new(0.0, 0.0)),
+        ScalarType::Bool => DynScalar::Bool(false),
+        ScalarType::String => DynScalar::String(String::new()),
+    };
+    T::from_dyn(value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s;
+    use ndarray::{array, Array2, Ix2};
+
+    #[test]
+    fn test_mem_store() -> Result<()> {
+        let store = Mem::new("scratch")?;
+        let group = store.new_group("group")?;
+        let subgroup = group.new_group("sub")?;
+        let mut dataset = subgroup.new_empty_dataset::<i32>("data", &[2, 3].as_slice().into(), WriteConfig::default())?;
+        assert_eq!(dataset.path(), PathBuf::from("/group/sub/data"));
+        assert!(store.exists("group/sub/data")?);
+        assert!(!store.exists("missing/data")?);
+        assert!(group.new_group("sub").is_err());
+
+        let arr: Array2<i32> = array![[1, 2], [3, 4]];
+        dataset.write_array_slice(arr.view().into(), s![.., 1..3].as_ref())?;
+        assert_eq!(dataset.read_array::<i32, _>()?, array![[0, 1, 2], [0, 3, 4]]);
+        assert_eq!(
+            dataset.read_array_slice::<i32, _, _>(s![vec![1, 0], 2..3].as_ref())?,
+            array![[4], [2]]
+        );
+        assert!(dataset.write_array_slice(arr.view().into(), s![1..3, ..2].as_ref()).is_err());
+        assert!(dataset.read_array::<f64, Ix2>().is_err());
+
+        dataset.reshape(&[3, 2].as_slice().into())?;
+        assert_eq!(dataset.read_array::<i32, _>()?, array![[0, 1], [0, 3], [0, 0]]);
+
+        dataset.new_attr("encoding-type", "array")?;
+        assert_eq!(dataset.get_attr::<String>("encoding-type")?, "array");
+
+        store.delete("/group/sub")?;
+        assert!(!group.exists("sub")?);
+        assert_eq!(store.list()?, vec!["group"]);
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
-use crate::backend::ScalarType;
+use crate::backend::{Complex32, Complex64, ScalarType, f16};
 use crate::data::utils::{array_major_minor_index_default, cs_major_minor_index2};
-use crate::data::{DataFrameIndex, DynCsrMatrix};
+use crate::data::{DataFrameIndex, DynCsrMatrix, SelectInfoElem};
 use crate::{AnnDataOp, ArrayElemOp};
-use anyhow::{ensure, Result};
+use anyhow::{ensure, Context, Result};
 use indexmap::IndexSet;
 use itertools::Itertools;
 use nalgebra_sparse::csr::CsrMatrix;
@@ -13,6 +13,9 @@ use polars::series::{IntoSeries, Series};
 
 use crate::data::{ArrayData, DynArray};
 
+/// Number of rows of the raw data matrices read at a time.
+const RAW_CHUNK_SIZE: usize = 1000;
+
 #[derive(Debug, Clone, Copy)]
 pub enum JoinType {
     Inner,
@@ -31,40 +34,14 @@ where
     O: AnnDataOp,
     S: ToString,
 {
-    // Concatenate var_names
-    let common_vars = adatas
+    // Concatenate var_names and vars
+    let vars = adatas
         .iter()
-        .map(|x| x.var_names().into_iter().collect::<IndexSet<_>>());
-    let common_vars: IndexSet<String> = match join {
-        JoinType::Inner => common_vars.reduce(|a, b| a.intersection(&b).cloned().collect()),
-        JoinType::Outer => common_vars.reduce(|a, b| a.union(&b).cloned().collect()),
-    }
-    .unwrap();
+        .map(|adata| Ok((adata.var_names(), adata.read_var()?)))
+        .collect::<Result<Vec<_>>>()?;
+    let (common_vars, df_var) = join_vars(&vars, join)?;
     out.set_var_names(common_vars.iter().cloned().collect())?;
-
-    // Concatenate vars
-    {
-        let df_var = adatas
-            .iter()
-            .map(|adata| {
-                let var = adata.read_var().unwrap();
-                let var_names = adata.var_names();
-                // Creating the series
-                let columns = var
-                    .get_columns()
-                    .iter()
-                    .map(|s| align_series(s, &var_names, &common_vars))
-                    .collect::<Result<Vec<_>>>()?;
-                Ok(DataFrame::new(columns)?)
-            })
-            .reduce(|a, b| {
-                let mut a = a?;
-                merge_df(&mut a, &b?)?;
-                anyhow::Ok(a)
-            })
-            .unwrap()?;
-        out.set_var(df_var)?;
-    }
+    out.set_var(df_var)?;
 
     // Concatenate obs
     {
@@ -136,9 +113,91 @@ where
         }
     }
 
+    // Concatenate raw. The result has a raw slot only if all inputs have one.
+    // The data matrices are read `RAW_CHUNK_SIZE` rows at a time.
+    {
+        let vars = adatas
+            .iter()
+            .map(|adata| adata.get_raw_var())
+            .collect::<Result<Option<Vec<_>>>>()?;
+        if let Some(vars) = vars.filter(|x| !x.is_empty()) {
+            let (common_vars, var) = join_vars(&vars, join)?;
+            let mut error = None;
+            let chunks = adatas
+                .iter()
+                .zip(vars.iter())
+                .flat_map(|(adata, (var_names, _))| {
+                    let n_obs = adata.n_obs();
+                    let col_indices = common_vars
+                        .iter()
+                        .map(|x| var_names.get_index(x))
+                        .collect::<Vec<_>>();
+                    (0..n_obs).step_by(RAW_CHUNK_SIZE).map(move |start| {
+                        let end = (start + RAW_CHUNK_SIZE).min(n_obs);
+                        let x = adata
+                            .read_raw_x_slice(SelectInfoElem::from(start..end))?
+                            .context("raw slot does not contain X")?;
+                        Ok(index_array(
+                            x,
+                            &(0..end - start).map(Some).collect::<Vec<_>>(),
+                            &col_indices,
+                        ))
+                    })
+                })
+                .map_while(|chunk: Result<ArrayData>| match chunk {
+                    Ok(chunk) => Some(chunk),
+                    Err(e) => {
+                        error = Some(e);
+                        None
+                    }
+                });
+            let result = out.set_raw_from_iter(chunks, common_vars.iter().cloned().collect(), var);
+            if let Some(e) = error {
+                let _ = out.del_raw();
+                return Err(e);
+            }
+            result?;
+        }
+    }
+
     Ok(())
 }
 
+/// Join the variables of multiple objects. Returns the new variable names and
+/// the variable annotations aligned to them.
+fn join_vars(
+    vars: &[(DataFrameIndex, DataFrame)],
+    join: JoinType,
+) -> Result<(IndexSet<String>, DataFrame)> {
+    let common_vars = vars
+        .iter()
+        .map(|(var_names, _)| var_names.clone().into_iter().collect::<IndexSet<_>>());
+    let common_vars: IndexSet<String> = match join {
+        JoinType::Inner => common_vars.reduce(|a, b| a.intersection(&b).cloned().collect()),
+        JoinType::Outer => common_vars.reduce(|a, b| a.union(&b).cloned().collect()),
+    }
+    .unwrap();
+
+    let df_var = vars
+        .iter()
+        .map(|(var_names, var)| {
+            // Creating the series
+            let columns = var
+                .get_columns()
+                .iter()
+                .map(|s| align_series(s, var_names, &common_vars))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(DataFrame::new(columns)?)
+        })
+        .reduce(|a, b| {
+            let mut a = a?;
+            merge_df(&mut a, &b?)?;
+            anyhow::Ok(a)
+        })
+        .unwrap()?;
+    Ok((common_vars, df_var))
+}
+
 fn merge_df(this: &mut DataFrame, other: &DataFrame) -> Result<()> {
     if other.is_empty() {
         return Ok(());
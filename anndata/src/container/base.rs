@@ -1,10 +1,10 @@
 use crate::{
-    backend::{AttributeOp, Backend, DataContainer, DataType, GroupOp},
+    backend::{AttributeOp, Backend, DataContainer, DatasetOp, DataType, GroupOp, WriteConfig},
     data::index::VecVecIndex,
     data::*,
 };
 
-use anyhow::{bail, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 use indexmap::set::IndexSet;
 use itertools::Itertools;
 use num::integer::div_rem;
@@ -172,6 +172,19 @@ impl<B: Backend> InnerDataFrameElem<B> {
         &self.column_names
     }
 
+    /// Returns the names and types of the columns. Only the metadata is read
+    /// if the dataframe is not in memory.
+    pub fn column_types(&self) -> Result<Vec<(String, polars::datatypes::DataType)>> {
+        match self.element {
+            Some(ref df) => Ok(df
+                .get_columns()
+                .iter()
+                .map(|c| (c.name().to_string(), c.dtype().clone()))
+                .collect()),
+            None => dataframe::read_column_types(&self.container),
+        }
+    }
+
     /// Set a column with a Series.
     //TODO: this is not efficient. We should be able to replace a column without reading the whole dataframe.
     pub fn set_column<S: IntoSeries>(&mut self, name: &str, new_col: S) -> Result<()> {
@@ -202,11 +215,20 @@ impl<B: Backend> InnerDataFrameElem<B> {
     }
 
     pub fn export<O: Backend, G: GroupOp<O>>(&self, location: &G, name: &str) -> Result<()> {
+        self.export_with_config::<O, _>(location, name, &WriteConfig::default())
+    }
+
+    pub fn export_with_config<O: Backend, G: GroupOp<O>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<()> {
         let df = match self.element {
             Some(ref df) => df.clone(),
             None => DataFrame::read(&self.container)?,
         };
-        let mut container = df.write(location, name)?;
+        let mut container = df.write_with_config(location, name, config)?;
         self.index.overwrite(&mut container)
     }
 
@@ -278,6 +300,33 @@ impl<B: Backend> InnerDataFrameElem<B> {
         Ok(())
     }
 
+    /// Append rows to the dataframe. Unlike arrays, the columns are rewritten.
+    pub(crate) fn append_rows(&mut self, index: &DataFrameIndex, data: &DataFrame) -> Result<()> {
+        ensure!(
+            data.height() == 0 || data.height() == index.len(),
+            "cannot append to dataframe as lengths of index and dataframe differ"
+        );
+        let mut new_index: DataFrameIndex =
+            self.index.clone().into_iter().chain(index.clone()).collect();
+        new_index.index_name = self.index.index_name.clone();
+        if self.width() == 0 && data.width() == 0 {
+            self.index = new_index;
+            return self.index.overwrite(&mut self.container);
+        }
+
+        ensure!(
+            data.width() == self.width(),
+            "cannot append a dataframe with {} columns to a dataframe with {} columns",
+            data.width(),
+            self.width(),
+        );
+        let mut df = self.data()?.clone();
+        df.vstack_mut(&data.select(self.column_names.iter().map(|x| x.as_str()))?)?;
+        self.index = new_index;
+        self.index.overwrite(&mut self.container)?;
+        self.save(df)
+    }
+
     /// inplace subsetting the DataFrameElem.
     pub fn subset<S>(&mut self, selection: &[S]) -> Result<()>
     where
@@ -399,9 +448,18 @@ impl<B: Backend> InnerElem<B> {
 
 impl<B: Backend> InnerElem<B> {
     pub fn export<O: Backend, G: GroupOp<O>>(&self, location: &G, name: &str) -> Result<()> {
+        self.export_with_config::<O, _>(location, name, &WriteConfig::default())
+    }
+
+    pub fn export_with_config<O: Backend, G: GroupOp<O>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<()> {
         match self.element.as_ref() {
-            Some(data) => data.write(location, name)?,
-            None => Data::read(&self.container)?.write(location, name)?,
+            Some(data) => data.write_with_config(location, name, config)?,
+            None => Data::read(&self.container)?.write_with_config(location, name, config)?,
         };
         Ok(())
     }
@@ -500,9 +558,18 @@ impl<B: Backend> InnerArrayElem<B> {
     }
 
     pub fn export<O: Backend, G: GroupOp<O>>(&self, location: &G, name: &str) -> Result<()> {
+        self.export_with_config::<O, _>(location, name, &WriteConfig::default())
+    }
+
+    pub fn export_with_config<O: Backend, G: GroupOp<O>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<()> {
         match self.element.as_ref() {
-            Some(data) => data.write(location, name)?,
-            None => ArrayData::read(&self.container)?.write(location, name)?,
+            Some(data) => data.write_with_config(location, name, config)?,
+            None => ArrayData::read(&self.container)?.write_with_config(location, name, config)?,
         };
         Ok(())
     }
@@ -514,6 +581,13 @@ impl<B: Backend> InnerArrayElem<B> {
         if selection.as_ref().iter().all(|x| x.as_ref().is_full()) {
             self.data()
         } else {
+            let ndim = self.shape().ndim();
+            ensure!(
+                selection.iter().skip(ndim).all(|x| x.as_ref().is_full()),
+                "cannot select along axis {} or higher of a {}-dimensional array",
+                ndim,
+                ndim
+            );
             match self.element.as_ref() {
                 Some(data) => Ok(data.select(selection).try_into()?),
                 None => ArrayData::read_select(&self.container, selection),
@@ -596,6 +670,63 @@ impl<B: Backend> InnerArrayElem<B> {
             .set_axis(axis, self.shape().ndim(), &full);
         self.subset(slice.as_slice())
     }
+
+    /// Append data along the first (rows) or second (columns) axis. Dense arrays
+    /// and sparse matrices are grown in place.
+    pub(crate) fn append_axis(&mut self, axis: usize, data: &ArrayData) -> Result<()> {
+        let shape = data.shape();
+        ensure!(
+            shape.ndim() == self.shape.ndim()
+                && (0..shape.ndim()).all(|i| i == axis || shape[i] == self.shape[i]),
+            "cannot append data of shape {} to an element of shape {} along axis {}",
+            shape,
+            self.shape,
+            axis,
+        );
+        match axis {
+            0 => data.append_rows(&mut self.container)?,
+            1 => data.append_cols(&mut self.container)?,
+            _ => bail!("cannot append data along axis {}", axis),
+        }
+        self.shape[axis] += shape[axis];
+        self.element = None;
+        Ok(())
+    }
+
+    /// Returns false if the datasets holding the element cannot be resized, in
+    /// which case rows or columns cannot be appended in place.
+    pub(crate) fn is_resizable(&self) -> Result<bool> {
+        // Data frames and awkward arrays are rewritten instead.
+        if !matches!(
+            self.dtype,
+            DataType::Array(_) | DataType::CsrMatrix(_) | DataType::CscMatrix(_)
+        ) {
+            return Ok(true);
+        }
+        match &self.container {
+            DataContainer::Dataset(dataset) => dataset.is_resizable(),
+            DataContainer::Group(group) => {
+                for name in ["data", "indices", "indptr"] {
+                    if group.exists(name)? && !group.open_dataset(name)?.is_resizable()? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            DataContainer::Null => Ok(true),
+        }
+    }
+
+    /// Reload the shape from the store, which may have been changed by another
+    /// process.
+    pub(crate) fn refresh(&mut self) -> Result<()> {
+        if let DataContainer::Dataset(dataset) = &self.container {
+            dataset.refresh()?;
+        }
+        self.shape = ArrayData::get_shape(&self.container)?;
+        self.element = None;
+        Ok(())
+    }
 }
 
 pub type ArrayElem<B> = Slot<InnerArrayElem<B>>;
@@ -1074,20 +1205,38 @@ impl<B: Backend, D> ChunkedArrayElem<B, D> {
     }
 }
 
+impl<B, D> ChunkedArrayElem<B, D>
+where
+    B: Backend,
+    D: TryFrom<ArrayData>,
+    <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
+{
+    /// Read rows `i..j` of the array, or the whole array if it is empty.
+    fn read(&self, i: usize, j: usize) -> Result<D> {
+        let data = if i == j {
+            self.elem.inner().data()?
+        } else {
+            self.elem.inner().select_axis(0, SelectInfoElem::from(i..j))?
+        };
+        data.try_into()
+            .map_err(|e| anyhow!("failed to convert rows {}..{}: {:?}", i, j, e))
+    }
+}
+
 impl<B, D> Iterator for ChunkedArrayElem<B, D>
 where
     B: Backend,
     D: TryFrom<ArrayData>,
     <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
 {
-    type Item = (D, usize, usize);
+    type Item = Result<(D, usize, usize)>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.current_position >= self.num_items {
             if self.current_position == 0 {
                 // return an empty array
                 self.current_position = 1;
-                Some((self.elem.inner().data().unwrap().try_into().unwrap(), 0, 0))
+                Some(self.read(0, 0).map(|data| (data, 0, 0)))
             } else {
                 None
             }
@@ -1095,14 +1244,7 @@ where
             let i = self.current_position;
             let j = std::cmp::min(self.num_items, self.current_position + self.chunk_size);
             self.current_position = j;
-            let data = self
-                .elem
-                .inner()
-                .select_axis(0, SelectInfoElem::from(i..j))
-                .unwrap()
-                .try_into()
-                .unwrap();
-            Some((data, i, j))
+            Some(self.read(i, j).map(|data| (data, i, j)))
         }
     }
 }
@@ -1147,15 +1289,17 @@ where
     D: TryFrom<ArrayData>,
     <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
 {
-    type Item = (D, usize, usize);
+    type Item = Result<(D, usize, usize)>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(mat) = self.arrays.get_mut(self.current_array) {
-            if let Some((data, start, stop)) = mat.next() {
-                let new_start = self.current_position;
-                let new_stop = new_start + stop - start;
-                self.current_position = new_stop;
-                Some((data, new_start, new_stop))
+            if let Some(chunk) = mat.next() {
+                Some(chunk.map(|(data, start, stop)| {
+                    let new_start = self.current_position;
+                    let new_stop = new_start + stop - start;
+                    self.current_position = new_stop;
+                    (data, new_start, new_stop)
+                }))
             } else {
                 self.current_array += 1;
                 self.next()
@@ -1164,17 +1308,7 @@ where
             if self.current_position == 0 {
                 // return an empty array
                 self.current_position = 1;
-                Some((
-                    self.arrays[0]
-                        .elem
-                        .inner()
-                        .data()
-                        .unwrap()
-                        .try_into()
-                        .unwrap(),
-                    0,
-                    0,
-                ))
+                Some(self.arrays[0].read(0, 0).map(|data| (data, 0, 0)))
             } else {
                 None
             }
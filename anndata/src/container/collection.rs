@@ -1,5 +1,5 @@
 use crate::{
-    anndata::new_mapping, backend::{iter_containers, AttributeOp, Backend, GroupOp}, container::base::*, data::*, ElemCollectionOp
+    anndata::new_mapping, backend::{iter_containers, AttributeOp, Backend, GroupOp, WritePolicy}, container::base::*, data::*, ElemCollectionOp
 };
 
 use anyhow::{bail, ensure, Result};
@@ -68,9 +68,22 @@ impl<B: Backend> InnerElemCollection<B> {
     }
 
     pub fn export<O: Backend, G: GroupOp<O>>(&self, location: &G, name: &str) -> Result<()> {
+        self.export_with_policy::<O, _>(location, name, name, &WritePolicy::default())
+    }
+
+    /// Export the elements using the configurations given by `policy`. `path` is
+    /// the path of the collection in the output file.
+    pub fn export_with_policy<O: Backend, G: GroupOp<O>>(
+        &self,
+        location: &G,
+        name: &str,
+        path: &str,
+        policy: &WritePolicy,
+    ) -> Result<()> {
         let group = new_mapping(location, name)?;
         for (key, val) in self.iter() {
-            val.inner().export::<O, _>(&group, key)?;
+            let config = policy.get(&format!("{}/{}", path, key));
+            val.inner().export_with_config::<O, _>(&group, key, config)?;
         }
         Ok(())
     }
@@ -386,9 +399,22 @@ impl<B: Backend> InnerAxisArrays<B> {
     }
 
     pub fn export<O: Backend, G: GroupOp<O>>(&self, location: &G, name: &str) -> Result<()> {
+        self.export_with_policy::<O, _>(location, name, name, &WritePolicy::default())
+    }
+
+    /// Export the elements using the configurations given by `policy`. `path` is
+    /// the path of the collection in the output file.
+    pub fn export_with_policy<O: Backend, G: GroupOp<O>>(
+        &self,
+        location: &G,
+        name: &str,
+        path: &str,
+        policy: &WritePolicy,
+    ) -> Result<()> {
         let group = new_mapping(location, name)?;
         for (key, val) in self.iter() {
-            val.inner().export::<O, _>(&group, key)?;
+            let config = policy.get(&format!("{}/{}", path, key));
+            val.inner().export_with_config::<O, _>(&group, key, config)?;
         }
         Ok(())
     }
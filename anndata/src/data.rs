@@ -7,7 +7,7 @@ pub use array::*;
 pub use data_traits::*;
 pub use mapping::*;
 
-use crate::backend::{Backend, DataContainer, DataType, GroupOp};
+use crate::backend::{Backend, Complex32, Complex64, DataContainer, DataType, GroupOp, WriteConfig, f16};
 
 use anyhow::{bail, Ok, Result};
 use nalgebra_sparse::csc::CscMatrix;
@@ -68,8 +68,11 @@ impl_into_data!(u8, U8);
 impl_into_data!(u16, U16);
 impl_into_data!(u32, U32);
 impl_into_data!(u64, U64);
+impl_into_data!(f16, F16);
 impl_into_data!(f32, F32);
 impl_into_data!(f64, F64);
+impl_into_data!(Complex32, Complex32);
+impl_into_data!(Complex64, Complex64);
 impl_into_data!(bool, Bool);
 impl_into_data!(String, String);
 
@@ -114,8 +117,8 @@ macro_rules! impl_try_from_for_scalar {
 }
 
 impl_try_from_for_scalar!(
-    I8, i8, I16, i16, I32, i32, I64, i64, U8, u8, U16, u16, U32, u32, U64, u64, F32, f32, F64, f64,
-    Bool, bool, String, String
+    I8, i8, I16, i16, I32, i32, I64, i64, U8, u8, U16, u16, U32, u32, U64, u64, F16, f16, F32, f32,
+    F64, f64, Complex32, Complex32, Complex64, Complex64, Bool, bool, String, String
 );
 
 impl TryFrom<Data> for DataFrame {
@@ -150,6 +153,7 @@ impl Readable for Data {
             DataType::Categorical
             | DataType::Array(_)
             | DataType::DataFrame
+            | DataType::AwkwardArray
             | DataType::CscMatrix(_)
             | DataType::CsrMatrix(_) => ArrayData::read(container).map(|x| x.into()),
             DataType::Scalar(_) => DynScalar::read(container).map(|x| x.into()),
@@ -189,4 +193,16 @@ impl Writable for Data {
             Data::Mapping(data) => data.write(location, name),
         }
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        match self {
+            Data::ArrayData(data) => data.write_with_config(location, name, config),
+            _ => self.write(location, name),
+        }
+    }
 }
\ No newline at end of file
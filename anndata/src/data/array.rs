@@ -1,3 +1,4 @@
+mod awkward;
 mod chunks;
 pub mod dataframe;
 mod dense;
@@ -5,6 +6,7 @@ pub mod slice;
 mod sparse;
 pub mod utils;
 
+pub use awkward::AwkwardArray;
 pub use chunks::ArrayChunk;
 pub use dataframe::DataFrameIndex;
 pub use dense::{ArrayConvert, CategoricalArray, DynArray, DynCowArray, DynScalar};
@@ -28,6 +30,7 @@ pub enum ArrayData {
     CsrNonCanonical(DynCsrNonCanonical),
     CscMatrix(DynCscMatrix),
     DataFrame(DataFrame),
+    AwkwardArray(AwkwardArray),
 }
 
 impl<T: Clone + Into<ArrayData>> From<&T> for ArrayData {
@@ -42,6 +45,12 @@ impl From<DataFrame> for ArrayData {
     }
 }
 
+impl From<AwkwardArray> for ArrayData {
+    fn from(data: AwkwardArray) -> Self {
+        ArrayData::AwkwardArray(data)
+    }
+}
+
 impl From<DynArray> for ArrayData {
     fn from(data: DynArray) -> Self {
         ArrayData::Array(data)
@@ -118,6 +127,16 @@ impl TryFrom<ArrayData> for DataFrame {
     }
 }
 
+impl TryFrom<ArrayData> for AwkwardArray {
+    type Error = anyhow::Error;
+    fn try_from(value: ArrayData) -> Result<Self, Self::Error> {
+        match value {
+            ArrayData::AwkwardArray(data) => Ok(data),
+            _ => bail!("Cannot convert {:?} to AwkwardArray", value.data_type()),
+        }
+    }
+}
+
 impl<T, D> TryFrom<ArrayData> for Array<T, D>
 where Array<T, D>: TryFrom<DynArray, Error = anyhow::Error>
 {
@@ -190,7 +209,9 @@ macro_rules! impl_arraydata_traits {
     };
 }
 
-impl_arraydata_traits!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64, bool, String);
+impl_arraydata_traits!(
+    i8, i16, i32, i64, u8, u16, u32, u64, f16, f32, f64, Complex32, Complex64, bool, String
+);
 
 impl Readable for ArrayData {
     fn read<B: Backend>(container: &DataContainer<B>) -> Result<Self> {
@@ -201,6 +222,9 @@ impl Readable for ArrayData {
             DataType::CsrMatrix(_) => read_csr(container),
             DataType::CscMatrix(_) => DynCscMatrix::read(container).map(ArrayData::CscMatrix),
             DataType::DataFrame => DataFrame::read(container).map(ArrayData::DataFrame),
+            DataType::AwkwardArray => {
+                AwkwardArray::read(container).map(ArrayData::AwkwardArray)
+            }
             ty => bail!("Cannot read type '{:?}' as matrix data", ty),
         }
     }
@@ -214,6 +238,7 @@ impl Element for ArrayData {
             ArrayData::CsrNonCanonical(data) => data.data_type(),
             ArrayData::CscMatrix(data) => data.data_type(),
             ArrayData::DataFrame(data) => data.data_type(),
+            ArrayData::AwkwardArray(data) => data.data_type(),
         }
     }
 
@@ -224,6 +249,7 @@ impl Element for ArrayData {
             ArrayData::CsrNonCanonical(data) => data.metadata(),
             ArrayData::CscMatrix(data) => data.metadata(),
             ArrayData::DataFrame(data) => data.metadata(),
+            ArrayData::AwkwardArray(data) => data.metadata(),
         }
     }
 }
@@ -240,6 +266,23 @@ impl Writable for ArrayData {
             ArrayData::CsrNonCanonical(data) => data.write(location, name),
             ArrayData::CscMatrix(data) => data.write(location, name),
             ArrayData::DataFrame(data) => data.write(location, name),
+            ArrayData::AwkwardArray(data) => data.write(location, name),
+        }
+    }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        match self {
+            ArrayData::Array(data) => data.write_with_config(location, name, config),
+            ArrayData::CsrMatrix(data) => data.write_with_config(location, name, config),
+            ArrayData::CsrNonCanonical(data) => data.write_with_config(location, name, config),
+            ArrayData::CscMatrix(data) => data.write_with_config(location, name, config),
+            ArrayData::DataFrame(data) => data.write_with_config(location, name, config),
+            ArrayData::AwkwardArray(data) => data.write_with_config(location, name, config),
         }
     }
 }
@@ -252,6 +295,7 @@ impl HasShape for ArrayData {
             ArrayData::CsrNonCanonical(data) => data.shape(),
             ArrayData::CscMatrix(data) => data.shape(),
             ArrayData::DataFrame(data) => HasShape::shape(data),
+            ArrayData::AwkwardArray(data) => data.shape(),
         }
     }
 }
@@ -276,6 +320,7 @@ impl Selectable for ArrayData {
             ArrayData::CsrNonCanonical(data) => data.select(info).into(),
             ArrayData::CscMatrix(data) => data.select(info).into(),
             ArrayData::DataFrame(data) => Selectable::select(data, info).into(),
+            ArrayData::AwkwardArray(data) => data.select(info).into(),
         }
     }
 }
@@ -307,6 +352,9 @@ impl Stackable for ArrayData {
                 <DataFrame as Stackable>::vstack(iter.map(|x| x.try_into().unwrap()))
                     .map(|x| x.into())
             }
+            ArrayData::AwkwardArray(_) => {
+                AwkwardArray::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
+            }
         }
     }
 }
@@ -318,6 +366,7 @@ impl ReadableArray for ArrayData {
             DataType::CsrMatrix(_) => DynCsrMatrix::get_shape(container),
             DataType::CscMatrix(_) => DynCscMatrix::get_shape(container),
             DataType::DataFrame => DataFrame::get_shape(container),
+            DataType::AwkwardArray => AwkwardArray::get_shape(container),
             ty => bail!("Cannot read shape information from type '{}'", ty),
         }
     }
@@ -338,6 +387,9 @@ impl ReadableArray for ArrayData {
             DataType::DataFrame => {
                 DataFrame::read_select(container, info).map(ArrayData::DataFrame)
             }
+            DataType::AwkwardArray => {
+                AwkwardArray::read_select(container, info).map(ArrayData::AwkwardArray)
+            }
             ty => bail!("Cannot read type '{:?}' as matrix data", ty),
         }
     }
@@ -384,8 +436,11 @@ fn read_csr<B: Backend>(container: &DataContainer<B>) -> Result<ArrayData> {
             ScalarType::U16 => _read_csr::<B, u16>(container),
             ScalarType::U32 => _read_csr::<B, u32>(container),
             ScalarType::U64 => _read_csr::<B, u64>(container),
+            ScalarType::F16 => _read_csr::<B, f16>(container),
             ScalarType::F32 => _read_csr::<B, f32>(container),
             ScalarType::F64 => _read_csr::<B, f64>(container),
+            ScalarType::Complex32 => _read_csr::<B, Complex32>(container),
+            ScalarType::Complex64 => _read_csr::<B, Complex64>(container),
             ScalarType::Bool => _read_csr::<B, bool>(container),
             ScalarType::String => _read_csr::<B, String>(container),
         },
@@ -458,8 +513,11 @@ where
             ScalarType::U16 => _read_csr::<B, u16, _>(container, info),
             ScalarType::U32 => _read_csr::<B, u32, _>(container, info),
             ScalarType::U64 => _read_csr::<B, u64, _>(container, info),
+            ScalarType::F16 => _read_csr::<B, f16, _>(container, info),
             ScalarType::F32 => _read_csr::<B, f32, _>(container, info),
             ScalarType::F64 => _read_csr::<B, f64, _>(container, info),
+            ScalarType::Complex32 => _read_csr::<B, Complex32, _>(container, info),
+            ScalarType::Complex64 => _read_csr::<B, Complex64, _>(container, info),
             ScalarType::Bool => _read_csr::<B, bool, _>(container, info),
             ScalarType::String => _read_csr::<B, String, _>(container, info),
         },
@@ -0,0 +1,345 @@
+use crate::backend::*;
+use crate::data::{
+    array::DynArray,
+    data_traits::*,
+    slice::{SelectInfoElem, SelectInfoElemBounds, Shape},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use ndarray::{Array1, Ix1};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// A ragged array, i.e., a one-dimensional array whose elements are lists of
+/// variable length, e.g., the TCR chains of each cell.
+///
+/// It is stored using the `awkward-array` encoding of the anndata specification.
+/// Only the subset of awkward layouts describing a list of scalars (or a list
+/// of strings) is supported, i.e., a `ListOffsetArray` whose content is a
+/// `NumpyArray`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AwkwardArray {
+    offsets: Vec<usize>,
+    values: DynArray,
+}
+
+impl AwkwardArray {
+    /// Create a new ragged array. The `i`-th row consists of the elements
+    /// `values[offsets[i]..offsets[i+1]]`.
+    pub fn try_new(offsets: Vec<usize>, values: DynArray) -> Result<Self> {
+        ensure!(
+            values.ndim() == 1,
+            "the values of an awkward array must be 1-dimensional, but got {} dimensions",
+            values.ndim()
+        );
+        ensure!(
+            offsets.first() == Some(&0),
+            "the offsets of an awkward array must start with 0"
+        );
+        ensure!(
+            offsets.windows(2).all(|x| x[0] <= x[1]),
+            "the offsets of an awkward array must be non-decreasing"
+        );
+        ensure!(
+            offsets[offsets.len() - 1] == values.len(),
+            "the last offset ({}) differs from the number of values ({})",
+            offsets[offsets.len() - 1],
+            values.len()
+        );
+        Ok(Self { offsets, values })
+    }
+
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// Values of all rows, concatenated.
+    pub fn values(&self) -> &DynArray {
+        &self.values
+    }
+
+    /// Number of rows.
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the `i`-th row.
+    pub fn row(&self, i: usize) -> Option<DynArray> {
+        if i < self.len() {
+            let range = SelectInfoElem::from(self.offsets[i]..self.offsets[i + 1]);
+            Some(self.values.select(&[range]))
+        } else {
+            None
+        }
+    }
+
+    pub fn into_parts(self) -> (Vec<usize>, DynArray) {
+        (self.offsets, self.values)
+    }
+
+    fn form(&self) -> Value {
+        let content = match &self.values {
+            DynArray::String(_) => json!({
+                "class": "ListOffsetArray",
+                "offsets": "i64",
+                "content": {
+                    "class": "NumpyArray",
+                    "primitive": "uint8",
+                    "inner_shape": [],
+                    "parameters": {"__array__": "char"},
+                    "form_key": "node2",
+                },
+                "parameters": {"__array__": "string"},
+                "form_key": "node1",
+            }),
+            values => json!({
+                "class": "NumpyArray",
+                "primitive": primitive_name(values.data_type().scalar_type().unwrap()),
+                "inner_shape": [],
+                "parameters": {},
+                "form_key": "node1",
+            }),
+        };
+        json!({
+            "class": "ListOffsetArray",
+            "offsets": "i64",
+            "content": content,
+            "parameters": {},
+            "form_key": "node0",
+        })
+    }
+}
+
+impl<T> FromIterator<Vec<T>> for AwkwardArray
+where
+    Array1<T>: Into<DynArray>,
+{
+    fn from_iter<I: IntoIterator<Item = Vec<T>>>(iter: I) -> Self {
+        let mut offsets = vec![0];
+        let mut values = Vec::new();
+        iter.into_iter().for_each(|row| {
+            values.extend(row);
+            offsets.push(values.len());
+        });
+        Self {
+            offsets,
+            values: Array1::from(values).into(),
+        }
+    }
+}
+
+impl Element for AwkwardArray {
+    fn data_type(&self) -> DataType {
+        DataType::AwkwardArray
+    }
+
+    fn metadata(&self) -> MetaData {
+        let mut metadata = HashMap::new();
+        metadata.insert("form".to_string(), self.form().to_string().into());
+        metadata.insert("length".to_string(), self.len().into());
+        MetaData::new("awkward-array", "0.1.0", Some(metadata))
+    }
+}
+
+impl Writable for AwkwardArray {
+    fn write<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+    ) -> Result<DataContainer<B>> {
+        let mut group = location.new_group(name)?;
+        self.metadata().save(&mut group)?;
+
+        self.offsets
+            .iter()
+            .map(|x| *x as i64)
+            .collect::<Array1<_>>()
+            .write(&group, "node0-offsets")?;
+        match &self.values {
+            DynArray::String(values) => {
+                let mut offsets = vec![0i64];
+                let mut chars = Vec::new();
+                values.iter().for_each(|x| {
+                    chars.extend_from_slice(x.as_bytes());
+                    offsets.push(chars.len() as i64);
+                });
+                Array1::from(offsets).write(&group, "node1-offsets")?;
+                Array1::from(chars).write(&group, "node2-data")?;
+            }
+            values => {
+                values.write(&group, "node1-data")?;
+            }
+        }
+        Ok(DataContainer::Group(group))
+    }
+}
+
+impl Readable for AwkwardArray {
+    fn read<B: Backend>(container: &DataContainer<B>) -> Result<Self> {
+        Self::read_select(container, &[SelectInfoElem::full()])
+    }
+}
+
+impl HasShape for AwkwardArray {
+    fn shape(&self) -> Shape {
+        self.len().into()
+    }
+}
+
+impl Selectable for AwkwardArray {
+    /// Panics if `info` selects along any axis but the first. The fallible
+    /// paths, such as [`ReadableArray::read_select`], reject such selections
+    /// with an error instead.
+    fn select<S>(&self, info: &[S]) -> Self
+    where
+        S: AsRef<SelectInfoElem>,
+    {
+        assert!(
+            info.iter().skip(1).all(|x| x.as_ref().is_full()),
+            "awkward arrays can only be selected along the first axis"
+        );
+        let (offsets, values) = select_rows(&self.offsets, info[0].as_ref());
+        Self {
+            offsets,
+            values: self.values.select(&[values]),
+        }
+    }
+}
+
+impl Stackable for AwkwardArray {
+    fn vstack<I: Iterator<Item = Self>>(iter: I) -> Result<Self> {
+        let mut iter = iter.peekable();
+        ensure!(iter.peek().is_some(), "Cannot vstack empty iterator");
+        let mut offsets = vec![0];
+        let values = DynArray::vstack(iter.map(|x| {
+            let n = offsets[offsets.len() - 1];
+            offsets.extend(x.offsets[1..].iter().map(|o| o + n));
+            x.values
+        }))?;
+        Ok(Self { offsets, values })
+    }
+}
+
+impl ReadableArray for AwkwardArray {
+    fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
+        let length: usize = container.get_attr("length")?;
+        Ok(length.into())
+    }
+
+    fn read_select<B, S>(container: &DataContainer<B>, info: &[S]) -> Result<Self>
+    where
+        B: Backend,
+        S: AsRef<SelectInfoElem>,
+    {
+        ensure!(
+            info.iter().skip(1).all(|x| x.as_ref().is_full()),
+            "awkward arrays can only be selected along the first axis"
+        );
+        let group = container.as_group()?;
+        let form: Value = serde_json::from_str(&group.get_attr::<String>("form")?)
+            .context("failed to parse the form of the awkward array")?;
+        let length: usize = group.get_attr("length")?;
+        ensure!(
+            is_list(&form),
+            "unsupported awkward array form: {}",
+            form
+        );
+
+        let row_offsets: Vec<usize> = group
+            .open_dataset(&format!("{}-offsets", form_key(&form)?))?
+            .read_array_cast::<_, Ix1>()?
+            .to_vec();
+        ensure!(
+            row_offsets.len() == length + 1,
+            "the awkward array has {} offsets, but its length is {}",
+            row_offsets.len(),
+            length
+        );
+        let (offsets, selection) = select_rows(&row_offsets, info[0].as_ref());
+
+        let content = &form["content"];
+        let values = if content["class"] == "NumpyArray" {
+            group
+                .open_dataset(&format!("{}-data", form_key(content)?))?
+                .read_dyn_array_slice(&[selection])?
+        } else if is_list(content) && content["parameters"]["__array__"] == "string" {
+            let str_offsets: Vec<usize> = group
+                .open_dataset(&format!("{}-offsets", form_key(content)?))?
+                .read_array_cast::<_, Ix1>()?
+                .to_vec();
+            let chars: Vec<u8> = group
+                .open_dataset(&format!("{}-data", form_key(&content["content"])?))?
+                .read_array::<u8, Ix1>()?
+                .to_vec();
+            SelectInfoElemBounds::new(&selection, str_offsets.len() - 1)
+                .iter()
+                .map(|i| {
+                    let bytes = &chars[str_offsets[i]..str_offsets[i + 1]];
+                    Ok(String::from_utf8(bytes.to_vec())?)
+                })
+                .collect::<Result<Array1<String>>>()?
+                .into()
+        } else {
+            bail!("unsupported awkward array form: {}", form)
+        };
+        Self::try_new(offsets, values)
+    }
+}
+
+impl WritableArray for AwkwardArray {}
+impl WritableArray for &AwkwardArray {}
+
+fn is_list(form: &Value) -> bool {
+    form["class"]
+        .as_str()
+        .is_some_and(|x| x.starts_with("ListOffsetArray"))
+}
+
+fn form_key(form: &Value) -> Result<&str> {
+    form["form_key"]
+        .as_str()
+        .with_context(|| format!("missing form_key in awkward array form: {}", form))
+}
+
+fn primitive_name(ty: ScalarType) -> &'static str {
+    match ty {
+        ScalarType::I8 => "int8",
+        ScalarType::I16 => "int16",
+        ScalarType::I32 => "int32",
+        ScalarType::I64 => "int64",
+        ScalarType::U8 => "uint8",
+        ScalarType::U16 => "uint16",
+        ScalarType::U32 => "uint32",
+        ScalarType::U64 => "uint64",
+        ScalarType::F16 => "float16",
+        ScalarType::F32 => "float32",
+        ScalarType::F64 => "float64",
+        ScalarType::Complex32 => "complex64",
+        ScalarType::Complex64 => "complex128",
+        ScalarType::Bool => "bool",
+        ScalarType::String => unreachable!("strings are stored as lists of characters"),
+    }
+}
+
+/// Compute the offsets of the selected rows and the selection of the underlying
+/// values.
+fn select_rows(offsets: &[usize], rows: &SelectInfoElem) -> (Vec<usize>, SelectInfoElem) {
+    let n = offsets.len() - 1;
+    if rows.is_full() {
+        let lo = offsets[0];
+        let new_offsets = offsets.iter().map(|x| x - lo).collect();
+        (new_offsets, SelectInfoElem::from(lo..offsets[n]))
+    } else {
+        let mut new_offsets = vec![0];
+        let mut indices = Vec::new();
+        SelectInfoElemBounds::new(rows, n).iter().for_each(|i| {
+            indices.extend(offsets[i]..offsets[i + 1]);
+            new_offsets.push(indices.len());
+        });
+        (new_offsets, SelectInfoElem::from(indices))
+    }
+}
@@ -1,16 +1,18 @@
-use crate::backend::{Backend, DataContainer, GroupOp, AttributeOp, BackendData, ScalarType};
-use crate::Selectable;
+use crate::backend::{Backend, DataContainer, DataType, GroupOp, AttributeOp, BackendData, ScalarType, Complex32, Complex64, f16};
+use crate::{Readable, Selectable, Writable};
+use crate::data::{Element, Stackable};
 use crate::data::{
     array::DynArray,
     ArrayData,
-    array::utils::ExtendableDataset,
+    array::utils::{append_cs_major, append_cs_minor, append_to_dataset, ExtendableDataset},
 };
 
-use anyhow::{bail, Result, Context};
+use anyhow::{bail, ensure, Result, Context};
 use ndarray::{Array, ArrayView1, ArrayD, RemoveAxis};
 use nalgebra_sparse::na::Scalar;
 use nalgebra_sparse::{CsrMatrix, CscMatrix};
-use super::{DynCsrMatrix, DynCscMatrix, DynCsrNonCanonical, CsrNonCanonical};
+use polars::frame::DataFrame;
+use super::{AwkwardArray, DynCsrMatrix, DynCscMatrix, DynCsrNonCanonical, CsrNonCanonical};
 
 pub trait ArrayChunk: Selectable {
     fn write_by_chunk<B, G, I>(iter: I, location: &G, name: &str) -> Result<DataContainer<B>>
@@ -18,6 +20,19 @@ pub trait ArrayChunk: Selectable {
         I: Iterator<Item = Self>,
         B: Backend,
         G: GroupOp<B>;
+
+    /// Append the rows of `self` to the data stored in `container`, growing
+    /// the underlying datasets in place.
+    fn append_rows<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()>;
+
+    /// Append the columns of `self` to the data stored in `container`.
+    fn append_cols<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()>;
+}
+
+fn check_data_type<B: Backend>(container: &DataContainer<B>, ty: DataType) -> Result<()> {
+    let current = container.encoding_type()?;
+    ensure!(current == ty, "cannot append {} to {}", ty, current);
+    Ok(())
 }
 
 impl ArrayChunk for ArrayData {
@@ -34,6 +49,40 @@ impl ArrayChunk for ArrayData {
                 DynCsrNonCanonical::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             ArrayData::CscMatrix(_) => DynCscMatrix::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             ArrayData::DataFrame(_) => todo!(),
+            ArrayData::AwkwardArray(_) => {
+                AwkwardArray::vstack(iter.map(|x| x.try_into().unwrap()))?.write(location, name)
+            }
+        }
+    }
+
+    fn append_rows<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        match self {
+            ArrayData::Array(x) => x.append_rows(container),
+            ArrayData::CsrMatrix(x) => x.append_rows(container),
+            ArrayData::CsrNonCanonical(x) => x.append_rows(container),
+            ArrayData::CscMatrix(x) => x.append_rows(container),
+            // These are not stored as flat datasets and are rewritten instead.
+            ArrayData::DataFrame(x) => {
+                let mut df = DataFrame::read(container)?;
+                df.vstack_mut(x)?;
+                *container = df.overwrite(std::mem::take(container))?;
+                Ok(())
+            }
+            ArrayData::AwkwardArray(x) => {
+                let arr = AwkwardArray::vstack([AwkwardArray::read(container)?, x.clone()].into_iter())?;
+                *container = arr.overwrite(std::mem::take(container))?;
+                Ok(())
+            }
+        }
+    }
+
+    fn append_cols<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        match self {
+            ArrayData::Array(x) => x.append_cols(container),
+            ArrayData::CsrMatrix(x) => x.append_cols(container),
+            ArrayData::CsrNonCanonical(x) => x.append_cols(container),
+            ArrayData::CscMatrix(x) => x.append_cols(container),
+            x => bail!("cannot append columns to {}", x.data_type()),
         }
     }
 }
@@ -57,10 +106,21 @@ impl ArrayChunk for DynArray {
             DynArray::I64(_) => ArrayD::<i64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynArray::F32(_) => ArrayD::<f32>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynArray::F64(_) => ArrayD::<f64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynArray::F16(_) => ArrayD::<f16>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynArray::Complex32(_) => ArrayD::<Complex32>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynArray::Complex64(_) => ArrayD::<Complex64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynArray::Bool(_) => ArrayD::<bool>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynArray::String(_) => ArrayD::<String>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
         }
     }
+
+    fn append_rows<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        crate::macros::dyn_map_fun!(self, DynArray, append_rows, container)
+    }
+
+    fn append_cols<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        crate::macros::dyn_map_fun!(self, DynArray, append_cols, container)
+    }
 }
 
 impl<D: RemoveAxis, T: BackendData> ArrayChunk for Array<T, D> {
@@ -93,6 +153,23 @@ impl<D: RemoveAxis, T: BackendData> ArrayChunk for Array<T, D> {
         container.new_attr("encoding-version", "0.2.0")?;
         Ok(container)
     }
+
+    fn append_rows<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        check_data_type(container, DataType::Array(T::DTYPE))?;
+        match container {
+            DataContainer::Dataset(dataset) => append_to_dataset::<B, _, _>(dataset, 0, self.view()),
+            _ => bail!("Expecting Dataset"),
+        }
+    }
+
+    fn append_cols<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        ensure!(self.ndim() >= 2, "cannot append columns to a 1-dimensional array");
+        check_data_type(container, DataType::Array(T::DTYPE))?;
+        match container {
+            DataContainer::Dataset(dataset) => append_to_dataset::<B, _, _>(dataset, 1, self.view()),
+            _ => bail!("Expecting Dataset"),
+        }
+    }
 }
 
 impl ArrayChunk for DynCsrMatrix {
@@ -114,10 +191,21 @@ impl ArrayChunk for DynCsrMatrix {
             DynCsrMatrix::I64(_) => CsrMatrix::<i64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCsrMatrix::F32(_) => CsrMatrix::<f32>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCsrMatrix::F64(_) => CsrMatrix::<f64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynCsrMatrix::F16(_) => CsrMatrix::<f16>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynCsrMatrix::Complex32(_) => CsrMatrix::<Complex32>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynCsrMatrix::Complex64(_) => CsrMatrix::<Complex64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCsrMatrix::Bool(_) => CsrMatrix::<bool>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCsrMatrix::String(_) => CsrMatrix::<String>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
         }
     }
+
+    fn append_rows<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        crate::macros::dyn_map_fun!(self, DynCsrMatrix, append_rows, container)
+    }
+
+    fn append_cols<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        crate::macros::dyn_map_fun!(self, DynCsrMatrix, append_cols, container)
+    }
 }
 
 
@@ -170,6 +258,18 @@ impl<T: BackendData> ArrayChunk for CsrMatrix<T> {
         group.new_attr("shape", [num_rows as u64, num_cols.unwrap_or(0) as u64].as_slice())?;
         Ok(DataContainer::Group(group))
     }
+
+    fn append_rows<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        check_data_type(container, DataType::CsrMatrix(T::DTYPE))?;
+        let (offsets, indices, data) = self.csr_data();
+        append_cs_major(container, 0, self.ncols(), offsets, indices, data)
+    }
+
+    fn append_cols<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        check_data_type(container, DataType::CsrMatrix(T::DTYPE))?;
+        let (offsets, indices, data) = self.csr_data();
+        append_cs_minor(container, 0, self.ncols(), offsets, indices, data)
+    }
 }
 
 impl ArrayChunk for DynCsrNonCanonical {
@@ -191,10 +291,21 @@ impl ArrayChunk for DynCsrNonCanonical {
             DynCsrNonCanonical::I64(_) => CsrNonCanonical::<i64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCsrNonCanonical::F32(_) => CsrNonCanonical::<f32>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCsrNonCanonical::F64(_) => CsrNonCanonical::<f64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynCsrNonCanonical::F16(_) => CsrNonCanonical::<f16>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynCsrNonCanonical::Complex32(_) => CsrNonCanonical::<Complex32>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynCsrNonCanonical::Complex64(_) => CsrNonCanonical::<Complex64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCsrNonCanonical::Bool(_) => CsrNonCanonical::<bool>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCsrNonCanonical::String(_) => CsrNonCanonical::<String>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
         }
     }
+
+    fn append_rows<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        crate::macros::dyn_map_fun!(self, DynCsrNonCanonical, append_rows, container)
+    }
+
+    fn append_cols<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        crate::macros::dyn_map_fun!(self, DynCsrNonCanonical, append_cols, container)
+    }
 }
 
 impl<T: BackendData> ArrayChunk for CsrNonCanonical<T> {
@@ -246,6 +357,18 @@ impl<T: BackendData> ArrayChunk for CsrNonCanonical<T> {
         group.new_attr("shape", [num_rows as u64, num_cols.unwrap_or(0) as u64].as_slice())?;
         Ok(DataContainer::Group(group))
     }
+
+    fn append_rows<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        check_data_type(container, DataType::CsrMatrix(T::DTYPE))?;
+        let (offsets, indices, data) = self.csr_data();
+        append_cs_major(container, 0, self.ncols(), offsets, indices, data)
+    }
+
+    fn append_cols<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        check_data_type(container, DataType::CsrMatrix(T::DTYPE))?;
+        let (offsets, indices, data) = self.csr_data();
+        append_cs_minor(container, 0, self.ncols(), offsets, indices, data)
+    }
 }
 
 
@@ -268,10 +391,21 @@ impl ArrayChunk for DynCscMatrix {
             DynCscMatrix::I64(_) => CscMatrix::<i64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCscMatrix::F32(_) => CscMatrix::<f32>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCscMatrix::F64(_) => CscMatrix::<f64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynCscMatrix::F16(_) => CscMatrix::<f16>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynCscMatrix::Complex32(_) => CscMatrix::<Complex32>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
+            DynCscMatrix::Complex64(_) => CscMatrix::<Complex64>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCscMatrix::Bool(_) => CscMatrix::<bool>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
             DynCscMatrix::String(_) => CscMatrix::<String>::write_by_chunk(iter.map(|x| x.try_into().unwrap()), location, name),
         }
     }
+
+    fn append_rows<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        crate::macros::dyn_map_fun!(self, DynCscMatrix, append_rows, container)
+    }
+
+    fn append_cols<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        crate::macros::dyn_map_fun!(self, DynCscMatrix, append_cols, container)
+    }
 }
 
 
@@ -336,6 +470,18 @@ impl<T: BackendData+Scalar> ArrayChunk for CscMatrix<T> {
         Ok(DataContainer::Group(group))
         */
     }
+
+    fn append_rows<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        check_data_type(container, DataType::CscMatrix(T::DTYPE))?;
+        let (offsets, indices, data) = self.csc_data();
+        append_cs_minor(container, 1, self.nrows(), offsets, indices, data)
+    }
+
+    fn append_cols<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
+        check_data_type(container, DataType::CscMatrix(T::DTYPE))?;
+        let (offsets, indices, data) = self.csc_data();
+        append_cs_major(container, 1, self.nrows(), offsets, indices, data)
+    }
 }
 
 
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
-use crate::backend::{AttributeOp, Backend, DataContainer, DatasetOp, GroupOp, ScalarType};
+use crate::backend::{
+    AttributeOp, Backend, DataContainer, DatasetOp, GroupOp, ScalarType, WriteConfig, f16,
+};
 use crate::data::array::{
     slice::{SelectInfoElem, Shape},
     CategoricalArray, DynArray,
@@ -12,8 +14,8 @@ use anyhow::{bail, Context, Result};
 use log::warn;
 use ndarray::{Array1, Ix1};
 use polars::chunked_array::ChunkedArray;
-use polars::datatypes::DataType;
-use polars::prelude::{DataFrame, Series};
+use polars::datatypes::{DataType, TimeUnit, TimeZone};
+use polars::prelude::{DataFrame, Int32Chunked, Int64Chunked, IntoSeries, Series};
 
 use super::{BackendData, SelectInfoBounds, SelectInfoElemBounds};
 
@@ -42,6 +44,22 @@ impl Writable for DataFrame {
         location: &G,
         name: &str,
     ) -> Result<DataContainer<B>> {
+        self.write_with_config(location, name, &WriteConfig::default())
+    }
+
+    /// Columns are written with the given configuration, ignoring block and
+    /// shard sizes that are not 1-dimensional. The index uses the default one.
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        let config = &WriteConfig {
+            block_size: config.block_size.clone().filter(|s| s.ndim() == 1),
+            shard_size: config.shard_size.clone().filter(|s| s.ndim() == 1),
+            ..config.clone()
+        };
         let mut group = if location.exists(name)? {
             location.open_group(name)?
         } else {
@@ -50,7 +68,7 @@ impl Writable for DataFrame {
         self.metadata().save(&mut group)?;
 
         self.iter().try_for_each(|x| {
-            write_series(x, &group, x.name())?;
+            write_series(x, &group, x.name(), config)?;
             anyhow::Ok(())
         })?;
 
@@ -82,7 +100,7 @@ impl Writable for DataFrame {
         }
 
         self.iter().try_for_each(|x| {
-            write_series(x, container.as_group()?, x.name())?;
+            write_series(x, container.as_group()?, x.name(), &WriteConfig::default())?;
             anyhow::Ok(())
         })?;
         self.metadata().save(&mut container)?;
@@ -167,10 +185,7 @@ impl ReadableArray for DataFrame {
             .iter()
             .map(|i| {
                 let name = &columns[i];
-                let series = container
-                    .as_group()?
-                    .open_dataset(name)
-                    .map(DataContainer::Dataset)
+                let series = DataContainer::<B>::open(container.as_group()?, name)
                     .and_then(|x| read_series::<B>(&x))
                     .with_context(|| format!("Failed to read series: {}", name))?;
 
@@ -351,46 +366,143 @@ fn write_series<B: Backend, G: GroupOp<B>>(
     series: &Series,
     location: &G,
     name: &str,
+    config: &WriteConfig,
 ) -> Result<DataContainer<B>> {
     match series.dtype() {
-        DataType::UInt8 => write_series_helper(series.u8()?, location, name),
-        DataType::UInt16 => write_series_helper(series.u16()?, location, name),
-        DataType::UInt32 => write_series_helper(series.u32()?, location, name),
-        DataType::UInt64 => write_series_helper(series.u64()?, location, name),
-        DataType::Int8 => write_series_helper(series.i8()?, location, name),
-        DataType::Int16 => write_series_helper(series.i16()?, location, name),
-        DataType::Int32 => write_series_helper(series.i32()?, location, name),
-        DataType::Int64 => write_series_helper(series.i64()?, location, name),
-        DataType::Float32 => series
-            .f32()?
-            .into_iter()
-            .map(|x| x.unwrap_or(std::f32::NAN))
-            .collect::<Array1<f32>>()
-            .write(location, name),
-        DataType::Float64 => series
-            .f64()?
-            .into_iter()
-            .map(|x| x.unwrap_or(std::f64::NAN))
-            .collect::<Array1<f64>>()
-            .write(location, name),
-        DataType::Boolean => write_series_helper(series.bool()?, location, name),
-        DataType::String => {
-            let series = series.str()?;
-            if let Some(str_vec) = series.iter().map(|x| x.map(|s| s.to_string())).collect::<Option<Vec<_>>>() {
-                Array1::from(str_vec).write(location, name)
-            } else {
-                series.into_iter().collect::<CategoricalArray>().write(location, name)
-            }
-        },
-        DataType::Categorical(_, _) => series
-            .categorical()?
-            .iter_str()
-            .collect::<CategoricalArray>()
-            .write(location, name),
+        DataType::UInt8 => write_series_helper(series.u8()?, location, name, config),
+        DataType::UInt16 => write_series_helper(series.u16()?, location, name, config),
+        DataType::UInt32 => write_series_helper(series.u32()?, location, name, config),
+        DataType::UInt64 => write_series_helper(series.u64()?, location, name, config),
+        DataType::Int8 => write_series_helper(series.i8()?, location, name, config),
+        DataType::Int16 => write_series_helper(series.i16()?, location, name, config),
+        DataType::Int32 => write_series_helper(series.i32()?, location, name, config),
+        DataType::Int64 => write_series_helper(series.i64()?, location, name, config),
+        DataType::Float32 => write_series_helper(series.f32()?, location, name, config),
+        DataType::Float64 => write_series_helper(series.f64()?, location, name, config),
+        DataType::Boolean => write_series_helper(series.bool()?, location, name, config),
+        DataType::String => write_series_helper(
+            series.str()?.into_iter().map(|x| x.map(|s| s.to_string())),
+            location,
+            name,
+            config,
+        ),
+        DataType::Categorical(_, _) | DataType::Enum(_, _) => {
+            CategoricalArray::try_from(series)?.write_with_config(location, name, config)
+        }
+        DataType::Date | DataType::Datetime(_, _) | DataType::Duration(_) => {
+            write_temporal(series, location, name, config)
+        }
         other => bail!("Unsupported series data type: {:?}", other),
     }
 }
 
+/// Temporal columns are stored as int64 arrays with the same layout as numpy's
+/// `datetime64` and `timedelta64` types: nulls are represented by NaT (`i64::MIN`)
+/// and the numpy dtype is recorded in the `dtype` attribute, so that they can be
+/// recovered in Python with `np.asarray(x).view(x.attrs["dtype"])`. Time zones
+/// are kept in the `timezone` attribute.
+fn write_temporal<B: Backend, G: GroupOp<B>>(
+    series: &Series,
+    location: &G,
+    name: &str,
+    config: &WriteConfig,
+) -> Result<DataContainer<B>> {
+    fn unit_str(unit: &TimeUnit) -> &'static str {
+        match unit {
+            TimeUnit::Nanoseconds => "ns",
+            TimeUnit::Microseconds => "us",
+            TimeUnit::Milliseconds => "ms",
+        }
+    }
+
+    let (dtype, timezone) = match series.dtype() {
+        DataType::Date => ("datetime64[D]".to_string(), None),
+        DataType::Datetime(unit, tz) => (
+            format!("datetime64[{}]", unit_str(unit)),
+            tz.as_ref().map(|x| x.to_string()),
+        ),
+        DataType::Duration(unit) => (format!("timedelta64[{}]", unit_str(unit)), None),
+        other => bail!("Not a temporal data type: {:?}", other),
+    };
+    let values = series.to_physical_repr().cast(&DataType::Int64)?;
+    let mut container = values
+        .i64()?
+        .into_iter()
+        .map(|x| x.unwrap_or(i64::MIN))
+        .collect::<Array1<i64>>()
+        .write_with_config(location, name, config)?;
+    container.new_attr("dtype", dtype)?;
+    if let Some(tz) = timezone {
+        container.new_attr("timezone", tz)?;
+    }
+    Ok(container)
+}
+
+/// Read columns written by `write_temporal`. Returns `None` if the array is not
+/// a temporal column.
+fn read_temporal<B: Backend>(container: &DataContainer<B>) -> Result<Option<Series>> {
+    let dtype = match temporal_dtype(container)? {
+        Some(dtype) => dtype,
+        None => return Ok(None),
+    };
+    let values: Array1<i64> = container.as_dataset()?.read_array()?;
+    let values: Int64Chunked = values
+        .into_iter()
+        .map(|x| if x == i64::MIN { None } else { Some(x) })
+        .collect();
+
+    let series = match dtype {
+        DataType::Date => values
+            .into_iter()
+            .map(|x| x.map(|x| x as i32))
+            .collect::<Int32Chunked>()
+            .into_date()
+            .into_series(),
+        DataType::Datetime(unit, tz) => values.into_datetime(unit, tz).into_series(),
+        DataType::Duration(unit) => values.into_duration(unit).into_series(),
+        _ => unreachable!(),
+    };
+    Ok(Some(series))
+}
+
+/// The type of a column written by `write_temporal`, or `None` if the array
+/// is not a temporal column.
+fn temporal_dtype<B: Backend>(container: &DataContainer<B>) -> Result<Option<DataType>> {
+    let dtype = match container.get_attr::<String>("dtype") {
+        Ok(dtype) => dtype,
+        Err(_) => return Ok(None),
+    };
+    let parse_unit = |unit: &str| match unit {
+        "ns" => Ok(TimeUnit::Nanoseconds),
+        "us" => Ok(TimeUnit::Microseconds),
+        "ms" => Ok(TimeUnit::Milliseconds),
+        _ => bail!("Unsupported time unit: {}", unit),
+    };
+
+    let dtype = if dtype == "datetime64[D]" {
+        DataType::Date
+    } else if let Some(unit) = dtype
+        .strip_prefix("datetime64[")
+        .and_then(|x| x.strip_suffix(']'))
+    {
+        let tz = container
+            .get_attr::<String>("timezone")
+            .ok()
+            .map(|tz| TimeZone::opt_try_new(Some(tz)))
+            .transpose()?
+            .flatten();
+        DataType::Datetime(parse_unit(unit)?, tz)
+    } else if let Some(unit) = dtype
+        .strip_prefix("timedelta64[")
+        .and_then(|x| x.strip_suffix(']'))
+    {
+        DataType::Duration(parse_unit(unit)?)
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(dtype))
+}
+
 fn read_series<B: Backend>(container: &DataContainer<B>) -> Result<Series> {
     let ty = container.encoding_type()?;
     match ty {
@@ -402,12 +514,74 @@ fn read_series<B: Backend>(container: &DataContainer<B>) -> Result<Series> {
             };
             Ok(s)
         }
-        crate::backend::DataType::Array(_) => Ok(DynArray::read(container)?.into()),
+        crate::backend::DataType::Array(_) => match read_temporal(container)? {
+            Some(series) => Ok(series),
+            None => DynArray::read(container)?.try_into(),
+        },
         crate::backend::DataType::NullableArray => read_nullable(container),
         _ => bail!("Unsupported data type: {:?}", ty),
     }
 }
 
+/// Read the names and types of the columns of a dataframe from the metadata,
+/// without reading the columns. The types are those of the series returned by
+/// [`DataFrame::read`].
+pub(crate) fn read_column_types<B: Backend>(
+    container: &DataContainer<B>,
+) -> Result<Vec<(String, DataType)>> {
+    let columns: Vec<String> = container.get_attr("column-order")?;
+    columns
+        .into_iter()
+        .map(|name| {
+            let series_container = DataContainer::<B>::open(container.as_group()?, &name)?;
+            let dtype = series_dtype::<B>(&series_container)
+                .with_context(|| format!("Failed to read the type of series: {}", name))?;
+            Ok((name, dtype))
+        })
+        .collect()
+}
+
+/// The type of the series returned by `read_series`.
+fn series_dtype<B: Backend>(container: &DataContainer<B>) -> Result<DataType> {
+    let scalar_dtype = |ty: ScalarType| {
+        let dtype = match ty {
+            ScalarType::I8 => DataType::Int8,
+            ScalarType::I16 => DataType::Int16,
+            ScalarType::I32 => DataType::Int32,
+            ScalarType::I64 => DataType::Int64,
+            ScalarType::U8 => DataType::UInt8,
+            ScalarType::U16 => DataType::UInt16,
+            ScalarType::U32 => DataType::UInt32,
+            ScalarType::U64 => DataType::UInt64,
+            ScalarType::F16 | ScalarType::F32 => DataType::Float32,
+            ScalarType::F64 => DataType::Float64,
+            ScalarType::Bool => DataType::Boolean,
+            ScalarType::String => DataType::String,
+            ScalarType::Complex32 | ScalarType::Complex64 => {
+                bail!("complex columns are not supported")
+            }
+        };
+        Ok(dtype)
+    };
+    let ty = container.encoding_type()?;
+    match ty {
+        crate::backend::DataType::Categorical => {
+            match container.as_group()?.open_dataset("categories")?.dtype()? {
+                ScalarType::String => Ok(DataType::Categorical(None, Default::default())),
+                ty => scalar_dtype(ty),
+            }
+        }
+        crate::backend::DataType::Array(ty) => match temporal_dtype(container)? {
+            Some(dtype) => Ok(dtype),
+            None => scalar_dtype(ty),
+        },
+        crate::backend::DataType::NullableArray => {
+            scalar_dtype(container.as_group()?.open_dataset("values")?.dtype()?)
+        }
+        _ => bail!("Unsupported data type: {:?}", ty),
+    }
+}
+
 /// Used to read non-string categorical data into regular arrays. After all, such
 /// data should not be stored as categorical data.
 fn read_cat_as_series<B: Backend>(container: &DataContainer<B>) -> Result<Series> {
@@ -417,6 +591,15 @@ fn read_cat_as_series<B: Backend>(container: &DataContainer<B>) -> Result<Series
     let categories = group.open_dataset("categories")?.read_dyn_array().unwrap();
 
     macro_rules! fun {
+        (F16, $value:expr) => {
+            codes.iter().map(|x| x.map(|i| $value[i].to_f32())).collect()
+        };
+        (Complex32, $value:expr) => {
+            bail!("complex categories are not supported")
+        };
+        (Complex64, $value:expr) => {
+            bail!("complex categories are not supported")
+        };
         ($variant:ident, $value:expr) => {
             codes.iter().map(|x| x.map(|i| $value[i].clone())).collect()
         };
@@ -426,7 +609,12 @@ fn read_cat_as_series<B: Backend>(container: &DataContainer<B>) -> Result<Series
     Ok(arr)
 }
 
-fn write_series_helper<B, G, I, T>(data: I, location: &G, name: &str) -> Result<DataContainer<B>>
+fn write_series_helper<B, G, I, T>(
+    data: I,
+    location: &G,
+    name: &str,
+    config: &WriteConfig,
+) -> Result<DataContainer<B>>
 where
     B: Backend,
     G: GroupOp<B>,
@@ -458,15 +646,18 @@ where
             | ScalarType::U32
             | ScalarType::U64 => "nullable-integer",
             ScalarType::Bool => "nullable-boolean",
-            ScalarType::String => "nullable-string",
-            ScalarType::F32 | ScalarType::F64 => bail!("float types are not supported"),
+            ScalarType::String => "nullable-string-array",
+            ScalarType::F16 | ScalarType::F32 | ScalarType::F64 => "nullable-float",
+            ScalarType::Complex32 | ScalarType::Complex64 => {
+                bail!("nullable complex arrays are not supported")
+            }
         };
         MetaData::new(encoding, "0.1.0", None).save(&mut group)?;
-        Array1::from(values).write(&group, "values")?;
-        Array1::from(mask).write(&group, "mask")?;
+        Array1::from(values).write_with_config(&group, "values", config)?;
+        Array1::from(mask).write_with_config(&group, "mask", config)?;
         Ok(DataContainer::Group(group))
     } else {
-        Array1::from(values).write(location, name)
+        Array1::from(values).write_with_config(location, name, config)
     }
 }
 
@@ -478,6 +669,18 @@ where
     let dataset = container.as_group()?.open_dataset("values")?;
 
     macro_rules! fun {
+        (f16) => {
+            mask.iter()
+                .zip(dataset.read_array::<f16, Ix1>()?.into_iter())
+                .map(|(m, v)| if *m { None } else { Some(v.to_f32()) })
+                .collect()
+        };
+        (Complex32) => {
+            bail!("nullable complex arrays are not supported")
+        };
+        (Complex64) => {
+            bail!("nullable complex arrays are not supported")
+        };
         ($variant:ident) => {
             mask.iter()
                 .zip(dataset.read_array::<$variant, Ix1>()?.into_iter())
@@ -13,10 +13,15 @@ use crate::{
 use anyhow::{anyhow, Result};
 use ndarray::{Array, Array1, ArrayD, ArrayView, Axis, Dimension, RemoveAxis, SliceInfoElem};
 use polars::{
-    prelude::CategoricalChunkedBuilder,
+    datatypes::{CategoricalOrdering, DataType as PolarsDataType},
+    prelude::{
+        CategoricalChunked, CategoricalChunkedBuilder, NewChunkedArray, RevMapping, StringChunked,
+        UInt32Chunked,
+    },
     series::{IntoSeries, Series},
 };
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::ops::Index;
 
 impl<'a, T: BackendData, D> Element for ArrayView<'a, T, D> {
@@ -39,7 +44,16 @@ impl<'a, T: BackendData, D: Dimension> Writable for ArrayView<'a, T, D> {
         location: &G,
         name: &str,
     ) -> Result<DataContainer<B>> {
-        let dataset = location.new_array_dataset(name, self.into(), Default::default())?;
+        self.write_with_config(location, name, &WriteConfig::default())
+    }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        let dataset = location.new_array_dataset(name, self.into(), config.clone())?;
         let mut container = DataContainer::<B>::Dataset(dataset);
         self.metadata().save(&mut container)?;
         Ok(container)
@@ -74,6 +88,15 @@ impl<T: BackendData, D: Dimension> Writable for Array<T, D> {
     ) -> Result<DataContainer<B>> {
         self.view().write(location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        self.view().write_with_config(location, name, config)
+    }
 }
 
 impl<T, D: Dimension> HasShape for Array<T, D> {
@@ -164,25 +187,85 @@ impl<'a, T: BackendData, D: Dimension> WritableArray for ArrayView<'a, T, D> {}
 /// These arrays encode the values as small width integers (codes), which map to
 /// the original label set (categories). Each entry in the codes array is the
 /// zero-based index of the encoded value in the categories array.
+/// If `ordered` is true, the order of the categories is meaningful.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CategoricalArray {
     pub codes: ArrayD<Option<u32>>,
     pub categories: Array1<String>,
+    pub ordered: bool,
 }
 
+/// Ordered categorical arrays are converted to the polars Enum type, whose
+/// categories are fixed. Unordered ones are converted to Categorical.
+/// In both cases the order of the categories, including unused ones, is kept.
 impl Into<Series> for CategoricalArray {
     fn into(self) -> Series {
-        CategoricalChunkedBuilder::new(
-            "".into(),
-            self.codes.len(),
-            polars::datatypes::CategoricalOrdering::Lexical,
-        )
-        .drain_iter_and_finish(
-            self.codes
-                .into_iter()
-                .map(|i| Some(self.categories[i? as usize].as_str())),
-        )
-        .into_series()
+        if self.ordered {
+            let categories = StringChunked::from_iter_values(
+                "".into(),
+                self.categories.iter().map(|x| x.as_str()),
+            );
+            let categories = categories.rechunk();
+            let rev_map = RevMapping::build_local(categories.downcast_iter().next().unwrap().clone());
+            let codes: UInt32Chunked = self.codes.into_iter().collect();
+            unsafe {
+                CategoricalChunked::from_cats_and_rev_map_unchecked(
+                    codes,
+                    Arc::new(rev_map),
+                    true,
+                    CategoricalOrdering::Physical,
+                )
+            }
+            .into_series()
+        } else {
+            // The categories are inserted first to fix their order in the
+            // mapping, and then sliced off.
+            let n = self.codes.len();
+            let n_categories = self.categories.len();
+            CategoricalChunkedBuilder::new(
+                "".into(),
+                n_categories + n,
+                CategoricalOrdering::Lexical,
+            )
+            .drain_iter_and_finish(
+                self.categories.iter().map(|x| Some(x.as_str())).chain(
+                    self.codes
+                        .iter()
+                        .map(|i| Some(self.categories[(*i)? as usize].as_str())),
+                ),
+            )
+            .into_series()
+            .slice(n_categories as i64, n)
+        }
+    }
+}
+
+impl TryFrom<&Series> for CategoricalArray {
+    type Error = anyhow::Error;
+
+    fn try_from(series: &Series) -> Result<Self> {
+        let ordered = matches!(series.dtype(), PolarsDataType::Enum(_, _));
+        let ca = series.categorical()?;
+        let categories: Array1<String> = ca
+            .get_rev_map()
+            .get_categories()
+            .values_iter()
+            .map(|x| x.to_string())
+            .collect();
+        let index: HashMap<&str, u32> = categories
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (x.as_str(), i as u32))
+            .collect();
+        let codes: Array1<Option<u32>> = ca
+            .iter_str()
+            .map(|x| x.map(|x| index[x]))
+            .collect();
+        Ok(CategoricalArray {
+            codes: codes.into_dyn(),
+            categories,
+            ordered,
+        })
     }
 }
 
@@ -214,6 +297,7 @@ impl<'a> FromIterator<Option<&'a str>> for CategoricalArray {
         CategoricalArray {
             codes: codes.into_dyn(),
             categories: categories.into_iter().map(|x| x.0).collect(),
+            ordered: false,
         }
     }
 }
@@ -221,7 +305,7 @@ impl<'a> FromIterator<Option<&'a str>> for CategoricalArray {
 impl Element for CategoricalArray {
     fn metadata(&self) -> MetaData {
         let mut metadata = HashMap::new();
-        metadata.insert("ordered".to_string(), false.into());
+        metadata.insert("ordered".to_string(), self.ordered.into());
         MetaData::new("categorical", "0.2.0", Some(metadata))
     }
 
@@ -235,14 +319,23 @@ impl Writable for CategoricalArray {
         &self,
         location: &G,
         name: &str,
+    ) -> Result<DataContainer<B>> {
+        self.write_with_config(location, name, &WriteConfig::default())
+    }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
     ) -> Result<DataContainer<B>> {
         let mut group = location.new_group(name)?;
         self.metadata().save(&mut group)?;
 
         self.codes
             .map(|x| x.map_or(-1, |x| x as i32))
-            .write(&group, "codes")?;
-        self.categories.view().write(&group, "categories")?;
+            .write_with_config(&group, "codes", config)?;
+        self.categories.view().write_with_config(&group, "categories", config)?;
 
         Ok(DataContainer::Group(group))
     }
@@ -269,6 +362,7 @@ impl Selectable for CategoricalArray {
         CategoricalArray {
             codes: Selectable::select(&self.codes, info),
             categories: self.categories.clone(),
+            ordered: self.ordered,
         }
     }
 }
@@ -280,8 +374,12 @@ impl Readable for CategoricalArray {
         let group = container.as_group()?;
         let codes: ArrayD<i32> = group.open_dataset("codes")?.read_array_cast()?;
         let codes = codes.mapv(|x| if x < 0 { None } else { Some(x as u32) });
-        let categories = group.open_dataset("categories")?.read_array().unwrap();
-        Ok(CategoricalArray { codes, categories })
+        let categories = group.open_dataset("categories")?.read_array()?;
+        Ok(CategoricalArray {
+            codes,
+            categories,
+            ordered: is_ordered::<B, _>(group),
+        })
     }
 }
 
@@ -301,6 +399,20 @@ impl ReadableArray for CategoricalArray {
         let codes: ArrayD<i32> = group.open_dataset("codes")?.read_array_slice(info)?;
         let codes = codes.mapv(|x| if x < 0 { None } else { Some(x as u32) });
         let categories = group.open_dataset("categories")?.read_array()?;
-        Ok(CategoricalArray { codes, categories })
+        Ok(CategoricalArray {
+            codes,
+            categories,
+            ordered: is_ordered::<B, _>(group),
+        })
     }
 }
+
+/// Read the `ordered` attribute of a categorical group. Depending on the writer
+/// it may be stored as a boolean or an integer.
+fn is_ordered<B: Backend, G: AttributeOp<B>>(group: &G) -> bool {
+    group
+        .get_json_attr("ordered")
+        .ok()
+        .and_then(|x| x.as_bool().or(x.as_u64().map(|x| x != 0)))
+        .unwrap_or(false)
+}
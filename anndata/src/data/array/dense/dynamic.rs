@@ -21,8 +21,11 @@ pub enum DynScalar {
     U16(u16),
     U32(u32),
     U64(u64),
+    F16(f16),
     F32(f32),
     F64(f64),
+    Complex32(Complex32),
+    Complex64(Complex64),
     Bool(bool),
     String(String),
 }
@@ -75,8 +78,8 @@ macro_rules! impl_from_dynscalar {
 }
 
 impl_from_dynscalar!(
-    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f32, F32, f64, F64,
-    bool, Bool, String, String
+    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f16, F16, f32, F32,
+    f64, F64, Complex32, Complex32, Complex64, Complex64, bool, Bool, String, String
 );
 
 impl Element for DynScalar {
@@ -124,8 +127,11 @@ pub enum DynArray {
     U16(ArrayD<u16>),
     U32(ArrayD<u32>),
     U64(ArrayD<u64>),
+    F16(ArrayD<f16>),
     F32(ArrayD<f32>),
     F64(ArrayD<f64>),
+    Complex32(ArrayD<Complex32>),
+    Complex64(ArrayD<Complex64>),
     Bool(ArrayD<bool>),
     String(ArrayD<String>),
 }
@@ -161,8 +167,8 @@ impl DynArray {
     }
 
     impl_dynarray_into_array!(
-        I8, i8, I16, i16, I32, i32, I64, i64, U8, u8, U16, u16, U32, u32, U64, u64, F32, f32, F64,
-        f64, Bool, bool, String, String
+        I8, i8, I16, i16, I32, i32, I64, i64, U8, u8, U16, u16, U32, u32, U64, u64, F16, f16, F32,
+        f32, F64, f64, Complex32, Complex32, Complex64, Complex64, Bool, bool, String, String
     );
 }
 
@@ -189,13 +195,15 @@ macro_rules! impl_dynarray_traits{
 }
 
 impl_dynarray_traits!(
-    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f32, F32, f64, F64,
-    bool, Bool, String, String
+    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f16, F16, f32, F32,
+    f64, F64, Complex32, Complex32, Complex64, Complex64, bool, Bool, String, String
 );
 
-impl Into<Series> for DynArray {
-    fn into(self) -> Series {
-        match self {
+impl TryFrom<DynArray> for Series {
+    type Error = anyhow::Error;
+
+    fn try_from(arr: DynArray) -> Result<Self, Self::Error> {
+        let series = match arr {
             DynArray::I8(x) => x.iter().collect(),
             DynArray::I16(x) => x.iter().collect(),
             DynArray::I32(x) => x.iter().collect(),
@@ -204,11 +212,16 @@ impl Into<Series> for DynArray {
             DynArray::U16(x) => x.iter().collect(),
             DynArray::U32(x) => x.iter().collect(),
             DynArray::U64(x) => x.iter().collect(),
+            DynArray::F16(x) => x.iter().map(|x| x.to_f32()).collect(),
             DynArray::F32(x) => x.iter().collect(),
             DynArray::F64(x) => x.iter().collect(),
+            DynArray::Complex32(_) | DynArray::Complex64(_) => {
+                bail!("complex arrays cannot be converted to Series")
+            }
             DynArray::Bool(x) => x.iter().collect(),
             DynArray::String(x) => x.iter().map(|x| x.as_str()).collect(),
-        }
+        };
+        Ok(series)
     }
 }
 
@@ -230,6 +243,15 @@ impl Writable for DynArray {
     ) -> Result<DataContainer<B>> {
         crate::macros::dyn_map_fun!(self, Self, write, location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        crate::macros::dyn_map_fun!(self, Self, write_with_config, location, name, config)
+    }
 }
 
 impl Readable for DynArray {
@@ -305,6 +327,15 @@ impl Stackable for DynArray {
             DynArray::F64(_) => {
                 ArrayD::<f64>::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
             }
+            DynArray::F16(_) => {
+                ArrayD::<f16>::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
+            }
+            DynArray::Complex32(_) => {
+                ArrayD::<Complex32>::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
+            }
+            DynArray::Complex64(_) => {
+                ArrayD::<Complex64>::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
+            }
             DynArray::Bool(_) => {
                 ArrayD::<bool>::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
             }
@@ -340,8 +371,11 @@ pub enum DynCowArray<'a> {
     U16(CowArray<'a, u16, IxDyn>),
     U32(CowArray<'a, u32, IxDyn>),
     U64(CowArray<'a, u64, IxDyn>),
+    F16(CowArray<'a, f16, IxDyn>),
     F32(CowArray<'a, f32, IxDyn>),
     F64(CowArray<'a, f64, IxDyn>),
+    Complex32(CowArray<'a, Complex32, IxDyn>),
+    Complex64(CowArray<'a, Complex64, IxDyn>),
     Bool(CowArray<'a, bool, IxDyn>),
     String(CowArray<'a, String, IxDyn>),
 }
@@ -422,8 +456,11 @@ impl_dyn_cowarray_convert!(u8, U8);
 impl_dyn_cowarray_convert!(u16, U16);
 impl_dyn_cowarray_convert!(u32, U32);
 impl_dyn_cowarray_convert!(u64, U64);
+impl_dyn_cowarray_convert!(f16, F16);
 impl_dyn_cowarray_convert!(f32, F32);
 impl_dyn_cowarray_convert!(f64, F64);
+impl_dyn_cowarray_convert!(Complex32, Complex32);
+impl_dyn_cowarray_convert!(Complex64, Complex64);
 impl_dyn_cowarray_convert!(bool, Bool);
 impl_dyn_cowarray_convert!(String, String);
 
@@ -590,6 +627,7 @@ impl<D: Dimension> ArrayConvert<Array<f32, D>> for DynArray {
     fn try_convert(self) -> Result<Array<f32, D>> {
         match self {
             DynArray::F32(data) => Ok(data.into_dimensionality()?),
+            DynArray::F16(data) => Ok(data.mapv(|x| x.to_f32()).into_dimensionality()?),
             DynArray::I8(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
             DynArray::I16(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
             DynArray::U8(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
@@ -604,6 +642,7 @@ impl<D: Dimension> ArrayConvert<Array<f64, D>> for DynArray {
     fn try_convert(self) -> Result<Array<f64, D>> {
         match self {
             DynArray::F64(data) => Ok(data.into_dimensionality()?),
+            DynArray::F16(data) => Ok(data.mapv(|x| x.to_f64()).into_dimensionality()?),
             DynArray::I8(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
             DynArray::I16(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
             DynArray::I32(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
@@ -617,6 +656,45 @@ impl<D: Dimension> ArrayConvert<Array<f64, D>> for DynArray {
     }
 }
 
+impl<D: Dimension> ArrayConvert<Array<f16, D>> for DynArray {
+    fn try_convert(self) -> Result<Array<f16, D>> {
+        match self {
+            DynArray::F16(data) => Ok(data.into_dimensionality()?),
+            DynArray::I8(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
+            DynArray::U8(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
+            DynArray::Bool(data) => Ok(data.mapv(|x| f16::from(x as u8)).into_dimensionality()?),
+            _ => bail!("Cannot convert to f16 Array"),
+        }
+    }
+}
+
+impl<D: Dimension> ArrayConvert<Array<Complex32, D>> for DynArray {
+    fn try_convert(self) -> Result<Array<Complex32, D>> {
+        match self {
+            DynArray::Complex32(data) => Ok(data.into_dimensionality()?),
+            other => {
+                let data: Array<f32, D> = other.try_convert()?;
+                Ok(data.mapv(|x| x.into()))
+            }
+        }
+    }
+}
+
+impl<D: Dimension> ArrayConvert<Array<Complex64, D>> for DynArray {
+    fn try_convert(self) -> Result<Array<Complex64, D>> {
+        match self {
+            DynArray::Complex64(data) => Ok(data.into_dimensionality()?),
+            DynArray::Complex32(data) => Ok(data
+                .mapv(|x| Complex64::new(x.re.into(), x.im.into()))
+                .into_dimensionality()?),
+            other => {
+                let data: Array<f64, D> = other.try_convert()?;
+                Ok(data.mapv(|x| x.into()))
+            }
+        }
+    }
+}
+
 impl<D: Dimension> ArrayConvert<Array<bool, D>> for DynArray {
     fn try_convert(self) -> Result<Array<bool, D>> {
         match self {
@@ -638,8 +716,11 @@ impl<D: Dimension> ArrayConvert<Array<String, D>> for DynArray {
             DynArray::U16(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
             DynArray::U32(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
             DynArray::U64(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
+            DynArray::F16(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
             DynArray::F32(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
             DynArray::F64(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
+            DynArray::Complex32(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
+            DynArray::Complex64(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
             DynArray::Bool(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
         }
     }
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use crate::backend::*;
 use crate::data::{
-    array::utils::{cs_major_index, cs_major_minor_index, cs_major_slice},
+    array::utils::{cs_major_index, cs_major_minor_index, cs_major_slice, write_cs_indices},
     array::DynScalar,
     data_traits::*,
     slice::{SelectInfoElem, Shape},
@@ -215,77 +215,31 @@ impl<T: BackendData> Writable for CscMatrix<T> {
         location: &G,
         name: &str,
     ) -> Result<DataContainer<B>> {
-        let mut group = location.new_group(name)?;
-        let shape = self.shape();
+        self.write_with_config(location, name, &WriteConfig::default())
+    }
 
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        let mut group = location.new_group(name)?;
         self.metadata().save(&mut group)?;
-        group.new_array_dataset("data", self.values().into(), Default::default())?;
-
-        let num_rows = shape[0];
-        // Use i32 or i64 as indices type in order to be compatible with scipy
-        if TryInto::<i32>::try_into(num_rows.saturating_sub(1)).is_ok() {
-            let try_convert_indptr: Option<Vec<i32>> = self
-                .col_offsets()
-                .iter()
-                .map(|x| (*x).try_into().ok())
-                .collect();
-            if let Some(indptr_i32) = try_convert_indptr {
-                group.new_array_dataset("indptr", indptr_i32.into(), Default::default())?;
-                group.new_array_dataset(
-                    "indices",
-                    self.row_indices()
-                        .iter()
-                        .map(|x| (*x) as i32)
-                        .collect::<Vec<_>>()
-                        .into(),
-                    Default::default(),
-                )?;
-            } else {
-                group.new_array_dataset(
-                    "indptr",
-                    self.col_offsets()
-                        .iter()
-                        .map(|x| TryInto::<i64>::try_into(*x).unwrap())
-                        .collect::<Vec<_>>()
-                        .into(),
-                    Default::default(),
-                )?;
-                group.new_array_dataset(
-                    "indices",
-                    self.row_indices()
-                        .iter()
-                        .map(|x| (*x) as i64)
-                        .collect::<Vec<_>>()
-                        .into(),
-                    Default::default(),
-                )?;
-            }
-        } else if TryInto::<i64>::try_into(num_rows.saturating_sub(1)).is_ok() {
-            group.new_array_dataset(
-                "indptr",
-                self.col_offsets()
-                    .iter()
-                    .map(|x| TryInto::<i64>::try_into(*x).unwrap())
-                    .collect::<Vec<_>>()
-                    .into(),
-                Default::default(),
-            )?;
-            group.new_array_dataset(
-                "indices",
-                self.row_indices()
-                    .iter()
-                    .map(|x| (*x) as i64)
-                    .collect::<Vec<_>>()
-                    .into(),
-                Default::default(),
-            )?;
-        } else {
-            panic!(
-                "The number of rows ({}) is too large to be stored as i64",
-                num_rows
-            );
+        if config.sparse_encoding == SparseEncoding::Array {
+            group.new_attr("encoding-type", "csc_array")?;
         }
 
+        let data_config = config.flattened();
+        group.new_array_dataset("data", self.values().into(), data_config)?;
+        write_cs_indices(
+            &group,
+            self.col_offsets(),
+            self.row_indices(),
+            self.shape()[0],
+            config,
+        )?;
+
         Ok(DataContainer::Group(group))
     }
 }
@@ -20,8 +20,11 @@ pub enum DynCsrMatrix {
     U16(CsrMatrix<u16>),
     U32(CsrMatrix<u32>),
     U64(CsrMatrix<u64>),
+    F16(CsrMatrix<f16>),
     F32(CsrMatrix<f32>),
     F64(CsrMatrix<f64>),
+    Complex32(CsrMatrix<Complex32>),
+    Complex64(CsrMatrix<Complex64>),
     Bool(CsrMatrix<bool>),
     String(CsrMatrix<String>),
 }
@@ -52,8 +55,8 @@ macro_rules! impl_dyncsr_traits {
 }
 
 impl_dyncsr_traits!(
-    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f32, F32, f64, F64,
-    bool, Bool, String, String
+    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f16, F16, f32, F32,
+    f64, F64, Complex32, Complex32, Complex64, Complex64, bool, Bool, String, String
 );
 
 impl Element for DynCsrMatrix {
@@ -74,6 +77,15 @@ impl Writable for DynCsrMatrix {
     ) -> Result<DataContainer<B>> {
         crate::macros::dyn_map_fun!(self, DynCsrMatrix, write, location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        crate::macros::dyn_map_fun!(self, DynCsrMatrix, write_with_config, location, name, config)
+    }
 }
 
 impl Readable for DynCsrMatrix {
@@ -146,6 +158,15 @@ impl Stackable for DynCsrMatrix {
             DynCsrMatrix::F64(_) => Ok(DynCsrMatrix::F64(CsrMatrix::<f64>::vstack(
                 iter.map(|x| x.try_into().unwrap()),
             )?)),
+            DynCsrMatrix::F16(_) => Ok(DynCsrMatrix::F16(CsrMatrix::<f16>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCsrMatrix::Complex32(_) => Ok(DynCsrMatrix::Complex32(
+                CsrMatrix::<Complex32>::vstack(iter.map(|x| x.try_into().unwrap()))?,
+            )),
+            DynCsrMatrix::Complex64(_) => Ok(DynCsrMatrix::Complex64(
+                CsrMatrix::<Complex64>::vstack(iter.map(|x| x.try_into().unwrap()))?,
+            )),
             DynCsrMatrix::Bool(_) => Ok(DynCsrMatrix::Bool(CsrMatrix::<bool>::vstack(
                 iter.map(|x| x.try_into().unwrap()),
             )?)),
@@ -194,8 +215,11 @@ pub enum DynCscMatrix {
     U16(CscMatrix<u16>),
     U32(CscMatrix<u32>),
     U64(CscMatrix<u64>),
+    F16(CscMatrix<f16>),
     F32(CscMatrix<f32>),
     F64(CscMatrix<f64>),
+    Complex32(CscMatrix<Complex32>),
+    Complex64(CscMatrix<Complex64>),
     Bool(CscMatrix<bool>),
     String(CscMatrix<String>),
 }
@@ -226,8 +250,8 @@ macro_rules! impl_dyncsc_traits {
 }
 
 impl_dyncsc_traits!(
-    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f32, F32, f64, F64,
-    bool, Bool, String, String
+    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f16, F16, f32, F32,
+    f64, F64, Complex32, Complex32, Complex64, Complex64, bool, Bool, String, String
 );
 
 impl Element for DynCscMatrix {
@@ -248,6 +272,15 @@ impl Writable for DynCscMatrix {
     ) -> Result<DataContainer<B>> {
         crate::macros::dyn_map_fun!(self, DynCscMatrix, write, location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        crate::macros::dyn_map_fun!(self, DynCscMatrix, write_with_config, location, name, config)
+    }
 }
 
 impl Readable for DynCscMatrix {
@@ -352,6 +385,7 @@ macro_rules! impl_arrayconvert {
                         [<Dyn $ty>]::U32(data) => $fun(data, |x| Ok(f32::from_u32(x).unwrap())),
                         [<Dyn $ty>]::U64(data) => $fun(data, |x| Ok(f32::from_u64(x).unwrap())),
                         [<Dyn $ty>]::F64(data) => $fun(data, |x| Ok(f32::from_f64(x).unwrap())),
+                        [<Dyn $ty>]::F16(data) => $fun(data, |x| Ok(x.to_f32())),
                         [<Dyn $ty>]::Bool(data) => $fun(data, |x| Ok(x.into())),
                         v => bail!("Cannot convert {} to {}<f32>", v.data_type(), stringify!($ty)),
                     }
@@ -371,6 +405,7 @@ macro_rules! impl_arrayconvert {
                         [<Dyn $ty>]::U32(data) => $fun(data, |x| Ok(x.into())),
                         [<Dyn $ty>]::U64(data) => $fun(data, |x| Ok(f64::from_u64(x).unwrap())),
                         [<Dyn $ty>]::F32(data) => $fun(data, |x| Ok(x.into())),
+                        [<Dyn $ty>]::F16(data) => $fun(data, |x| Ok(x.to_f64())),
                         [<Dyn $ty>]::Bool(data) => $fun(data, |x| Ok(x.into())),
                         v => bail!("Cannot convert {} to {}<f64>", v.data_type(), stringify!($ty)),
                     }
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use crate::backend::*;
 use crate::data::{
-    array::utils::{cs_major_index, cs_major_minor_index, cs_major_slice},
+    array::utils::{cs_major_index, cs_major_minor_index, cs_major_slice, write_cs_indices},
     data_traits::*,
     slice::{SelectInfoElem, Shape},
     SelectInfoBounds, SelectInfoElemBounds,
@@ -26,8 +26,11 @@ pub enum DynCsrNonCanonical {
     U16(CsrNonCanonical<u16>),
     U32(CsrNonCanonical<u32>),
     U64(CsrNonCanonical<u64>),
+    F16(CsrNonCanonical<f16>),
     F32(CsrNonCanonical<f32>),
     F64(CsrNonCanonical<f64>),
+    Complex32(CsrNonCanonical<Complex32>),
+    Complex64(CsrNonCanonical<Complex64>),
     Bool(CsrNonCanonical<bool>),
     String(CsrNonCanonical<String>),
 }
@@ -75,6 +78,18 @@ impl DynCsrNonCanonical {
                 .canonicalize()
                 .map(DynCsrMatrix::F64)
                 .map_err(Into::into),
+            DynCsrNonCanonical::F16(data) => data
+                .canonicalize()
+                .map(DynCsrMatrix::F16)
+                .map_err(Into::into),
+            DynCsrNonCanonical::Complex32(data) => data
+                .canonicalize()
+                .map(DynCsrMatrix::Complex32)
+                .map_err(Into::into),
+            DynCsrNonCanonical::Complex64(data) => data
+                .canonicalize()
+                .map(DynCsrMatrix::Complex64)
+                .map_err(Into::into),
             DynCsrNonCanonical::Bool(data) => data
                 .canonicalize()
                 .map(DynCsrMatrix::Bool)
@@ -113,8 +128,8 @@ macro_rules! impl_noncanonicalcsr_traits {
 }
 
 impl_noncanonicalcsr_traits!(
-    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f32, F32, f64, F64,
-    bool, Bool, String, String
+    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f16, F16, f32, F32,
+    f64, F64, Complex32, Complex32, Complex64, Complex64, bool, Bool, String, String
 );
 
 impl From<DynCsrMatrix> for DynCsrNonCanonical {
@@ -146,6 +161,15 @@ impl Writable for DynCsrNonCanonical {
     ) -> Result<DataContainer<B>> {
         crate::macros::dyn_map_fun!(self, DynCsrNonCanonical, write, location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        crate::macros::dyn_map_fun!(self, DynCsrNonCanonical, write_with_config, location, name, config)
+    }
 }
 
 impl Readable for DynCsrNonCanonical {
@@ -218,6 +242,15 @@ impl Stackable for DynCsrNonCanonical {
             DynCsrNonCanonical::F64(_) => Ok(DynCsrNonCanonical::F64(
                 CsrNonCanonical::<f64>::vstack(iter.map(|x| x.try_into().unwrap()))?,
             )),
+            DynCsrNonCanonical::F16(_) => Ok(DynCsrNonCanonical::F16(
+                CsrNonCanonical::<f16>::vstack(iter.map(|x| x.try_into().unwrap()))?,
+            )),
+            DynCsrNonCanonical::Complex32(_) => Ok(DynCsrNonCanonical::Complex32(
+                CsrNonCanonical::<Complex32>::vstack(iter.map(|x| x.try_into().unwrap()))?,
+            )),
+            DynCsrNonCanonical::Complex64(_) => Ok(DynCsrNonCanonical::Complex64(
+                CsrNonCanonical::<Complex64>::vstack(iter.map(|x| x.try_into().unwrap()))?,
+            )),
             DynCsrNonCanonical::Bool(_) => Ok(DynCsrNonCanonical::Bool(
                 CsrNonCanonical::<bool>::vstack(iter.map(|x| x.try_into().unwrap()))?,
             )),
@@ -647,78 +680,31 @@ impl<T: BackendData> Writable for CsrNonCanonical<T> {
         location: &G,
         name: &str,
     ) -> Result<DataContainer<B>> {
-        let mut group = location.new_group(name)?;
-        let shape = self.shape();
+        self.write_with_config(location, name, &WriteConfig::default())
+    }
 
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        let mut group = location.new_group(name)?;
         self.metadata().save(&mut group)?;
-
-        group.new_array_dataset("data", self.values().into(), Default::default())?;
-
-        let num_cols = shape[1];
-        // Use i32 or i64 as indices type in order to be compatible with scipy
-        if TryInto::<i32>::try_into(num_cols.saturating_sub(1)).is_ok() {
-            let try_convert_indptr: Option<Vec<i32>> = self
-                .row_offsets()
-                .iter()
-                .map(|x| (*x).try_into().ok())
-                .collect();
-            if let Some(indptr_i32) = try_convert_indptr {
-                group.new_array_dataset("indptr", indptr_i32.into(), Default::default())?;
-                group.new_array_dataset(
-                    "indices",
-                    self.col_indices()
-                        .iter()
-                        .map(|x| (*x) as i32)
-                        .collect::<Vec<_>>()
-                        .into(),
-                    Default::default(),
-                )?;
-            } else {
-                group.new_array_dataset(
-                    "indptr",
-                    self.row_offsets()
-                        .iter()
-                        .map(|x| TryInto::<i64>::try_into(*x).unwrap())
-                        .collect::<Vec<_>>()
-                        .into(),
-                    Default::default(),
-                )?;
-                group.new_array_dataset(
-                    "indices",
-                    self.col_indices()
-                        .iter()
-                        .map(|x| (*x) as i64)
-                        .collect::<Vec<_>>()
-                        .into(),
-                    Default::default(),
-                )?;
-            }
-        } else if TryInto::<i64>::try_into(num_cols.saturating_sub(1)).is_ok() {
-            group.new_array_dataset(
-                "indptr",
-                self.row_offsets()
-                    .iter()
-                    .map(|x| TryInto::<i64>::try_into(*x).unwrap())
-                    .collect::<Vec<_>>()
-                    .into(),
-                Default::default(),
-            )?;
-            group.new_array_dataset(
-                "indices",
-                self.col_indices()
-                    .iter()
-                    .map(|x| (*x) as i64)
-                    .collect::<Vec<_>>()
-                    .into(),
-                Default::default(),
-            )?;
-        } else {
-            panic!(
-                "The number of columns ({}) is too large to be stored as i64",
-                num_cols
-            );
+        if config.sparse_encoding == SparseEncoding::Array {
+            group.new_attr("encoding-type", "csr_array")?;
         }
 
+        let data_config = config.flattened();
+        group.new_array_dataset("data", self.values().into(), data_config)?;
+        write_cs_indices(
+            &group,
+            self.row_offsets(),
+            self.col_indices(),
+            self.shape()[1],
+            config,
+        )?;
+
         Ok(DataContainer::Group(group))
     }
 }
@@ -1,14 +1,17 @@
-use crate::backend::{Backend, BackendData, DatasetOp, GroupOp, WriteConfig};
+use crate::backend::{
+    AttributeOp, Backend, BackendData, DataContainer, DatasetOp, GroupOp, IndexWidth, ScalarType,
+    WriteConfig,
+};
 use crate::data::{SelectInfoElem, Shape};
 use crate::ArrayData;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use itertools::Itertools;
 use nalgebra_sparse::{
     pattern::{SparsityPattern, SparsityPatternFormatError},
     CsrMatrix,
 };
-use ndarray::{Array2, ArrayView, RemoveAxis};
+use ndarray::{Array1, Array2, ArrayView, ArrayView1, Axis, Dimension, Ix1, RemoveAxis};
 use smallvec::SmallVec;
 
 use super::CsrNonCanonical;
@@ -110,6 +113,289 @@ impl<B: Backend, T: BackendData> ExtendableDataset<B, T> {
     }
 }
 
+/// Append `data` to an existing dataset along `axis`, growing the dataset in place.
+/// The other dimensions of `data` must match those of the dataset.
+pub(crate) fn append_to_dataset<B, T, D>(
+    dataset: &mut B::Dataset,
+    axis: usize,
+    data: ArrayView<'_, T, D>,
+) -> Result<()>
+where
+    B: Backend,
+    T: BackendData,
+    D: Dimension,
+{
+    let mut shape = dataset.shape();
+    ensure!(
+        shape.ndim() == data.ndim()
+            && shape
+                .as_ref()
+                .iter()
+                .zip(data.shape())
+                .enumerate()
+                .all(|(i, (x, y))| i == axis || x == y),
+        "cannot append data of shape {:?} to a dataset of shape {} along axis {}",
+        data.shape(),
+        shape,
+        axis,
+    );
+    let n = data.len_of(Axis(axis));
+    if n == 0 {
+        return Ok(());
+    }
+    let start = shape[axis];
+    shape[axis] += n;
+    dataset.reshape(&shape).with_context(|| {
+        format!(
+            "cannot resize the dataset to {}, datasets with a contiguous layout have a fixed shape",
+            shape
+        )
+    })?;
+    if !data.is_empty() {
+        let slice: SmallVec<[SelectInfoElem; 3]> = (0..shape.ndim())
+            .map(|i| {
+                if i == axis {
+                    (start..shape[i]).into()
+                } else {
+                    SelectInfoElem::full()
+                }
+            })
+            .collect();
+        dataset.write_array_slice(data.into(), slice.as_ref())?;
+    }
+    Ok(())
+}
+
+/// Check that `max` can be stored in an index dataset of type `ty`.
+fn check_index_type(ty: ScalarType, max: usize) -> Result<()> {
+    let fits = match ty {
+        ScalarType::I32 => i32::try_from(max).is_ok(),
+        ScalarType::I64 => i64::try_from(max).is_ok(),
+        ScalarType::U32 => u32::try_from(max).is_ok(),
+        ScalarType::U64 => u64::try_from(max).is_ok(),
+        ty => bail!("unsupported type for sparse indices: {}", ty),
+    };
+    ensure!(fits, "sparse index {} cannot be stored as {}", max, ty);
+    Ok(())
+}
+
+/// Write indices to a 1-dimensional index dataset from position `start`, using
+/// the integer type of the dataset.
+fn write_index<B: Backend>(dataset: &B::Dataset, start: usize, values: &[usize]) -> Result<()> {
+    if values.is_empty() {
+        return Ok(());
+    }
+    let slice = [SelectInfoElem::from(start..start + values.len())];
+    macro_rules! fun {
+        ($ty:ty) => {{
+            let arr: Array1<$ty> = values.iter().map(|x| *x as $ty).collect();
+            dataset.write_array_slice(arr.into(), &slice)
+        }};
+    }
+    match dataset.dtype()? {
+        ScalarType::I32 => fun!(i32),
+        ScalarType::I64 => fun!(i64),
+        ScalarType::U32 => fun!(u32),
+        ScalarType::U64 => fun!(u64),
+        ty => bail!("unsupported type for sparse indices: {}", ty),
+    }
+}
+
+/// Append major lanes, i.e., rows of a csr matrix or columns of a csc matrix, to
+/// the compressed sparse matrix stored in `container`. The arrays are grown in
+/// place and the `indptr` and `indices` arrays keep their integer type.
+pub(crate) fn append_cs_major<B, T>(
+    container: &mut DataContainer<B>,
+    major_axis: usize,
+    len_minor: usize,
+    offsets: &[usize],
+    indices: &[usize],
+    data: &[T],
+) -> Result<()>
+where
+    B: Backend,
+    T: BackendData,
+{
+    let group = container.as_group()?;
+    let mut shape: Vec<usize> = group.get_attr("shape")?;
+    ensure!(
+        shape[1 - major_axis] == len_minor,
+        "cannot append data of length {} along axis {} to a sparse matrix of shape {:?}",
+        len_minor,
+        1 - major_axis,
+        shape,
+    );
+    let mut data_ds = group.open_dataset("data")?;
+    let mut indices_ds = group.open_dataset("indices")?;
+    let mut indptr_ds = group.open_dataset("indptr")?;
+    let nnz = data_ds.shape()[0];
+    let len_indptr = indptr_ds.shape()[0];
+
+    // Check the index types before anything is written.
+    check_index_type(indptr_ds.dtype()?, nnz + data.len())?;
+    check_index_type(indices_ds.dtype()?, len_minor.saturating_sub(1))?;
+
+    append_to_dataset::<B, _, _>(&mut data_ds, 0, ArrayView1::from(data))?;
+    indices_ds.reshape(&(nnz + indices.len()).into())?;
+    write_index::<B>(&indices_ds, nnz, indices)?;
+    let indptr: Vec<usize> = offsets[1..].iter().map(|x| x + nnz).collect();
+    indptr_ds.reshape(&(len_indptr + indptr.len()).into())?;
+    write_index::<B>(&indptr_ds, len_indptr, &indptr)?;
+
+    shape[major_axis] += offsets.len() - 1;
+    let shape: Vec<u64> = shape.into_iter().map(|x| x as u64).collect();
+    container.new_attr("shape", shape.as_slice())
+}
+
+/// Append minor lanes, i.e., columns of a csr matrix or rows of a csc matrix, to
+/// the compressed sparse matrix stored in `container`. `indices` are the minor
+/// indices of the new lanes, starting from 0.
+///
+/// Every major lane receives new entries, so the `data` and `indices` arrays are
+/// rewritten in place. Entries only move towards the end of the arrays, so the
+/// lanes are moved in chunks starting from the last one, without loading the
+/// whole matrix.
+pub(crate) fn append_cs_minor<B, T>(
+    container: &mut DataContainer<B>,
+    major_axis: usize,
+    len_minor: usize,
+    offsets: &[usize],
+    indices: &[usize],
+    data: &[T],
+) -> Result<()>
+where
+    B: Backend,
+    T: BackendData,
+{
+    const CHUNK_SIZE: usize = 10000;
+
+    let group = container.as_group()?;
+    let mut shape: Vec<usize> = group.get_attr("shape")?;
+    let len_major = shape[major_axis];
+    let old_len_minor = shape[1 - major_axis];
+    ensure!(
+        offsets.len() == len_major + 1,
+        "cannot append data of length {} along axis {} to a sparse matrix of shape {:?}",
+        offsets.len().saturating_sub(1),
+        major_axis,
+        shape,
+    );
+    let mut data_ds = group.open_dataset("data")?;
+    let mut indices_ds = group.open_dataset("indices")?;
+    let indptr_ds = group.open_dataset("indptr")?;
+    let old_offsets: Vec<usize> = indptr_ds
+        .read_array_cast::<_, Ix1>()?
+        .into_raw_vec_and_offset()
+        .0;
+    let nnz = old_offsets[len_major] + data.len();
+
+    // Check the index types before anything is written.
+    check_index_type(indptr_ds.dtype()?, nnz)?;
+    check_index_type(indices_ds.dtype()?, (old_len_minor + len_minor).saturating_sub(1))?;
+
+    data_ds.reshape(&nnz.into())?;
+    indices_ds.reshape(&nnz.into())?;
+    let mut end = len_major;
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK_SIZE);
+        let (lo, hi) = (old_offsets[start], old_offsets[end]);
+        let (chunk_data, chunk_indices): (Vec<T>, Vec<usize>) = if lo < hi {
+            let slice = [SelectInfoElem::from(lo..hi)];
+            (
+                data_ds
+                    .read_array_slice::<T, _, Ix1>(&slice)?
+                    .into_raw_vec_and_offset()
+                    .0,
+                indices_ds
+                    .read_array_slice_cast::<usize, Ix1, _>(&slice)?
+                    .into_raw_vec_and_offset()
+                    .0,
+            )
+        } else {
+            (Vec::new(), Vec::new())
+        };
+
+        let new_lo = lo + offsets[start];
+        let new_hi = hi + offsets[end];
+        let mut new_data = Vec::with_capacity(new_hi - new_lo);
+        let mut new_indices = Vec::with_capacity(new_hi - new_lo);
+        (start..end).for_each(|i| {
+            let (a, b) = (old_offsets[i] - lo, old_offsets[i + 1] - lo);
+            new_data.extend_from_slice(&chunk_data[a..b]);
+            new_indices.extend_from_slice(&chunk_indices[a..b]);
+            let (a, b) = (offsets[i], offsets[i + 1]);
+            new_data.extend_from_slice(&data[a..b]);
+            new_indices.extend(indices[a..b].iter().map(|x| x + old_len_minor));
+        });
+        if new_lo < new_hi {
+            let slice = [SelectInfoElem::from(new_lo..new_hi)];
+            data_ds.write_array_slice(Array1::from(new_data).into(), &slice)?;
+            write_index::<B>(&indices_ds, new_lo, &new_indices)?;
+        }
+        end = start;
+    }
+    let new_offsets: Vec<usize> = old_offsets
+        .iter()
+        .zip(offsets)
+        .map(|(x, y)| x + y)
+        .collect();
+    write_index::<B>(&indptr_ds, 0, &new_offsets)?;
+
+    shape[1 - major_axis] += len_minor;
+    let shape: Vec<u64> = shape.into_iter().map(|x| x as u64).collect();
+    container.new_attr("shape", shape.as_slice())
+}
+
+/// Write the `indptr` and `indices` arrays of a compressed sparse matrix using
+/// the integer type chosen by `config.index_width`.
+/// - len_minor: number of columns/rows of the csr/csc matrix
+pub(crate) fn write_cs_indices<B, G>(
+    group: &G,
+    offsets: &[usize],
+    indices: &[usize],
+    len_minor: usize,
+    config: &WriteConfig,
+) -> Result<()>
+where
+    B: Backend,
+    G: GroupOp<B>,
+{
+    let nnz = offsets.last().copied().unwrap_or(0);
+    let fits_i32 = i32::try_from(nnz).is_ok() && i32::try_from(len_minor.saturating_sub(1)).is_ok();
+    let use_i32 = match config.index_width {
+        IndexWidth::Auto => fits_i32,
+        IndexWidth::I32 => {
+            ensure!(
+                fits_i32,
+                "cannot store a sparse matrix with {} non-zeros and minor dimension {} using i32 indices",
+                nnz,
+                len_minor
+            );
+            true
+        }
+        IndexWidth::I64 => false,
+    };
+    let index_config = || config.flattened();
+    if use_i32 {
+        let indptr: Vec<i32> = offsets.iter().map(|x| *x as i32).collect();
+        let indices: Vec<i32> = indices.iter().map(|x| *x as i32).collect();
+        group.new_array_dataset("indptr", indptr.into(), index_config())?;
+        group.new_array_dataset("indices", indices.into(), index_config())?;
+    } else {
+        let indptr: Vec<i64> = offsets
+            .iter()
+            .map(|x| i64::try_from(*x))
+            .collect::<Result<_, _>>()?;
+        let indices: Vec<i64> = indices
+            .iter()
+            .map(|x| i64::try_from(*x))
+            .collect::<Result<_, _>>()?;
+        group.new_array_dataset("indptr", indptr.into(), index_config())?;
+        group.new_array_dataset("indices", indices.into(), index_config())?;
+    }
+    Ok(())
+}
+
 /// select rows of csr_matrix, or columns of csc_matrix
 /// - major_indices: row_indices/col_indices of csr/csc matrix
 /// - offset: indptr
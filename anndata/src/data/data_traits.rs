@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::backend::{Backend, DataContainer, GroupOp, AttributeOp, DataType};
+use crate::backend::{Backend, DataContainer, GroupOp, AttributeOp, DataType, WriteConfig};
 use crate::data::{
     array::slice::{SelectInfoElem, Shape},
     array::DynScalar,
@@ -80,6 +80,17 @@ pub trait Writable: Element {
         name: &str,
     ) -> Result<DataContainer<B>>;
 
+    /// Write the data using the given configuration. Data types that do not
+    /// make use of the configuration fall back to `write`.
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        _config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        self.write(location, name)
+    }
+
     /// Overwrite the data in the container. The default implementation deletes the 
     /// container and creates a new one. The data is then written to the new container.
     /// Specialized implementations may choose to overwrite the data in place.
@@ -104,6 +115,15 @@ where
     ) -> Result<DataContainer<B>> {
             (*self).write(location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        (*self).write_with_config(location, name, config)
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
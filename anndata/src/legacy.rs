@@ -0,0 +1,390 @@
+//! Reading files written by anndata < 0.7.
+//!
+//! Old versions of anndata stored dataframes and `obsm`/`varm` as compound
+//! (record) arrays, kept the categories of categorical columns in
+//! `uns['<column>_categories']`, identified sparse matrices only by the
+//! `h5sparse_format` attribute, and stored the raw slot as `raw.X`, `raw.var`
+//! and `raw.varm`. [`read_legacy`] converts such files to the current
+//! representations and reports the conversions it applied.
+
+use crate::{
+    backend::{AttributeOp, Backend, DataContainer, DataType, DatasetOp, GroupOp},
+    data::{ArrayConvert, CategoricalArray, DataFrameIndex, DynArray, Mapping},
+    AnnDataOp, ArrayData, AxisArraysOp, Data, ElemCollectionOp, RawData, Readable,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use nalgebra_sparse::{CscMatrix, CsrMatrix};
+use ndarray::Array1;
+use polars::prelude::{DataFrame, Series};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A conversion applied by [`read_legacy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conversion {
+    /// A compound (record) array was converted to a dataframe, or to one array
+    /// per field for `obsm` and `varm`.
+    RecordArray { path: String },
+    /// The integer codes of a dataframe column were combined with the
+    /// categories stored in `uns` into a categorical column.
+    UnsCategories { column: String, categories: String },
+    /// A sparse matrix without encoding metadata was read using its
+    /// `h5sparse_format` attribute.
+    H5Sparse { path: String },
+    /// `raw.X`, `raw.var` and `raw.varm` were moved to the raw slot.
+    FlatRaw,
+}
+
+impl fmt::Display for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conversion::RecordArray { path } => write!(f, "{}: record array", path),
+            Conversion::UnsCategories { column, categories } => {
+                write!(f, "{}: categories from {}", column, categories)
+            }
+            Conversion::H5Sparse { path } => write!(f, "{}: h5sparse matrix", path),
+            Conversion::FlatRaw => write!(f, "raw: flat raw.X/raw.var/raw.varm layout"),
+        }
+    }
+}
+
+/// The list of conversions applied by [`read_legacy`]. It is empty if the file
+/// did not contain any legacy element.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LegacyReport {
+    pub conversions: Vec<Conversion>,
+}
+
+impl LegacyReport {
+    pub fn is_empty(&self) -> bool {
+        self.conversions.is_empty()
+    }
+
+    fn push(&mut self, conversion: Conversion) {
+        self.conversions.push(conversion);
+    }
+}
+
+impl fmt::Display for LegacyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.conversions
+            .iter()
+            .try_for_each(|x| writeln!(f, "{}", x))
+    }
+}
+
+/// Read `file`, which may use the legacy layout, and write its content to
+/// `output`. Elements that already use the current encodings are copied
+/// unchanged. The input file is not modified.
+pub fn read_legacy<B: Backend, O: AnnDataOp>(file: &B::Store, output: &O) -> Result<LegacyReport> {
+    let mut report = LegacyReport::default();
+    let mut uns = if file.exists("uns")? {
+        read_mapping::<B>(&file.open_group("uns")?, "uns", &mut report)?
+    } else {
+        HashMap::new()
+    };
+
+    if file.exists("X")? {
+        output.set_x(read_array(DataContainer::<B>::open(file, "X")?, "X", &mut report)?)?;
+    }
+    if file.exists("obs")? {
+        let container = DataContainer::<B>::open(file, "obs")?;
+        let (index, obs) = read_dataframe(container, "obs", &mut uns, &mut report)?;
+        output.set_obs_names(index)?;
+        output.set_obs(obs)?;
+    }
+    if file.exists("var")? {
+        let container = DataContainer::<B>::open(file, "var")?;
+        let (index, var) = read_dataframe(container, "var", &mut uns, &mut report)?;
+        output.set_var_names(index)?;
+        output.set_var(var)?;
+    }
+
+    read_arrays::<B, _>(file, "obsm", &mut report)?
+        .into_iter()
+        .try_for_each(|(k, v)| output.obsm().add(&k, v))?;
+    read_arrays::<B, _>(file, "obsp", &mut report)?
+        .into_iter()
+        .try_for_each(|(k, v)| output.obsp().add(&k, v))?;
+    read_arrays::<B, _>(file, "varm", &mut report)?
+        .into_iter()
+        .try_for_each(|(k, v)| output.varm().add(&k, v))?;
+    read_arrays::<B, _>(file, "varp", &mut report)?
+        .into_iter()
+        .try_for_each(|(k, v)| output.varp().add(&k, v))?;
+    read_arrays::<B, _>(file, "layers", &mut report)?
+        .into_iter()
+        .try_for_each(|(k, v)| output.layers().add(&k, v))?;
+
+    if let Some(raw) = read_raw::<B>(file, &mut uns, &mut report)? {
+        output.set_raw(raw)?;
+    }
+
+    uns.into_iter()
+        .try_for_each(|(k, v)| output.uns().add(&k, v))?;
+    Ok(report)
+}
+
+fn read_raw<B: Backend>(
+    file: &B::Store,
+    uns: &mut HashMap<String, Data>,
+    report: &mut LegacyReport,
+) -> Result<Option<RawData>> {
+    let (x, var, varm) = if file.exists("raw")? {
+        let group = file.open_group("raw")?;
+        if !group.exists("X")? {
+            return Ok(None);
+        }
+        let x = read_array(DataContainer::<B>::open(&group, "X")?, "raw/X", report)?;
+        let var = if group.exists("var")? {
+            let container = DataContainer::<B>::open(&group, "var")?;
+            Some(read_dataframe(container, "raw/var", uns, report)?)
+        } else {
+            None
+        };
+        let varm = read_arrays::<B, _>(&group, "varm", report)?;
+        (x, var, varm)
+    } else if file.exists("raw.X")? {
+        report.push(Conversion::FlatRaw);
+        let x = read_array(DataContainer::<B>::open(file, "raw.X")?, "raw.X", report)?;
+        let var = if file.exists("raw.var")? {
+            let container = DataContainer::<B>::open(file, "raw.var")?;
+            Some(read_dataframe(container, "raw.var", uns, report)?)
+        } else {
+            None
+        };
+        let varm = read_arrays::<B, _>(file, "raw.varm", report)?;
+        (x, var, varm)
+    } else {
+        return Ok(None);
+    };
+
+    let mut raw = RawData::new(x);
+    if let Some((var_names, var)) = var {
+        raw.var_names = var_names;
+        raw.var = var;
+    }
+    raw.varm = varm.into_iter().collect();
+    Ok(Some(raw))
+}
+
+/// Read a matrix, including sparse matrices that predate the encoding metadata.
+fn read_array<B: Backend>(
+    container: DataContainer<B>,
+    path: &str,
+    report: &mut LegacyReport,
+) -> Result<ArrayData> {
+    let format = match &container {
+        DataContainer::Group(group) if group.get_attr::<String>("encoding-type").is_err() => {
+            group.get_attr::<String>("h5sparse_format").ok()
+        }
+        _ => None,
+    };
+    let Some(format) = format else {
+        return match read_unencoded(&container)? {
+            Some(array) => Ok(array.into()),
+            None => ArrayData::read(&container),
+        };
+    };
+
+    report.push(Conversion::H5Sparse { path: path.to_string() });
+    let group = container.as_group()?;
+    let shape: Vec<usize> = group.get_attr("h5sparse_shape")?;
+    ensure!(shape.len() == 2, "h5sparse_shape of {} must have length 2", path);
+    let data = group.open_dataset("data")?.read_dyn_array()?;
+    let indices: Array1<usize> = group.open_dataset("indices")?.read_array_cast()?;
+    let indptr: Array1<usize> = group.open_dataset("indptr")?.read_array_cast()?;
+    let indices = indices.into_raw_vec_and_offset().0;
+    let indptr = indptr.into_raw_vec_and_offset().0;
+
+    macro_rules! fun {
+        ($variant:ident, $data:expr) => {{
+            let values = $data.into_raw_vec_and_offset().0;
+            match format.as_str() {
+                "csr" => CsrMatrix::try_from_csr_data(shape[0], shape[1], indptr, indices, values)
+                    .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path, e))?
+                    .into(),
+                "csc" => CscMatrix::try_from_csc_data(shape[0], shape[1], indptr, indices, values)
+                    .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path, e))?
+                    .into(),
+                f => bail!("unknown h5sparse_format '{}' in {}", f, path),
+            }
+        }};
+    }
+    Ok(crate::macros::dyn_map!(data, DynArray, fun))
+}
+
+/// Read a dataframe. Record arrays are converted to dataframes, and integer
+/// columns whose categories are found in `uns` become categorical columns.
+/// The categories are removed from `uns`.
+fn read_dataframe<B: Backend>(
+    container: DataContainer<B>,
+    path: &str,
+    uns: &mut HashMap<String, Data>,
+    report: &mut LegacyReport,
+) -> Result<(DataFrameIndex, DataFrame)> {
+    let (index, columns) = if let Some(records) = read_records(&container)? {
+        report.push(Conversion::RecordArray { path: path.to_string() });
+        let mut index = None;
+        let mut columns = Vec::new();
+        for (name, values) in records {
+            if name == "index" {
+                let values: Array1<String> = values.try_convert()?;
+                index = Some(values.to_vec().into());
+            } else {
+                columns.push(to_series(&name, values)?);
+            }
+        }
+        let n = columns.first().map_or(0, |x| x.len());
+        (index.unwrap_or(n.into()), columns)
+    } else {
+        let df = DataFrame::read(&container)?;
+        let index = DataFrameIndex::read(&container)?;
+        (index, df.take_columns().into_iter().map(|x| x.take_materialized_series()).collect())
+    };
+
+    let df = columns
+        .into_iter()
+        .map(|series| {
+            let key = format!("{}_categories", series.name());
+            if !series.dtype().is_integer() || !uns.contains_key(&key) {
+                return Ok(series);
+            }
+            let categories = match uns.remove(&key).unwrap() {
+                Data::ArrayData(ArrayData::Array(x)) => x.try_convert()?,
+                _ => bail!("uns['{}'] is not an array", key),
+            };
+            report.push(Conversion::UnsCategories {
+                column: format!("{}/{}", path, series.name()),
+                categories: format!("uns/{}", key),
+            });
+            to_categorical(&series, categories)
+        })
+        .collect::<Result<DataFrame>>()?;
+    Ok((index, df))
+}
+
+fn to_categorical(codes: &Series, categories: Array1<String>) -> Result<Series> {
+    let n = categories.len();
+    let name = codes.name().clone();
+    let codes = codes
+        .cast(&polars::datatypes::DataType::Int64)?
+        .i64()?
+        .into_iter()
+        .map(|x| match x {
+            Some(c) if c >= 0 => {
+                ensure!((c as usize) < n, "category code {} of column {} is out of range", c, name);
+                Ok(Some(c as u32))
+            }
+            _ => Ok(None),
+        })
+        .collect::<Result<Array1<_>>>()?;
+    let series: Series = CategoricalArray {
+        codes: codes.into_dyn(),
+        categories,
+        ordered: false,
+    }
+    .into();
+    Ok(series.with_name(name))
+}
+
+/// Read the arrays stored in `name`, which is either a group or, in old files,
+/// a record array with one field per array.
+fn read_arrays<B: Backend, G: GroupOp<B>>(
+    location: &G,
+    name: &str,
+    report: &mut LegacyReport,
+) -> Result<Vec<(String, ArrayData)>> {
+    if !location.exists(name)? {
+        return Ok(Vec::new());
+    }
+    let container = DataContainer::<B>::open(location, name)?;
+    if let Some(records) = read_records(&container)? {
+        report.push(Conversion::RecordArray { path: name.to_string() });
+        Ok(records.into_iter().map(|(k, v)| (k, v.into())).collect())
+    } else {
+        let group = container.as_group()?;
+        group
+            .list()?
+            .into_iter()
+            .map(|key| {
+                let path = format!("{}/{}", name, key);
+                let data = read_array(DataContainer::<B>::open(group, &key)?, &path, report)?;
+                Ok((key, data))
+            })
+            .collect()
+    }
+}
+
+fn read_mapping<B: Backend>(
+    group: &B::Group,
+    path: &str,
+    report: &mut LegacyReport,
+) -> Result<HashMap<String, Data>> {
+    group
+        .list()?
+        .into_iter()
+        .map(|key| {
+            let path = format!("{}/{}", path, key);
+            let container = DataContainer::<B>::open(group, &key)?;
+            let data = if let Some(records) = read_records(&container)? {
+                report.push(Conversion::RecordArray { path });
+                records
+                    .into_iter()
+                    .map(|(k, v)| to_series(&k, v))
+                    .collect::<Result<DataFrame>>()?
+                    .into()
+            } else if let Some(array) = read_unencoded(&container)? {
+                ArrayData::from(array).into()
+            } else if let DataType::Mapping = container.encoding_type()? {
+                Mapping::from(read_mapping::<B>(container.as_group()?, &path, report)?).into()
+            } else {
+                Data::read(&container).with_context(|| format!("cannot read {}", path))?
+            };
+            Ok((key, data))
+        })
+        .collect()
+}
+
+/// Old files stored arrays without encoding metadata, which would otherwise be
+/// mistaken for scalars.
+fn read_unencoded<B: Backend>(container: &DataContainer<B>) -> Result<Option<DynArray>> {
+    match container {
+        DataContainer::Dataset(dataset)
+            if dataset.get_attr::<String>("encoding-type").is_err()
+                && dataset.shape().ndim() > 0 =>
+        {
+            Ok(Some(dataset.read_dyn_array()?))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn read_records<B: Backend>(container: &DataContainer<B>) -> Result<Option<Vec<(String, DynArray)>>> {
+    let DataContainer::Dataset(dataset) = container else {
+        return Ok(None);
+    };
+    let Some(fields) = dataset.record_fields()? else {
+        return Ok(None);
+    };
+    fields
+        .into_iter()
+        .map(|name| {
+            let values = dataset.read_record_field(&name)?;
+            Ok((name, values))
+        })
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+fn to_series(name: &str, values: DynArray) -> Result<Series> {
+    ensure!(
+        values.ndim() == 1,
+        "field '{}' cannot be converted to a column as it has {} dimensions",
+        name,
+        values.ndim()
+    );
+    let series = Series::try_from(values)?;
+    Ok(series.with_name(name.into()))
+}
@@ -5,10 +5,14 @@ pub mod backend;
 pub mod data;
 pub mod container;
 pub mod reader;
+pub mod legacy;
 mod macros;
 
 pub use traits::{AnnDataOp, AxisArraysOp, ElemCollectionOp, ArrayElemOp};
-pub use crate::anndata::{AnnData, AnnDataSet, StackedAnnData};
+pub use crate::anndata::{
+    AnnData, AnnDataSet, AnnDataView, ObsData, Raw, RawData, StackedAnnData, VarData,
+    ViewArrayElem, ViewAxisArrays, ViewChunkedArrayElem,
+};
 pub use backend::Backend;
 pub use data::{HasShape, Data, Readable, Writable, ArrayData, WritableArray, ReadableArray, Selectable};
 pub use container::{
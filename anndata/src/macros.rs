@@ -9,8 +9,11 @@ macro_rules! dyn_match {
             $enum::U16 => $inner_macro!(u16),
             $enum::U32 => $inner_macro!(u32),
             $enum::U64 => $inner_macro!(u64),
+            $enum::F16 => $inner_macro!(f16),
             $enum::F32 => $inner_macro!(f32),
             $enum::F64 => $inner_macro!(f64),
+            $enum::Complex32 => $inner_macro!(Complex32),
+            $enum::Complex64 => $inner_macro!(Complex64),
             $enum::Bool => $inner_macro!(bool),
             $enum::String => $inner_macro!(String),
         }
@@ -28,8 +31,11 @@ macro_rules! dyn_map {
             $enum::U16(_val) => $inner_macro!(U16, _val),
             $enum::U32(_val) => $inner_macro!(U32, _val),
             $enum::U64(_val) => $inner_macro!(U64, _val),
+            $enum::F16(_val) => $inner_macro!(F16, _val),
             $enum::F32(_val) => $inner_macro!(F32, _val),
             $enum::F64(_val) => $inner_macro!(F64, _val),
+            $enum::Complex32(_val) => $inner_macro!(Complex32, _val),
+            $enum::Complex64(_val) => $inner_macro!(Complex64, _val),
             $enum::Bool(_val) => $inner_macro!(Bool, _val),
             $enum::String(_val) => $inner_macro!(String, _val),
         }
@@ -47,8 +53,11 @@ macro_rules! dyn_map_fun {
             $enum::U16(_val) => _val.$fun($($arg),*),
             $enum::U32(_val) => _val.$fun($($arg),*),
             $enum::U64(_val) => _val.$fun($($arg),*),
+            $enum::F16(_val) => _val.$fun($($arg),*),
             $enum::F32(_val) => _val.$fun($($arg),*),
             $enum::F64(_val) => _val.$fun($($arg),*),
+            $enum::Complex32(_val) => _val.$fun($($arg),*),
+            $enum::Complex64(_val) => _val.$fun($($arg),*),
             $enum::Bool(_val) => _val.$fun($($arg),*),
             $enum::String(_val) => _val.$fun($($arg),*),
         }
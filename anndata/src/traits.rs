@@ -1,5 +1,5 @@
 use crate::{
-    anndata::{new_layers, new_mapping, new_obsm, new_obsp, new_varm, new_varp},
+    anndata::{new_layers, new_mapping, new_obsm, new_obsp, new_varm, new_varp, InnerRaw, Raw, RawData},
     backend::DataType,
     container::{ChunkedArrayElem, InnerDataFrameElem, StackedChunkedArrayElem},
     data::*,
@@ -10,6 +10,7 @@ use crate::{
 use anyhow::{bail, ensure, Context, Result};
 use polars::prelude::DataFrame;
 use smallvec::SmallVec;
+use std::collections::HashMap;
 
 /// Trait defining operations on an AnnData container.
 pub trait AnnDataOp {
@@ -166,6 +167,43 @@ pub trait AnnDataOp {
     fn del_varp(&self) -> Result<()>;
     /// Deletes the layers.
     fn del_layers(&self) -> Result<()>;
+
+    /// Reads the 'raw' slot. Returns `None` if the slot is empty.
+    fn get_raw(&self) -> Result<Option<RawData>>;
+    /// Sets the 'raw' slot. The number of rows of the raw matrix must be
+    /// equal to the number of observations.
+    fn set_raw(&self, raw: RawData) -> Result<()>;
+    /// Deletes the 'raw' slot.
+    fn del_raw(&self) -> Result<()>;
+
+    /// Reads the variable names and the variables metadata of the 'raw' slot,
+    /// without its data matrix. Returns `None` if the slot is empty.
+    fn get_raw_var(&self) -> Result<Option<(DataFrameIndex, DataFrame)>> {
+        Ok(self.get_raw()?.map(|raw| (raw.var_names, raw.var)))
+    }
+    /// Reads the data matrix of the 'raw' slot. Returns `None` if the slot is empty.
+    fn read_raw_x(&self) -> Result<Option<ArrayData>> {
+        Ok(self.get_raw()?.map(|raw| raw.x))
+    }
+    /// Reads the rows of the data matrix of the 'raw' slot selected by `slice`.
+    /// Returns `None` if the slot is empty.
+    fn read_raw_x_slice<S: AsRef<SelectInfoElem>>(&self, slice: S) -> Result<Option<ArrayData>> {
+        Ok(self.read_raw_x()?.map(|x| x.select_axis(0, slice)))
+    }
+    /// Sets the 'raw' slot, writing its data matrix chunk by chunk.
+    fn set_raw_from_iter<I, D>(&self, iter: I, var_names: DataFrameIndex, var: DataFrame) -> Result<()>
+    where
+        I: Iterator<Item = D>,
+        D: ArrayChunk + Into<ArrayData>,
+    {
+        let x = ArrayData::vstack(iter.map(Into::into))?;
+        self.set_raw(RawData {
+            x,
+            var,
+            var_names,
+            varm: HashMap::new(),
+        })
+    }
 }
 
 impl<T: AnnDataOp> AnnDataOp for &T {
@@ -352,6 +390,38 @@ impl<T: AnnDataOp> AnnDataOp for &T {
     {
         (*self).set_layers(data)
     }
+
+    fn get_raw(&self) -> Result<Option<RawData>> {
+        (*self).get_raw()
+    }
+
+    fn set_raw(&self, raw: RawData) -> Result<()> {
+        (*self).set_raw(raw)
+    }
+
+    fn del_raw(&self) -> Result<()> {
+        (*self).del_raw()
+    }
+
+    fn get_raw_var(&self) -> Result<Option<(DataFrameIndex, DataFrame)>> {
+        (*self).get_raw_var()
+    }
+
+    fn read_raw_x(&self) -> Result<Option<ArrayData>> {
+        (*self).read_raw_x()
+    }
+
+    fn read_raw_x_slice<S: AsRef<SelectInfoElem>>(&self, slice: S) -> Result<Option<ArrayData>> {
+        (*self).read_raw_x_slice(slice)
+    }
+
+    fn set_raw_from_iter<I, D>(&self, iter: I, var_names: DataFrameIndex, var: DataFrame) -> Result<()>
+    where
+        I: Iterator<Item = D>,
+        D: ArrayChunk + Into<ArrayData>,
+    {
+        (*self).set_raw_from_iter(iter, var_names, var)
+    }
 }
 
 
@@ -367,11 +437,12 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
     fn set_x_from_iter<I: Iterator<Item = D>, D: ArrayChunk>(&self, iter: I) -> Result<()> {
         let mut obs_lock = self.n_obs.lock();
         let mut vars_lock = self.n_vars.lock();
+        self.mark_dirty("set_x_from_iter")?;
         self.del_x()?;
         let new_elem = ArrayElem::try_from(ArrayChunk::write_by_chunk(iter, &self.file, "X")?)?;
         let shape = new_elem.inner().shape().clone();
 
-        match obs_lock.try_set(shape[0]).and(vars_lock.try_set(shape[1])) {
+        let result = match obs_lock.try_set(shape[0]).and(vars_lock.try_set(shape[1])) {
             Ok(_) => {
                 self.x.swap(&new_elem);
                 Ok(())
@@ -380,7 +451,9 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
                 new_elem.clear()?;
                 Err(e)
             }
-        }
+        };
+        self.mark_clean()?;
+        result
     }
 
     fn set_x<D: Into<ArrayData>>(&self, data: D) -> Result<()> {
@@ -393,13 +466,14 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
         self.n_obs.try_set(shape[0])?;
         self.n_vars.try_set(shape[1])?;
 
+        self.mark_dirty("set_x")?;
         if !self.x.is_none() {
             self.x.inner().save(data)?;
         } else {
             let new_elem = ArrayElem::try_from(data.write(&self.file, "X")?)?;
             self.x.swap(&new_elem);
         }
-        Ok(())
+        self.mark_clean()
     }
 
     fn del_x(&self) -> Result<()> {
@@ -421,6 +495,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
                 && self.obsm().is_empty()
                 && self.obsp().is_empty()
                 && self.layers().is_empty()
+                && self.raw.is_none()
             {
                 n_obs.set(n);
             } else {
@@ -640,6 +715,48 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
     fn del_layers(&self) -> Result<()> {
         self.layers.clear()
     }
+
+    fn get_raw(&self) -> Result<Option<RawData>> {
+        self.raw.lock().as_ref().map(|raw| raw.data()).transpose()
+    }
+    fn set_raw(&self, raw: RawData) -> Result<()> {
+        let staged = Raw::<B>::stage(&self.file, "raw", |location, name| {
+            InnerRaw::new(location, name, &self.n_obs, raw)
+        })?;
+        self.mark_dirty("set_raw")?;
+        self.raw.replace(&self.file, &staged, "raw", &self.n_obs)?;
+        self.mark_clean()
+    }
+    fn del_raw(&self) -> Result<()> {
+        self.mark_dirty("del_raw")?;
+        self.raw.clear()?;
+        self.mark_clean()
+    }
+    fn get_raw_var(&self) -> Result<Option<(DataFrameIndex, DataFrame)>> {
+        self.raw
+            .lock()
+            .as_ref()
+            .map(|raw| Ok((raw.var_names(), raw.read_var()?)))
+            .transpose()
+    }
+    fn read_raw_x(&self) -> Result<Option<ArrayData>> {
+        self.raw.lock().as_ref().map(|raw| raw.read_x()).transpose()
+    }
+    fn read_raw_x_slice<S: AsRef<SelectInfoElem>>(&self, slice: S) -> Result<Option<ArrayData>> {
+        self.raw.lock().as_ref().map(|raw| raw.read_x_slice(slice)).transpose()
+    }
+    fn set_raw_from_iter<I, D>(&self, iter: I, var_names: DataFrameIndex, var: DataFrame) -> Result<()>
+    where
+        I: Iterator<Item = D>,
+        D: ArrayChunk + Into<ArrayData>,
+    {
+        let staged = Raw::<B>::stage(&self.file, "raw", |location, name| {
+            InnerRaw::new_from_iter(location, name, &self.n_obs, iter, var_names, &var)
+        })?;
+        self.mark_dirty("set_raw_from_iter")?;
+        self.raw.replace(&self.file, &staged, "raw", &self.n_obs)?;
+        self.mark_clean()
+    }
 }
 
 impl<B: Backend> AnnDataOp for AnnDataSet<B> {
@@ -791,6 +908,32 @@ impl<B: Backend> AnnDataOp for AnnDataSet<B> {
     fn del_layers(&self) -> Result<()> {
         self.annotation.del_layers()
     }
+
+    fn get_raw(&self) -> Result<Option<RawData>> {
+        self.annotation.get_raw()
+    }
+    fn set_raw(&self, raw: RawData) -> Result<()> {
+        self.annotation.set_raw(raw)
+    }
+    fn del_raw(&self) -> Result<()> {
+        self.annotation.del_raw()
+    }
+    fn get_raw_var(&self) -> Result<Option<(DataFrameIndex, DataFrame)>> {
+        self.annotation.get_raw_var()
+    }
+    fn read_raw_x(&self) -> Result<Option<ArrayData>> {
+        self.annotation.read_raw_x()
+    }
+    fn read_raw_x_slice<S: AsRef<SelectInfoElem>>(&self, slice: S) -> Result<Option<ArrayData>> {
+        self.annotation.read_raw_x_slice(slice)
+    }
+    fn set_raw_from_iter<I, D>(&self, iter: I, var_names: DataFrameIndex, var: DataFrame) -> Result<()>
+    where
+        I: Iterator<Item = D>,
+        D: ArrayChunk + Into<ArrayData>,
+    {
+        self.annotation.set_raw_from_iter(iter, var_names, var)
+    }
 }
 
 /// Trait for operations on element collections.
@@ -933,7 +1076,7 @@ impl<B: Backend> AxisArraysOp for &StackedAxisArrays<B> {
 
 /// Trait for operations on array elements.
 pub trait ArrayElemOp {
-    type ArrayIter<D>: ExactSizeIterator<Item = (D, usize, usize)>
+    type ArrayIter<D>: ExactSizeIterator<Item = Result<(D, usize, usize)>>
     where
         D: TryFrom<ArrayData>,
         <D as TryFrom<ArrayData>>::Error: std::fmt::Debug;
@@ -981,7 +1124,8 @@ pub trait ArrayElemOp {
             .transpose()
     }
 
-    /// Returns an iterator over the data.
+    /// Returns an iterator over chunks of rows. Errors encountered while
+    /// reading a chunk are returned in place of the chunk.
     fn iter<D>(&self, chunk_size: usize) -> Self::ArrayIter<D>
     where
         D: TryFrom<ArrayData>,
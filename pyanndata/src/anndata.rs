@@ -10,7 +10,7 @@ pub use memory::PyAnnData;
 use anndata;
 use anndata::concat::JoinType;
 use anndata::Backend;
-use anndata_hdf5::H5;
+use anndata_hdf5::{ChunkCache, H5Options, H5};
 use anyhow::Result;
 use pyo3::prelude::*;
 use std::{
@@ -22,10 +22,12 @@ use std::{
 pub(crate) fn get_backend<P: AsRef<Path>>(filename: P, backend: Option<&str>) -> &str {
     if let Some(backend) = backend {
         backend
+    } else if let Ok(backend) = anndata_any::detect_backend(&filename) {
+        backend
     } else {
         if let Some(ext) = filename.as_ref().extension() {
             match ext.to_str().unwrap() {
-                "zarr" | "zarrs" => Zarr::NAME,
+                "zarr" | "zarrs" | "zip" => Zarr::NAME,
                 "h5ad" | "h5" | "h5ads" => H5::NAME,
                 _ => H5::NAME,
             }
@@ -48,21 +50,58 @@ pub(crate) fn get_backend<P: AsRef<Path>>(filename: P, backend: Option<&str>) ->
 ///     If `'r+'`, the file is opened in read/write mode.
 ///     If `None`, the AnnData object is read into memory.
 /// backend: Literal['hdf5', 'zarr']
+/// chunk_cache_size: int | None
+///     Size in bytes of the HDF5 raw data chunk cache of each dataset.
+///     Default is 1 MB. Only used by the HDF5 backend.
+/// chunk_cache_slots: int | None
+///     Number of slots in the HDF5 chunk cache. Should be a prime number about
+///     100 times the number of chunks that fit in the cache. Default is 521.
+/// chunk_cache_preemption: float | None
+///     HDF5 chunk cache preemption policy between 0 and 1. Default is 0.75.
+/// page_buffer_size: int | None
+///     Size in bytes of the HDF5 page buffer. Only applicable to files created
+///     with paged file-space aggregation.
 #[pyfunction]
 #[pyo3(
-    signature = (filename, backed="r+", backend=None),
-    text_signature = "(filename, backed='r+', backend=None)",
+    signature = (
+        filename, backed="r+", backend=None, *, chunk_cache_size=None, chunk_cache_slots=None,
+        chunk_cache_preemption=None, page_buffer_size=None,
+    ),
+    text_signature = "(filename, backed='r+', backend=None, *, chunk_cache_size=None, chunk_cache_slots=None, chunk_cache_preemption=None, page_buffer_size=None)",
 )]
+#[allow(clippy::too_many_arguments)]
 pub fn read<'py>(
     py: Python<'py>,
     filename: PathBuf,
     backed: Option<&str>,
     backend: Option<&str>,
+    chunk_cache_size: Option<usize>,
+    chunk_cache_slots: Option<usize>,
+    chunk_cache_preemption: Option<f64>,
+    page_buffer_size: Option<usize>,
 ) -> Result<Bound<'py, PyAny>> {
     let adata = match backed {
         Some(m) => {
             let backend = get_backend(&filename, backend);
-            AnnData::new_from(filename, m, backend)
+            let chunk_cache = if chunk_cache_size.is_some()
+                || chunk_cache_slots.is_some()
+                || chunk_cache_preemption.is_some()
+            {
+                let default = ChunkCache::default();
+                Some(ChunkCache {
+                    nslots: chunk_cache_slots.unwrap_or(default.nslots),
+                    nbytes: chunk_cache_size.unwrap_or(default.nbytes),
+                    w0: chunk_cache_preemption.unwrap_or(default.w0),
+                })
+            } else {
+                None
+            };
+            let options = H5Options {
+                chunk_cache,
+                page_buffer_size,
+                ..Default::default()
+            };
+            AnnData::new_from_with(filename, m, backend, &options)
                 .unwrap()
                 .into_pyobject(py)?
                 .into_any()
@@ -8,7 +8,7 @@ use anndata::container::Slot;
 use anndata::data::{DataFrameIndex, SelectInfoElem, SelectInfoElemBounds};
 use anndata::{self, ArrayElemOp, AxisArraysOp, Data, ElemCollectionOp, Selectable};
 use anndata::{AnnDataOp, ArrayData, Backend};
-use anndata_hdf5::H5;
+use anndata_hdf5::{H5Options, H5};
 use anndata_zarr::Zarr;
 use anyhow::{bail, Result};
 use downcast_rs::{impl_downcast, Downcast};
@@ -92,11 +92,21 @@ impl AnnData {
     }
 
     pub fn new_from(filename: PathBuf, mode: &str, backend: &str) -> Result<Self> {
+        Self::new_from_with(filename, mode, backend, &H5Options::default())
+    }
+
+    /// Same as `new_from`, with options for HDF5 files.
+    pub fn new_from_with(
+        filename: PathBuf,
+        mode: &str,
+        backend: &str,
+        h5_options: &H5Options,
+    ) -> Result<Self> {
         match backend {
             H5::NAME => {
                 let file = match mode {
-                    "r" => H5::open(filename)?,
-                    "r+" => H5::open_rw(filename)?,
+                    "r" => H5::open_with(filename, h5_options)?,
+                    "r+" => H5::open_rw_with(filename, h5_options)?,
                     _ => bail!("Unknown mode: {}", mode),
                 };
                 anndata::AnnData::<H5>::open(file).map(|adata| adata.into())
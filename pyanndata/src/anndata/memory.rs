@@ -7,9 +7,10 @@ use pyo3::exceptions::PyTypeError;
 use pyo3::types::IntoPyDict;
 use pyo3_polars::PyDataFrame;
 use anndata::{self, Selectable, ElemCollectionOp, ArrayElemOp};
-use anndata::{AnnDataOp, AxisArraysOp, ArrayData, Data, Backend, HasShape};
+use anndata::{AnnDataOp, AxisArraysOp, ArrayData, Data, Backend, HasShape, RawData};
 use anndata::data::{ArrayChunk, DataFrameIndex, SelectInfoElem, Shape, Stackable};
-use anyhow::{Result, bail};
+use anyhow::{Result, bail, ensure};
+use std::collections::HashMap;
 
 pub struct PyAnnData<'py>(Bound<'py, PyAny>);
 
@@ -100,6 +101,12 @@ impl<'py> PyAnnData<'py> {
                 .into_iter()
                 .try_for_each(|k| adata.varp().add(&k, inner.varp().get_item::<ArrayData>(&k)?.unwrap()))?;
         }
+        {
+            // Set raw
+            if let Some(raw) = inner.get_raw()? {
+                adata.set_raw(raw)?;
+            }
+        }
         Ok(adata)
     }
 }
@@ -339,6 +346,70 @@ impl<'py> AnnDataOp for PyAnnData<'py> {
         self.0.setattr("layers", None::<PyObject>)?;
         Ok(())
     }
+
+    fn get_raw(&self) -> Result<Option<RawData>> {
+        let raw = self.0.getattr("raw")?;
+        if raw.is_none() {
+            return Ok(None);
+        }
+        let x: ArrayData = raw.getattr("X")?.extract::<PyArrayData>()?.into();
+        let var: PyDataFrame = self.py()
+            .import("polars")?
+            .call_method1("from_pandas", (raw.getattr("var")?,))?
+            .extract()?;
+        let var_names: DataFrameIndex = raw.getattr("var_names")?.extract::<Vec<String>>()?.into();
+        let varm = raw.getattr("varm")?;
+        let varm = varm
+            .call_method0("keys")?
+            .try_iter()?
+            .map(|k| {
+                let k: String = k?.extract()?;
+                let v: ArrayData = varm.call_method1("__getitem__", (k.as_str(),))?.extract::<PyArrayData>()?.into();
+                Ok((k, v))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        Ok(Some(RawData { x, var: var.into(), var_names, varm }))
+    }
+
+    fn set_raw(&self, raw: RawData) -> Result<()> {
+        ensure!(
+            raw.x.shape()[0] == self.n_obs(),
+            "the number of rows of raw X must be equal to n_obs"
+        );
+        let py = self.py();
+        let var_names = raw.var_names.into_vec();
+        let var = if raw.var.width() == 0 {
+            py.import("pandas")?.call_method(
+                "DataFrame",
+                (),
+                Some(&[("index", var_names)].into_py_dict(py)?),
+            )?
+        } else {
+            PyDataFrame(raw.var).into_pyobject(py)?
+                .call_method0("to_pandas")?
+                .call_method1("set_index", (var_names,))?
+        };
+        let varm = raw
+            .varm
+            .into_iter()
+            .map(|(k, v)| (k, PyArrayData::from(v)))
+            .collect::<HashMap<_, _>>();
+        let kwargs = [
+            ("X", PyArrayData::from(raw.x).into_pyobject(py)?.into_any()),
+            ("obs", self.getattr("obs")?),
+            ("var", var),
+            ("varm", varm.into_pyobject(py)?.into_any()),
+        ]
+        .into_py_dict(py)?;
+        let adata = PyModule::import(py, "anndata")?.call_method("AnnData", (), Some(&kwargs))?;
+        self.setattr("raw", adata)?;
+        Ok(())
+    }
+
+    fn del_raw(&self) -> Result<()> {
+        self.0.setattr("raw", None::<PyObject>)?;
+        Ok(())
+    }
 }
 
 pub struct PyArrayIterator<D> {
@@ -367,7 +438,7 @@ where
     D: TryFrom<ArrayData>,
     <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
 {
-    type Item = (D, usize, usize);
+    type Item = Result<(D, usize, usize)>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.current_row >= self.total_rows {
@@ -378,7 +449,11 @@ where
             self.current_row = j;
             let slice = SelectInfoElem::from(i..j);
             let data = self.array.select_axis(0, slice);
-            Some((data.try_into().unwrap(), i, j))
+            Some(
+                data.try_into()
+                    .map(|data| (data, i, j))
+                    .map_err(|e| anyhow::anyhow!("failed to convert rows {}..{}: {:?}", i, j, e)),
+            )
         }
     }
 }
@@ -320,8 +320,11 @@ impl PyChunkedArray {
         slf
     }
 
-    fn __next__(mut slf: PyRefMut<Self>) -> Option<(PyArrayData, usize, usize)> {
-        slf.0.next().map(|(data, start, end)| (data.into(), start, end))
+    fn __next__(mut slf: PyRefMut<Self>) -> Result<Option<(PyArrayData, usize, usize)>> {
+        slf.0
+            .next()
+            .map(|chunk| chunk.map(|(data, start, end)| (data.into(), start, end)))
+            .transpose()
     }
 }
 
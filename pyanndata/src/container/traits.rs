@@ -364,7 +364,7 @@ impl<B: Backend + 'static> ElemCollectionTrait for ElemCollection<B> {
     }
 }
 
-pub trait ChunkedArrayTrait: ExactSizeIterator<Item = (ArrayData, usize, usize)> + Send + Sync {}
+pub trait ChunkedArrayTrait: ExactSizeIterator<Item = Result<(ArrayData, usize, usize)>> + Send + Sync {}
 
 impl<B: Backend> ChunkedArrayTrait for ChunkedArrayElem<B, ArrayData> {}
 impl<B: Backend> ChunkedArrayTrait for StackedChunkedArrayElem<B, ArrayData> {}
\ No newline at end of file
@@ -7,7 +7,7 @@ use pyo3_polars::PyDataFrame;
 pub use slice::{to_select_info, to_select_elem};
 
 use std::{collections::HashMap, ops::Deref};
-use pyo3::{prelude::*, types::PyDict};
+use pyo3::{prelude::*, types::{PyComplex, PyDict}};
 use anndata::data::{Data, ArrayData, DynScalar, Mapping};
 
 pub struct PyArrayData(ArrayData);
@@ -49,6 +49,8 @@ impl<'py> FromPyObject<'py> for PyArrayData {
             Ok(ArrayData::from(ob.extract::<PyDataFrame>()?.0).into())
         } else if isinstance_of_polars(ob)? {
             Ok(ArrayData::from(ob.extract::<PyDataFrame>()?.0).into())
+        } else if isinstance_of_awkward(ob)? {
+            Ok(ArrayData::from(array::to_awkward(ob)?).into())
         } else {
             Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(
                 format!("Could not convert Python type {} to Rust data", ob.get_type())
@@ -69,6 +71,7 @@ impl<'py> IntoPyObject<'py> for PyArrayData {
             ArrayData::Array(arr) => array::arr_to_py(arr, py),
             ArrayData::CsrNonCanonical(csr) => array::csr_noncanonical_to_py(csr, py),
             ArrayData::DataFrame(df) => PyDataFrame(df).into_pyobject(py),
+            ArrayData::AwkwardArray(arr) => array::awkward_to_py(arr, py),
         }
     }
 }
@@ -151,7 +154,10 @@ fn scalar_to_py<'py>(s: DynScalar, py: Python<'py>) -> PyResult<Bound<'py, PyAny
         DynScalar::U32(s) => Ok(s.into_pyobject(py)?.into_any()),
         DynScalar::U64(s) => Ok(s.into_pyobject(py)?.into_any()),
         DynScalar::F32(s) => Ok(s.into_pyobject(py)?.into_any()),
+        DynScalar::F16(s) => Ok(s.to_f32().into_pyobject(py)?.into_any()),
         DynScalar::F64(s) => Ok(s.into_pyobject(py)?.into_any()),
+        DynScalar::Complex32(s) => Ok(PyComplex::from_doubles(py, s.re as f64, s.im as f64).into_any()),
+        DynScalar::Complex64(s) => Ok(PyComplex::from_doubles(py, s.re, s.im).into_any()),
         DynScalar::Bool(s) => Ok(s.into_pyobject(py)?.to_owned().into_any()),
         DynScalar::String(s) => Ok(s.into_pyobject(py)?.into_any()),
     }
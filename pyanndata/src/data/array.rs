@@ -1,6 +1,7 @@
 use crate::data::{isinstance_of_csc, isinstance_of_csr};
 
-use anndata::data::{CsrNonCanonical, DynArray, DynCscMatrix, DynCsrMatrix, DynCsrNonCanonical};
+use anndata::backend::{Complex32, Complex64, f16};
+use anndata::data::{AwkwardArray, CsrNonCanonical, DynArray, DynCscMatrix, DynCsrMatrix, DynCsrNonCanonical};
 use nalgebra_sparse::{CscMatrix, CsrMatrix};
 use ndarray::ArrayD;
 use numpy::{IntoPyArray, PyArrayMethods, PyReadonlyArrayDyn};
@@ -41,6 +42,10 @@ macro_rules! proc_py_numeric {
                 let x: $ty_anno<u64> = $data;
                 x.into()
             }
+            "float16" => {
+                let x: $ty_anno<f16> = $data;
+                x.into()
+            }
             "float32" => {
                 let x: $ty_anno<f32> = $data;
                 x.into()
@@ -49,6 +54,14 @@ macro_rules! proc_py_numeric {
                 let x: $ty_anno<f64> = $data;
                 x.into()
             }
+            "complex64" => {
+                let x: $ty_anno<Complex32> = $data;
+                x.into()
+            }
+            "complex128" => {
+                let x: $ty_anno<Complex64> = $data;
+                x.into()
+            }
             "bool" => {
                 let x: $ty_anno<bool> = $data;
                 x.into()
@@ -252,7 +265,10 @@ pub(super) fn arr_to_py<'py>(arr: DynArray, py: Python<'py>) -> PyResult<Bound<'
         DynArray::U32(arr) => arr.into_pyarray(py).into_any(),
         DynArray::U64(arr) => arr.into_pyarray(py).into_any(),
         DynArray::F32(arr) => arr.into_pyarray(py).into_any(),
+        DynArray::F16(arr) => arr.into_pyarray(py).into_any(),
         DynArray::F64(arr) => arr.into_pyarray(py).into_any(),
+        DynArray::Complex32(arr) => arr.into_pyarray(py).into_any(),
+        DynArray::Complex64(arr) => arr.into_pyarray(py).into_any(),
         DynArray::Bool(arr) => arr.into_pyarray(py).into_any(),
         DynArray::String(_) => todo!(),
     };
@@ -288,7 +304,10 @@ pub(super) fn csr_to_py<'py>(csr: DynCsrMatrix, py: Python<'py>) -> PyResult<Bou
         DynCsrMatrix::U32(csr) => helper(csr, py),
         DynCsrMatrix::U64(csr) => helper(csr, py),
         DynCsrMatrix::F32(csr) => helper(csr, py),
+        DynCsrMatrix::F16(csr) => helper(csr, py),
         DynCsrMatrix::F64(csr) => helper(csr, py),
+        DynCsrMatrix::Complex32(csr) => helper(csr, py),
+        DynCsrMatrix::Complex64(csr) => helper(csr, py),
         DynCsrMatrix::Bool(csr) => helper(csr, py),
         DynCsrMatrix::String(_) => todo!(),
     }
@@ -323,7 +342,10 @@ pub(super) fn csr_noncanonical_to_py<'py>(csr: DynCsrNonCanonical, py: Python<'p
         DynCsrNonCanonical::U32(csr) => helper(csr, py),
         DynCsrNonCanonical::U64(csr) => helper(csr, py),
         DynCsrNonCanonical::F32(csr) => helper(csr, py),
+        DynCsrNonCanonical::F16(csr) => helper(csr, py),
         DynCsrNonCanonical::F64(csr) => helper(csr, py),
+        DynCsrNonCanonical::Complex32(csr) => helper(csr, py),
+        DynCsrNonCanonical::Complex64(csr) => helper(csr, py),
         DynCsrNonCanonical::Bool(csr) => helper(csr, py),
         DynCsrNonCanonical::String(_) => todo!(),
     }
@@ -358,8 +380,37 @@ pub(super) fn csc_to_py<'py>(csc: DynCscMatrix, py: Python<'py>) -> PyResult<Bou
         DynCscMatrix::U32(csc) => helper(csc, py),
         DynCscMatrix::U64(csc) => helper(csc, py),
         DynCscMatrix::F32(csc) => helper(csc, py),
+        DynCscMatrix::F16(csc) => helper(csc, py),
         DynCscMatrix::F64(csc) => helper(csc, py),
+        DynCscMatrix::Complex32(csc) => helper(csc, py),
+        DynCscMatrix::Complex64(csc) => helper(csc, py),
         DynCscMatrix::Bool(csc) => helper(csc, py),
         DynCscMatrix::String(_) => todo!(),
     }
-}
\ No newline at end of file
+}
+pub(super) fn to_awkward(ob: &Bound<'_, PyAny>) -> PyResult<AwkwardArray> {
+    let ak = ob.py().import("awkward")?;
+    let counts: Vec<usize> = ak
+        .call_method1("to_numpy", (ak.call_method1("num", (ob, 1))?,))?
+        .call_method0("tolist")?
+        .extract()?;
+    let values = ak.call_method1("to_numpy", (ak.call_method1("flatten", (ob, 1))?,))?;
+    let offsets = std::iter::once(0)
+        .chain(counts.into_iter().scan(0, |acc, x| {
+            *acc += x;
+            Some(*acc)
+        }))
+        .collect();
+    AwkwardArray::try_new(offsets, to_array(&values)?)
+        .map_err(|e| PyTypeError::new_err(e.to_string()))
+}
+
+pub(super) fn awkward_to_py<'py>(arr: AwkwardArray, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+    let counts: Vec<usize> = arr.offsets().windows(2).map(|x| x[1] - x[0]).collect();
+    let values = match arr.into_parts().1 {
+        DynArray::String(x) => x.into_iter().collect::<Vec<_>>().into_pyobject(py)?.into_any(),
+        x => arr_to_py(x, py)?,
+    };
+    py.import("awkward")?
+        .call_method1("unflatten", (values, counts.into_pyarray(py)))
+}
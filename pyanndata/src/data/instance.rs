@@ -40,6 +40,13 @@ pub fn isinstance_of_pyanndata<'py>(obj: &Bound<'py, PyAny>) -> PyResult<bool> {
     )
 }
 
+pub fn isinstance_of_awkward<'py>(obj: &Bound<'py, PyAny>) -> PyResult<bool> {
+    match obj.py().import("awkward") {
+        Ok(ak) => obj.is_instance(ak.getattr("Array")?.downcast::<PyType>().unwrap()),
+        Err(_) => Ok(false),
+    }
+}
+
 pub fn isinstance_of_pandas<'py>(obj: &Bound<'py, PyAny>) -> PyResult<bool> {
     obj.is_instance(
         obj.py()
@@ -69,6 +69,10 @@ def test_basic(x, tmp_path, backend):
     if backend == "hdf5":
         ad.read_h5ad(output).write(output, compression="gzip")
         read(output, backend='hdf5').close()
+        read(
+            output, backed='r', backend='hdf5', chunk_cache_size=16 * 1024 * 1024,
+            chunk_cache_slots=10007, chunk_cache_preemption=1.0,
+        ).close()
 
 @pytest.mark.parametrize("backend", ["hdf5", "zarr"])
 @given(x=arrays(